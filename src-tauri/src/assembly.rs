@@ -0,0 +1,888 @@
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+/// Larger files are not mod assemblies worth reading
+const MAX_ASSEMBLY_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Identity and mod attributes read from the .NET metadata of a DLL
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssemblyInfo {
+    pub name: String,
+    /// Four-part assembly version, e.g. `1.2.0.0`
+    pub version: String,
+    pub informational_version: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub company: Option<String>,
+    /// From a `[BepInPlugin]` attribute
+    pub plugin: Option<PluginInfo>,
+    /// From a `[SilkMod]` attribute
+    pub silk_mod: Option<SilkModInfo>,
+    pub references: Vec<AssemblyReference>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginInfo {
+    pub guid: String,
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SilkModInfo {
+    pub id: Option<String>,
+    pub name: String,
+    pub authors: Vec<String>,
+    pub version: Option<String>,
+    /// The Silk version the mod was built against
+    pub silk_version: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssemblyReference {
+    pub name: String,
+    pub version: String,
+}
+
+impl AssemblyInfo {
+    /// The id other mods refer to this one by: the Silk mod id, plugin GUID or assembly name
+    pub fn mod_id(&self) -> String {
+        self.silk_mod
+            .as_ref()
+            .and_then(|m| m.id.clone())
+            .or_else(|| self.plugin.as_ref().map(|p| p.guid.clone()))
+            .unwrap_or_else(|| self.name.clone())
+    }
+
+    pub fn mod_name(&self) -> String {
+        self.silk_mod
+            .as_ref()
+            .map(|m| m.name.clone())
+            .or_else(|| self.plugin.as_ref().map(|p| p.name.clone()))
+            .or_else(|| self.title.clone())
+            .unwrap_or_else(|| self.name.clone())
+    }
+
+    /// The declared mod version, falling back to the informational and then the assembly version
+    pub fn mod_version(&self) -> String {
+        self.silk_mod
+            .as_ref()
+            .and_then(|m| m.version.clone())
+            .or_else(|| self.plugin.as_ref().map(|p| p.version.clone()))
+            // Build metadata such as `1.0.0+3f2a1c` is appended by the SDK
+            .or_else(|| {
+                self.informational_version
+                    .as_deref()
+                    .map(|v| v.split('+').next().unwrap_or(v).to_string())
+            })
+            .unwrap_or_else(|| self.version.clone())
+    }
+
+    pub fn mod_author(&self) -> Option<String> {
+        self.silk_mod
+            .as_ref()
+            .map(|m| m.authors.join(", "))
+            .filter(|a| !a.is_empty())
+            .or_else(|| self.company.clone())
+    }
+}
+
+/// Read the metadata of a .NET assembly on disk; `None` for a native DLL
+pub fn read_assembly(path: &Path) -> Result<Option<AssemblyInfo>, String> {
+    let size = fs::metadata(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .len();
+    if size > MAX_ASSEMBLY_SIZE {
+        return Err(format!("{} is too large to be a mod assembly", path.display()));
+    }
+
+    let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    parse_assembly(&data).map_err(|e| format!("Failed to read assembly metadata from {}: {}", path.display(), e))
+}
+
+/// Assemblies already read, with the modification time of the file when it was read
+type ReadCache = HashMap<PathBuf, (SystemTime, Option<AssemblyInfo>)>;
+
+fn read_cache() -> &'static Mutex<ReadCache> {
+    static ASSEMBLIES: OnceLock<Mutex<ReadCache>> = OnceLock::new();
+    ASSEMBLIES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Like [`read_assembly`], but a file is only parsed again once it changes. Files that
+/// can't be read are logged and give `None`.
+pub fn read_assembly_cached(path: &Path) -> Option<AssemblyInfo> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+    if let Some(modified) = modified {
+        let cache = read_cache().lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((_, info)) = cache.get(path).filter(|(read_at, _)| *read_at == modified) {
+            return info.clone();
+        }
+    }
+
+    let info = match read_assembly(path) {
+        Ok(Some(info)) => Some(info),
+        Ok(None) => {
+            debug!("{} is not a .NET assembly", path.display());
+            None
+        }
+        Err(e) => {
+            warn!("{}", e);
+            None
+        }
+    };
+    if let Some(modified) = modified {
+        read_cache()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(path.to_path_buf(), (modified, info.clone()));
+    }
+    info
+}
+
+/// Parse the CLI metadata of a PE image (ECMA-335 partition II, sections 22-25); `None`
+/// for a native image without a CLI header
+pub fn parse_assembly(data: &[u8]) -> Result<Option<AssemblyInfo>, String> {
+    let Some(metadata) = cli_metadata(data)? else {
+        return Ok(None);
+    };
+    let tables = Tables::parse(metadata)?;
+
+    if tables.rows[ASSEMBLY] == 0 {
+        return Err("the file is a .NET module, not an assembly".to_string());
+    }
+    let mut info = AssemblyInfo {
+        name: tables.string(tables.cell(ASSEMBLY, 1, 7)?)?,
+        version: tables.version(ASSEMBLY, 1, 1)?,
+        ..Default::default()
+    };
+
+    for row in 1..=tables.rows[ASSEMBLY_REF] {
+        info.references.push(AssemblyReference {
+            name: tables.string(tables.cell(ASSEMBLY_REF, row, 6)?)?,
+            version: tables.version(ASSEMBLY_REF, row, 0)?,
+        });
+    }
+
+    for row in 1..=tables.rows[CUSTOM_ATTRIBUTE] {
+        // Attributes we can't decode are skipped rather than failing the whole assembly
+        let Ok((type_name, args)) = tables.custom_attribute(row) else {
+            continue;
+        };
+        let text = |i: usize| match args.get(i) {
+            Some(AttributeArg::Text(s)) => Some(s.clone()),
+            _ => None,
+        };
+
+        match type_name.as_str() {
+            "System.Reflection.AssemblyInformationalVersionAttribute" => info.informational_version = text(0),
+            "System.Reflection.AssemblyTitleAttribute" => info.title = text(0).filter(|s| !s.is_empty()),
+            "System.Reflection.AssemblyDescriptionAttribute" => info.description = text(0).filter(|s| !s.is_empty()),
+            "System.Reflection.AssemblyCompanyAttribute" => info.company = text(0).filter(|s| !s.is_empty()),
+            "BepInEx.BepInPlugin" => {
+                if let (Some(guid), Some(name), Some(version)) = (text(0), text(1), text(2)) {
+                    info.plugin = Some(PluginInfo { guid, name, version });
+                }
+            }
+            name if name.ends_with(".SilkModAttribute") || name.ends_with(".SilkMod") => {
+                info.silk_mod = Some(silk_mod_info(&args));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(Some(info))
+}
+
+/// `[SilkMod(name, authors, version, silkVersion, modId, ...)]`, read by position: the
+/// string arguments in order, and the first string array as the authors
+fn silk_mod_info(args: &[AttributeArg]) -> SilkModInfo {
+    let mut strings = args.iter().filter_map(|arg| match arg {
+        AttributeArg::Text(s) => Some(s.clone()),
+        _ => None,
+    });
+    let authors = args
+        .iter()
+        .find_map(|arg| match arg {
+            AttributeArg::List(list) => Some(list.clone()),
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    SilkModInfo {
+        name: strings.next().unwrap_or_default(),
+        authors,
+        version: strings.next(),
+        silk_version: strings.next(),
+        id: strings.next(),
+    }
+}
+
+/// Bounds-checked little-endian reads over a byte slice
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn at(data: &'a [u8], pos: usize) -> Self {
+        Reader { data, pos }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.data.len());
+        let end = end.ok_or_else(|| "unexpected end of data".to_string())?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        let b = self.bytes(8)?;
+        Ok(u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+    }
+
+    /// An ECMA-335 compressed unsigned integer (II.23.2)
+    fn compressed(&mut self) -> Result<u32, String> {
+        let first = self.u8()? as u32;
+        if first & 0x80 == 0 {
+            Ok(first)
+        } else if first & 0xC0 == 0x80 {
+            Ok(((first & 0x3F) << 8) | self.u8()? as u32)
+        } else if first & 0xE0 == 0xC0 {
+            let rest = self.bytes(3)?;
+            Ok(((first & 0x1F) << 24) | (rest[0] as u32) << 16 | (rest[1] as u32) << 8 | rest[2] as u32)
+        } else {
+            Err("invalid compressed integer".to_string())
+        }
+    }
+}
+
+/// Find the CLI metadata root through the PE headers; `None` when the image has no CLI header
+fn cli_metadata(data: &[u8]) -> Result<Option<&[u8]>, String> {
+    if data.get(..2) != Some(b"MZ".as_slice()) {
+        return Err("not a Windows executable".to_string());
+    }
+    let pe_offset = Reader::at(data, 0x3C).u32()? as usize;
+    let mut pe = Reader::at(data, pe_offset);
+    if pe.bytes(4)? != b"PE\0\0" {
+        return Err("missing PE signature".to_string());
+    }
+
+    // COFF file header
+    pe.bytes(2)?;
+    let section_count = pe.u16()? as usize;
+    pe.bytes(12)?;
+    let optional_size = pe.u16()? as usize;
+    pe.bytes(2)?;
+
+    // The CLI header is data directory 14, after 96 (PE32) or 112 (PE32+) bytes of optional header
+    let optional_start = pe.pos;
+    let directories_offset = match pe.u16()? {
+        0x10B => 96,
+        0x20B => 112,
+        magic => return Err(format!("unknown optional header magic {:#x}", magic)),
+    };
+    let directory_count = Reader::at(data, optional_start + directories_offset - 4).u32()?;
+    if directory_count <= 14 {
+        return Ok(None);
+    }
+    let mut cli_directory = Reader::at(data, optional_start + directories_offset + 14 * 8);
+    let cli_rva = cli_directory.u32()?;
+    if cli_rva == 0 {
+        return Ok(None);
+    }
+
+    let mut sections = Vec::with_capacity(section_count);
+    let mut section = Reader::at(data, optional_start + optional_size);
+    for _ in 0..section_count {
+        section.bytes(8)?;
+        let virtual_size = section.u32()?;
+        let virtual_address = section.u32()?;
+        let raw_size = section.u32()?;
+        let raw_offset = section.u32()?;
+        section.bytes(16)?;
+        sections.push((virtual_address, virtual_size.max(raw_size), raw_offset));
+    }
+    let to_offset = |rva: u32| -> Result<usize, String> {
+        sections
+            .iter()
+            .find(|(address, size, _)| rva >= *address && rva - address < *size)
+            .and_then(|(address, _, raw)| (*raw as usize).checked_add((rva - address) as usize))
+            .ok_or_else(|| format!("address {:#x} is outside every section", rva))
+    };
+
+    let mut cli_header = Reader::at(data, to_offset(cli_rva)?);
+    cli_header.bytes(8)?;
+    let metadata_rva = cli_header.u32()?;
+    let metadata_size = cli_header.u32()? as usize;
+    Reader::at(data, to_offset(metadata_rva)?).bytes(metadata_size).map(Some)
+}
+
+// Metadata tables this parser reads
+const TYPE_REF: usize = 0x01;
+const TYPE_DEF: usize = 0x02;
+const METHOD_DEF: usize = 0x06;
+const MEMBER_REF: usize = 0x0A;
+const CUSTOM_ATTRIBUTE: usize = 0x0C;
+const ASSEMBLY: usize = 0x20;
+const ASSEMBLY_REF: usize = 0x23;
+
+const TABLE_COUNT: usize = 0x2D;
+
+/// Stands in for the unused tags of a coded index
+const UNUSED: usize = usize::MAX;
+
+/// Tables a coded index can point into, in tag order (II.24.2.6)
+const TYPE_DEF_OR_REF: &[usize] = &[0x02, 0x01, 0x1B];
+const HAS_CONSTANT: &[usize] = &[0x04, 0x08, 0x17];
+const HAS_CUSTOM_ATTRIBUTE: &[usize] = &[
+    0x06, 0x04, 0x01, 0x02, 0x08, 0x09, 0x0A, 0x00, 0x0E, 0x17, 0x14, 0x11, 0x1A, 0x1B, 0x20, 0x23, 0x26, 0x27, 0x28,
+    0x2A, 0x2C, 0x2B,
+];
+const HAS_FIELD_MARSHAL: &[usize] = &[0x04, 0x08];
+const HAS_DECL_SECURITY: &[usize] = &[0x02, 0x06, 0x20];
+const MEMBER_REF_PARENT: &[usize] = &[0x02, 0x01, 0x1A, 0x06, 0x1B];
+const HAS_SEMANTICS: &[usize] = &[0x14, 0x17];
+const METHOD_DEF_OR_REF: &[usize] = &[0x06, 0x0A];
+const MEMBER_FORWARDED: &[usize] = &[0x04, 0x06];
+const IMPLEMENTATION: &[usize] = &[0x26, 0x23, 0x27];
+const CUSTOM_ATTRIBUTE_TYPE: &[usize] = &[UNUSED, UNUSED, 0x06, 0x0A, UNUSED];
+const RESOLUTION_SCOPE: &[usize] = &[0x00, 0x1A, 0x23, 0x01];
+const TYPE_OR_METHOD_DEF: &[usize] = &[0x02, 0x06];
+
+#[derive(Clone, Copy)]
+enum Column {
+    Fixed(usize),
+    Str,
+    Guid,
+    Blob,
+    Index(usize),
+    Coded(&'static [usize]),
+}
+
+use Column::{Blob, Coded, Fixed, Guid, Index, Str};
+
+/// Column layout of every metadata table (II.22)
+const SCHEMA: [&[Column]; TABLE_COUNT] = [
+    /* 0x00 Module */ &[Fixed(2), Str, Guid, Guid, Guid],
+    /* 0x01 TypeRef */ &[Coded(RESOLUTION_SCOPE), Str, Str],
+    /* 0x02 TypeDef */ &[Fixed(4), Str, Str, Coded(TYPE_DEF_OR_REF), Index(0x04), Index(0x06)],
+    /* 0x03 FieldPtr */ &[Index(0x04)],
+    /* 0x04 Field */ &[Fixed(2), Str, Blob],
+    /* 0x05 MethodPtr */ &[Index(0x06)],
+    /* 0x06 MethodDef */ &[Fixed(4), Fixed(2), Fixed(2), Str, Blob, Index(0x08)],
+    /* 0x07 ParamPtr */ &[Index(0x08)],
+    /* 0x08 Param */ &[Fixed(2), Fixed(2), Str],
+    /* 0x09 InterfaceImpl */ &[Index(0x02), Coded(TYPE_DEF_OR_REF)],
+    /* 0x0A MemberRef */ &[Coded(MEMBER_REF_PARENT), Str, Blob],
+    /* 0x0B Constant */ &[Fixed(2), Coded(HAS_CONSTANT), Blob],
+    /* 0x0C CustomAttribute */ &[Coded(HAS_CUSTOM_ATTRIBUTE), Coded(CUSTOM_ATTRIBUTE_TYPE), Blob],
+    /* 0x0D FieldMarshal */ &[Coded(HAS_FIELD_MARSHAL), Blob],
+    /* 0x0E DeclSecurity */ &[Fixed(2), Coded(HAS_DECL_SECURITY), Blob],
+    /* 0x0F ClassLayout */ &[Fixed(2), Fixed(4), Index(0x02)],
+    /* 0x10 FieldLayout */ &[Fixed(4), Index(0x04)],
+    /* 0x11 StandAloneSig */ &[Blob],
+    /* 0x12 EventMap */ &[Index(0x02), Index(0x14)],
+    /* 0x13 EventPtr */ &[Index(0x14)],
+    /* 0x14 Event */ &[Fixed(2), Str, Coded(TYPE_DEF_OR_REF)],
+    /* 0x15 PropertyMap */ &[Index(0x02), Index(0x17)],
+    /* 0x16 PropertyPtr */ &[Index(0x17)],
+    /* 0x17 Property */ &[Fixed(2), Str, Blob],
+    /* 0x18 MethodSemantics */ &[Fixed(2), Index(0x06), Coded(HAS_SEMANTICS)],
+    /* 0x19 MethodImpl */ &[Index(0x02), Coded(METHOD_DEF_OR_REF), Coded(METHOD_DEF_OR_REF)],
+    /* 0x1A ModuleRef */ &[Str],
+    /* 0x1B TypeSpec */ &[Blob],
+    /* 0x1C ImplMap */ &[Fixed(2), Coded(MEMBER_FORWARDED), Str, Index(0x1A)],
+    /* 0x1D FieldRVA */ &[Fixed(4), Index(0x04)],
+    /* 0x1E EncLog */ &[Fixed(4), Fixed(4)],
+    /* 0x1F EncMap */ &[Fixed(4)],
+    /* 0x20 Assembly */ &[Fixed(4), Fixed(2), Fixed(2), Fixed(2), Fixed(2), Fixed(4), Blob, Str, Str],
+    /* 0x21 AssemblyProcessor */ &[Fixed(4)],
+    /* 0x22 AssemblyOS */ &[Fixed(4), Fixed(4), Fixed(4)],
+    /* 0x23 AssemblyRef */ &[Fixed(2), Fixed(2), Fixed(2), Fixed(2), Fixed(4), Blob, Str, Str, Blob],
+    /* 0x24 AssemblyRefProcessor */ &[Fixed(4), Index(0x23)],
+    /* 0x25 AssemblyRefOS */ &[Fixed(4), Fixed(4), Fixed(4), Index(0x23)],
+    /* 0x26 File */ &[Fixed(4), Str, Blob],
+    /* 0x27 ExportedType */ &[Fixed(4), Fixed(4), Str, Str, Coded(IMPLEMENTATION)],
+    /* 0x28 ManifestResource */ &[Fixed(4), Fixed(4), Str, Coded(IMPLEMENTATION)],
+    /* 0x29 NestedClass */ &[Index(0x02), Index(0x02)],
+    /* 0x2A GenericParam */ &[Fixed(2), Fixed(2), Coded(TYPE_OR_METHOD_DEF), Str],
+    /* 0x2B MethodSpec */ &[Coded(METHOD_DEF_OR_REF), Blob],
+    /* 0x2C GenericParamConstraint */ &[Index(0x2A), Coded(TYPE_DEF_OR_REF)],
+];
+
+/// A decoded fixed argument of a custom attribute
+#[derive(Debug, Clone)]
+enum AttributeArg {
+    Text(String),
+    List(Vec<String>),
+    /// A null string, or a value of a type we don't need
+    Other,
+}
+
+/// Parameter types a custom attribute constructor can take (II.23.3)
+#[derive(Debug, Clone)]
+enum ArgType {
+    Fixed(usize),
+    String,
+    Array(Box<ArgType>),
+}
+
+/// The `#~` table stream with the heaps it refers to
+struct Tables<'a> {
+    data: &'a [u8],
+    rows: [u32; TABLE_COUNT],
+    offsets: [usize; TABLE_COUNT],
+    row_sizes: [usize; TABLE_COUNT],
+    strings: &'a [u8],
+    blobs: &'a [u8],
+    wide_strings: bool,
+    wide_guids: bool,
+    wide_blobs: bool,
+}
+
+impl<'a> Tables<'a> {
+    fn parse(metadata: &'a [u8]) -> Result<Self, String> {
+        let mut root = Reader::at(metadata, 0);
+        if root.u32()? != 0x424A_5342 {
+            return Err("missing metadata signature".to_string());
+        }
+        root.bytes(8)?;
+        let version_length = root.u32()? as usize;
+        root.bytes(version_length)?;
+        root.bytes(2)?;
+        let stream_count = root.u16()?;
+
+        let (mut table_stream, mut strings, mut blobs) = (None, &[][..], &[][..]);
+        for _ in 0..stream_count {
+            let offset = root.u32()? as usize;
+            let size = root.u32()? as usize;
+            let name_start = root.pos;
+            while root.u8()? != 0 {}
+            // Names are padded to a multiple of four bytes including the terminator
+            let name = &metadata[name_start..root.pos - 1];
+            root.pos = name_start + (root.pos - name_start).div_ceil(4) * 4;
+
+            let stream = Reader::at(metadata, offset).bytes(size)?;
+            match name {
+                b"#~" | b"#-" => table_stream = Some(stream),
+                b"#Strings" => strings = stream,
+                b"#Blob" => blobs = stream,
+                _ => {}
+            }
+        }
+        let data = table_stream.ok_or_else(|| "missing metadata tables".to_string())?;
+
+        let mut header = Reader::at(data, 4);
+        header.bytes(2)?;
+        let heap_sizes = header.u8()?;
+        header.bytes(1)?;
+        let valid = header.u64()?;
+        header.bytes(8)?;
+
+        let mut rows = [0u32; TABLE_COUNT];
+        for (table, count) in rows.iter_mut().enumerate() {
+            if valid & (1 << table) != 0 {
+                *count = header.u32()?;
+            }
+        }
+        // Row counts of tables this parser doesn't know still take up space before the rows
+        for table in TABLE_COUNT..64 {
+            if valid & (1 << table) != 0 {
+                header.u32()?;
+            }
+        }
+        // Uncompressed (`#-`) streams may carry four extra bytes
+        if heap_sizes & 0x40 != 0 {
+            header.bytes(4)?;
+        }
+
+        let mut tables = Tables {
+            data,
+            rows,
+            offsets: [0; TABLE_COUNT],
+            row_sizes: [0; TABLE_COUNT],
+            strings,
+            blobs,
+            wide_strings: heap_sizes & 0x01 != 0,
+            wide_guids: heap_sizes & 0x02 != 0,
+            wide_blobs: heap_sizes & 0x04 != 0,
+        };
+
+        let mut offset = header.pos;
+        for table in 0..TABLE_COUNT {
+            tables.row_sizes[table] = SCHEMA[table].iter().map(|c| tables.column_size(*c)).sum();
+            tables.offsets[table] = offset;
+            offset += tables.row_sizes[table] * rows[table] as usize;
+        }
+        if offset > data.len() {
+            return Err("metadata tables are truncated".to_string());
+        }
+
+        Ok(tables)
+    }
+
+    fn column_size(&self, column: Column) -> usize {
+        let wide = match column {
+            Fixed(size) => return size,
+            Str => self.wide_strings,
+            Guid => self.wide_guids,
+            Blob => self.wide_blobs,
+            Index(table) => self.rows[table] > 0xFFFF,
+            Coded(tables) => {
+                let tag_bits = usize::BITS - (tables.len() - 1).leading_zeros();
+                let max_rows = tables.iter().map(|t| self.rows.get(*t).copied().unwrap_or(0)).max().unwrap_or(0);
+                max_rows >= 1 << (16 - tag_bits)
+            }
+        };
+        if wide {
+            4
+        } else {
+            2
+        }
+    }
+
+    /// A cell of a table; rows are numbered from 1
+    fn cell(&self, table: usize, row: u32, column: usize) -> Result<u32, String> {
+        if row == 0 || row > self.rows[table] {
+            return Err(format!("row {} is out of range for table {:#x}", row, table));
+        }
+        let columns = SCHEMA[table];
+        let before: usize = columns[..column].iter().map(|c| self.column_size(*c)).sum();
+        let position = self.offsets[table] + (row as usize - 1) * self.row_sizes[table] + before;
+
+        let mut reader = Reader::at(self.data, position);
+        match self.column_size(columns[column]) {
+            2 => reader.u16().map(u32::from),
+            4 => reader.u32(),
+            _ => Err("unsupported column".to_string()),
+        }
+    }
+
+    /// Four consecutive version columns starting at `column`, as `major.minor.build.revision`
+    fn version(&self, table: usize, row: u32, column: usize) -> Result<String, String> {
+        let parts = (column..column + 4)
+            .map(|c| self.cell(table, row, c).map(|v| v.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(parts.join("."))
+    }
+
+    fn string(&self, index: u32) -> Result<String, String> {
+        let start = index as usize;
+        let rest = self.strings.get(start..).ok_or_else(|| "string index out of range".to_string())?;
+        let end = rest.iter().position(|b| *b == 0).unwrap_or(rest.len());
+        Ok(String::from_utf8_lossy(&rest[..end]).to_string())
+    }
+
+    fn blob(&self, index: u32) -> Result<&'a [u8], String> {
+        let mut reader = Reader::at(self.blobs, index as usize);
+        let length = reader.compressed()? as usize;
+        reader.bytes(length)
+    }
+
+    /// Full name of a TypeRef or TypeDef
+    fn type_name(&self, table: usize, row: u32) -> Result<String, String> {
+        let name = self.string(self.cell(table, row, 1)?)?;
+        let namespace = self.string(self.cell(table, row, 2)?)?;
+        Ok(if namespace.is_empty() { name } else { format!("{}.{}", namespace, name) })
+    }
+
+    /// The attribute type's full name and its decoded constructor arguments
+    fn custom_attribute(&self, row: u32) -> Result<(String, Vec<AttributeArg>), String> {
+        let constructor = self.cell(CUSTOM_ATTRIBUTE, row, 1)?;
+        let (tag, index) = (constructor & 0x7, constructor >> 3);
+
+        let (type_name, signature) = match tag {
+            // A constructor defined in this assembly: its type is the TypeDef whose method list contains it
+            2 => {
+                let owner = (1..=self.rows[TYPE_DEF])
+                    .rev()
+                    .find(|t| self.cell(TYPE_DEF, *t, 5).is_ok_and(|first| first <= index))
+                    .ok_or_else(|| "constructor without a type".to_string())?;
+                (self.type_name(TYPE_DEF, owner)?, self.blob(self.cell(METHOD_DEF, index, 4)?)?)
+            }
+            3 => {
+                let parent = self.cell(MEMBER_REF, index, 0)?;
+                let type_name = match (parent & 0x7, parent >> 3) {
+                    (0, row) => self.type_name(TYPE_DEF, row)?,
+                    (1, row) => self.type_name(TYPE_REF, row)?,
+                    _ => return Err("unsupported attribute constructor".to_string()),
+                };
+                (type_name, self.blob(self.cell(MEMBER_REF, index, 2)?)?)
+            }
+            _ => return Err("invalid attribute constructor".to_string()),
+        };
+
+        let value = self.blob(self.cell(CUSTOM_ATTRIBUTE, row, 2)?)?;
+        Ok((type_name, decode_arguments(signature, value)?))
+    }
+}
+
+/// Constructor parameter types from a method signature, up to the first one we can't decode
+fn parameter_types(signature: &[u8]) -> Result<Vec<ArgType>, String> {
+    let mut reader = Reader::at(signature, 0);
+    let calling_convention = reader.u8()?;
+    if calling_convention & 0x10 != 0 {
+        reader.compressed()?;
+    }
+    let count = reader.compressed()?;
+    if reader.u8()? != 0x01 {
+        return Err("attribute constructor does not return void".to_string());
+    }
+
+    let mut types = Vec::new();
+    for _ in 0..count {
+        match element_type(&mut reader)? {
+            Some(arg_type) => types.push(arg_type),
+            None => break,
+        }
+    }
+    Ok(types)
+}
+
+/// A parameter type, allowing one level of array around a scalar element
+fn element_type(reader: &mut Reader) -> Result<Option<ArgType>, String> {
+    let tag = reader.u8()?;
+    if tag != 0x1D {
+        return Ok(scalar_type(tag));
+    }
+    match reader.u8()? {
+        0x1D => Err("nested arrays are not valid attribute arguments".to_string()),
+        element => Ok(scalar_type(element).map(|element| ArgType::Array(Box::new(element)))),
+    }
+}
+
+fn scalar_type(tag: u8) -> Option<ArgType> {
+    match tag {
+        0x02 | 0x04 | 0x05 => Some(ArgType::Fixed(1)),
+        0x03 | 0x06 | 0x07 => Some(ArgType::Fixed(2)),
+        0x08 | 0x09 | 0x0C => Some(ArgType::Fixed(4)),
+        0x0A | 0x0B | 0x0D => Some(ArgType::Fixed(8)),
+        0x0E => Some(ArgType::String),
+        // Enums, System.Type and boxed objects need more than the signature to decode
+        _ => None,
+    }
+}
+
+/// Decode the fixed arguments of a custom attribute value blob (II.23.3)
+fn decode_arguments(signature: &[u8], value: &[u8]) -> Result<Vec<AttributeArg>, String> {
+    let mut reader = Reader::at(value, 0);
+    if reader.u16()? != 0x0001 {
+        return Err("invalid custom attribute prolog".to_string());
+    }
+
+    let mut args = Vec::new();
+    for arg_type in parameter_types(signature)? {
+        args.push(match arg_type {
+            ArgType::String => ser_string(&mut reader)?.map_or(AttributeArg::Other, AttributeArg::Text),
+            ArgType::Fixed(size) => {
+                reader.bytes(size)?;
+                AttributeArg::Other
+            }
+            ArgType::Array(element) => {
+                let count = reader.u32()?;
+                let mut items = Vec::new();
+                // A null array is stored as a count of 0xFFFFFFFF
+                if count != u32::MAX {
+                    for _ in 0..count {
+                        match element.as_ref() {
+                            ArgType::String => items.extend(ser_string(&mut reader)?),
+                            ArgType::Fixed(size) => {
+                                reader.bytes(*size)?;
+                            }
+                            ArgType::Array(_) => return Err("nested arrays are not valid attribute arguments".to_string()),
+                        }
+                    }
+                }
+                AttributeArg::List(items)
+            }
+        });
+    }
+    Ok(args)
+}
+
+/// A length-prefixed UTF-8 string, where a 0xFF length means null
+fn ser_string(reader: &mut Reader) -> Result<Option<String>, String> {
+    if reader.data.get(reader.pos) == Some(&0xFF) {
+        reader.pos += 1;
+        return Ok(None);
+    }
+    let length = reader.compressed()? as usize;
+    Ok(Some(String::from_utf8_lossy(reader.bytes(length)?).to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A minimal assembly with `[BepInPlugin]`, `[SilkMod]`, assembly attributes and an
+    /// attribute whose constructor takes an enum, which can't be decoded
+    const EXAMPLE_MOD: &[u8] = include_bytes!("../tests/fixtures/ExampleMod.dll");
+    /// The same assembly with a PE32+ optional header
+    const EXAMPLE_MOD_64: &[u8] = include_bytes!("../tests/fixtures/ExampleMod64.dll");
+    /// The same image with its CLI header directory cleared, as in a native DLL
+    const NATIVE: &[u8] = include_bytes!("../tests/fixtures/Native.dll");
+
+    fn tables_with(rows: &[(usize, u32)], heap_sizes: u8) -> Tables<'static> {
+        let mut tables = Tables {
+            data: &[],
+            rows: [0; TABLE_COUNT],
+            offsets: [0; TABLE_COUNT],
+            row_sizes: [0; TABLE_COUNT],
+            strings: &[],
+            blobs: &[],
+            wide_strings: heap_sizes & 0x01 != 0,
+            wide_guids: heap_sizes & 0x02 != 0,
+            wide_blobs: heap_sizes & 0x04 != 0,
+        };
+        for (table, count) in rows {
+            tables.rows[*table] = *count;
+        }
+        tables
+    }
+
+    fn row_size(tables: &Tables, table: usize) -> usize {
+        SCHEMA[table].iter().map(|c| tables.column_size(*c)).sum()
+    }
+
+    #[test]
+    fn row_sizes_match_the_spec() {
+        let narrow = tables_with(&[], 0);
+        assert_eq!(row_size(&narrow, TYPE_REF), 6);
+        assert_eq!(row_size(&narrow, TYPE_DEF), 14);
+        assert_eq!(row_size(&narrow, METHOD_DEF), 14);
+        assert_eq!(row_size(&narrow, MEMBER_REF), 6);
+        assert_eq!(row_size(&narrow, CUSTOM_ATTRIBUTE), 6);
+        assert_eq!(row_size(&narrow, ASSEMBLY), 22);
+        assert_eq!(row_size(&narrow, ASSEMBLY_REF), 20);
+
+        // Wide heaps widen every string, GUID and blob column
+        let wide = tables_with(&[], 0x07);
+        assert_eq!(row_size(&wide, 0x00), 2 + 4 * 4);
+        assert_eq!(row_size(&wide, ASSEMBLY), 16 + 3 * 4);
+    }
+
+    #[test]
+    fn coded_indexes_list_every_tag() {
+        assert_eq!(TYPE_DEF_OR_REF.len(), 3);
+        assert_eq!(HAS_CONSTANT.len(), 3);
+        assert_eq!(HAS_CUSTOM_ATTRIBUTE.len(), 22);
+        assert_eq!(MEMBER_REF_PARENT.len(), 5);
+        assert_eq!(CUSTOM_ATTRIBUTE_TYPE.len(), 5);
+        assert_eq!(RESOLUTION_SCOPE.len(), 4);
+        assert_eq!(IMPLEMENTATION.len(), 3);
+    }
+
+    #[test]
+    fn coded_indexes_widen_past_their_tag_bits() {
+        // Two tag bits leave 14 bits for the row
+        assert_eq!(tables_with(&[(TYPE_REF, 0x3FFF)], 0).column_size(Coded(TYPE_DEF_OR_REF)), 2);
+        assert_eq!(tables_with(&[(TYPE_REF, 0x4000)], 0).column_size(Coded(TYPE_DEF_OR_REF)), 4);
+        // Five tag bits leave 11
+        assert_eq!(tables_with(&[(ASSEMBLY_REF, 0x7FF)], 0).column_size(Coded(HAS_CUSTOM_ATTRIBUTE)), 2);
+        assert_eq!(tables_with(&[(ASSEMBLY_REF, 0x800)], 0).column_size(Coded(HAS_CUSTOM_ATTRIBUTE)), 4);
+        // Three tag bits, where unused tags don't count towards the size
+        assert_eq!(tables_with(&[(MEMBER_REF, 0x1FFF)], 0).column_size(Coded(CUSTOM_ATTRIBUTE_TYPE)), 2);
+        assert_eq!(tables_with(&[(MEMBER_REF, 0x2000)], 0).column_size(Coded(CUSTOM_ATTRIBUTE_TYPE)), 4);
+        // Plain indexes widen past 16 bits
+        assert_eq!(tables_with(&[(METHOD_DEF, 0xFFFF)], 0).column_size(Index(METHOD_DEF)), 2);
+        assert_eq!(tables_with(&[(METHOD_DEF, 0x10000)], 0).column_size(Index(METHOD_DEF)), 4);
+    }
+
+    #[test]
+    fn reads_plugin_and_mod_attributes() {
+        for image in [EXAMPLE_MOD, EXAMPLE_MOD_64] {
+            let info = parse_assembly(image).unwrap().unwrap();
+
+            assert_eq!(info.name, "ExampleMod");
+            assert_eq!(info.version, "2.1.0.0");
+            assert_eq!(info.informational_version.as_deref(), Some("2.1.0+abc123"));
+            assert_eq!(info.company.as_deref(), Some("Example Co"));
+
+            let plugin = info.plugin.as_ref().unwrap();
+            assert_eq!(plugin.guid, "com.example.plugin");
+            assert_eq!(plugin.name, "Example Plugin");
+            assert_eq!(plugin.version, "2.1.0");
+
+            let silk_mod = info.silk_mod.as_ref().unwrap();
+            assert_eq!(silk_mod.name, "Example Mod");
+            assert_eq!(silk_mod.authors, vec!["Alice", "Bob"]);
+            assert_eq!(silk_mod.silk_version.as_deref(), Some("0.6.1"));
+
+            let references: Vec<(&str, &str)> =
+                info.references.iter().map(|r| (r.name.as_str(), r.version.as_str())).collect();
+            assert_eq!(references, vec![("mscorlib", "4.0.0.0"), ("BepInEx", "5.6.0.0"), ("Silk", "0.6.1.0")]);
+
+            assert_eq!(info.mod_id(), "example-mod");
+            assert_eq!(info.mod_version(), "2.1.0");
+            assert_eq!(info.mod_author().as_deref(), Some("Alice, Bob"));
+        }
+    }
+
+    #[test]
+    fn native_images_are_not_assemblies() {
+        assert!(parse_assembly(NATIVE).unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_malformed_images() {
+        assert!(parse_assembly(b"not a dll").is_err());
+        // Cut off inside the metadata
+        assert!(parse_assembly(&EXAMPLE_MOD[..0x300]).is_err());
+    }
+
+    #[test]
+    fn rejects_sections_past_the_address_space() {
+        // Move the only section's raw data to the end of the 32-bit range, so adding the
+        // CLI header's offset within it overflows a u32
+        let mut image = EXAMPLE_MOD.to_vec();
+        let section = 0x98 + 224 + 20;
+        image[section..section + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(parse_assembly(&image).is_err());
+    }
+
+    #[test]
+    fn rejects_nested_array_signatures() {
+        // HASTHIS, one parameter, void, then an array of arrays many levels deep
+        let mut signature = vec![0x20, 0x01, 0x01];
+        signature.extend(std::iter::repeat_n(0x1D, 100_000));
+        signature.push(0x0E);
+        assert!(decode_arguments(&signature, &[0x01, 0x00, 0x00, 0x00, 0x00, 0x00]).is_err());
+
+        // A single string array still decodes
+        let value = [0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, b'a', 0x00, 0x00];
+        let args = decode_arguments(&[0x20, 0x01, 0x01, 0x1D, 0x0E], &value).unwrap();
+        assert!(matches!(&args[..], [AttributeArg::List(items)] if items == &["a".to_string()]));
+    }
+
+    #[test]
+    fn decodes_compressed_integers() {
+        for (bytes, value) in [(&[0x03][..], 0x03), (&[0x80, 0x80], 0x80), (&[0xC0, 0x00, 0x40, 0x00], 0x4000)] {
+            assert_eq!(Reader::at(bytes, 0).compressed().unwrap(), value);
+        }
+        assert!(Reader::at(&[0xE0], 0).compressed().is_err());
+    }
+}
@@ -0,0 +1,496 @@
+use crate::settings::BackupRetention;
+use crate::{download, extract, transaction, version};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+/// Folders, relative to the game directory, captured by a backup.
+/// `.entwine_metadata.json` and the install manifests live in `Silk/Mods`.
+const BACKED_UP_DIRS: &[&str] = &["Silk/Mods", "Silk/Config"];
+
+/// Entry in each backup archive that describes it
+const INFO_NAME: &str = "backup.json";
+
+/// A snapshot of the Mods folder, configs and mod metadata of one game install
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    pub id: String,
+    /// Seconds since the Unix epoch
+    pub created_at: u64,
+    pub reason: String,
+    pub silk_version: Option<String>,
+    pub mod_count: usize,
+    /// Size of the compressed archive in bytes
+    #[serde(default)]
+    pub size: u64,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Format seconds since the Unix epoch as `YYYYMMDD-HHMMSS` (UTC)
+fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// Folder holding the backups of one game install, outside the game directory so they
+/// survive the game being reinstalled or verified by Steam
+fn backups_dir(game_path: &str) -> Result<PathBuf, String> {
+    let data_dir = dirs::data_local_dir().ok_or_else(|| "Failed to get data directory".to_string())?;
+    let canonical = fs::canonicalize(game_path).unwrap_or_else(|_| PathBuf::from(game_path));
+    let key = download::to_hex(&Sha256::digest(canonical.to_string_lossy().as_bytes()));
+
+    Ok(data_dir.join("entwine").join("backups").join(&key[..16]))
+}
+
+fn backup_path(game_path: &str, id: &str) -> Result<PathBuf, String> {
+    let id = extract::safe_file_name(id).map_err(|e| format!("Invalid backup id: {}", e))?;
+    let path = backups_dir(game_path)?.join(format!("{}.zip", id));
+    if !path.is_file() {
+        return Err(format!("Backup not found: {}", id));
+    }
+    Ok(path)
+}
+
+fn count_mods(mods_dir: &Path) -> usize {
+    let Ok(entries) = fs::read_dir(mods_dir) else {
+        return 0;
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .count()
+}
+
+fn read_info(path: &Path) -> Result<BackupInfo, String> {
+    let file = fs::File::open(path).map_err(|e| format!("Failed to open backup: {}", e))?;
+    let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("Failed to read backup: {}", e))?;
+
+    let mut content = String::new();
+    archive
+        .by_name(INFO_NAME)
+        .map_err(|e| format!("Backup has no {}: {}", INFO_NAME, e))?
+        .read_to_string(&mut content)
+        .map_err(|e| format!("Failed to read backup info: {}", e))?;
+
+    let mut info: BackupInfo =
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse backup info: {}", e))?;
+    info.size = size;
+    Ok(info)
+}
+
+/// Every readable backup of a game install, newest first
+pub fn list_backups(game_path: &str) -> Result<Vec<BackupInfo>, String> {
+    let dir = backups_dir(game_path)?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(&dir).map_err(|e| format!("Failed to read backups directory: {}", e))? {
+        let path = entry.map_err(|e| format!("Failed to read entry: {}", e))?.path();
+        if path.extension().and_then(|s| s.to_str()) != Some("zip") {
+            continue;
+        }
+
+        match read_info(&path) {
+            Ok(info) => backups.push(info),
+            Err(e) => warn!("Skipping unreadable backup {}: {}", path.display(), e),
+        }
+    }
+
+    backups.sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| b.id.cmp(&a.id)));
+    Ok(backups)
+}
+
+/// Write the backed-up folders of `game_dir` into a zip archive
+fn write_archive(game_dir: &Path, output: &Path, info: &BackupInfo) -> Result<(), String> {
+    let file = fs::File::create(output).map_err(|e| format!("Failed to create backup file: {}", e))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .large_file(true);
+
+    let info_json =
+        serde_json::to_string_pretty(info).map_err(|e| format!("Failed to serialize backup info: {}", e))?;
+    zip.start_file(INFO_NAME, options)
+        .map_err(|e| format!("Failed to write backup: {}", e))?;
+    zip.write_all(info_json.as_bytes())
+        .map_err(|e| format!("Failed to write backup: {}", e))?;
+
+    for dir in BACKED_UP_DIRS {
+        let source = game_dir.join(dir);
+        if !source.is_dir() {
+            continue;
+        }
+
+        // Staging folders of an install in progress are not part of the state
+        let walker = WalkDir::new(&source)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| {
+                !entry
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with(transaction::TRANSACTION_DIR_PREFIX)
+            });
+
+        for entry in walker {
+            let entry = entry.map_err(|e| format!("Failed to read {}: {}", dir, e))?;
+            let relative = entry
+                .path()
+                .strip_prefix(game_dir)
+                .map_err(|e| format!("Invalid path {}: {}", entry.path().display(), e))?;
+            let name = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            if entry.file_type().is_dir() {
+                zip.add_directory(name, options)
+                    .map_err(|e| format!("Failed to write backup: {}", e))?;
+            } else if entry.file_type().is_file() {
+                let mut source_file = fs::File::open(entry.path())
+                    .map_err(|e| format!("Failed to open {}: {}", entry.path().display(), e))?;
+                zip.start_file(name, options)
+                    .map_err(|e| format!("Failed to write backup: {}", e))?;
+                io::copy(&mut source_file, &mut zip)
+                    .map_err(|e| format!("Failed to back up {}: {}", entry.path().display(), e))?;
+            }
+        }
+    }
+
+    zip.finish().map_err(|e| format!("Failed to finish backup archive: {}", e))?;
+    Ok(())
+}
+
+/// Snapshot `Silk/Mods` (including the mod metadata) and `Silk/Config` into a new backup
+pub fn create_backup(game_path: &str, reason: &str) -> Result<BackupInfo, String> {
+    let game_dir = PathBuf::from(game_path);
+    if !game_dir.exists() {
+        return Err("Game path does not exist".to_string());
+    }
+
+    let dir = backups_dir(game_path)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create backups directory: {}", e))?;
+
+    let created_at = now();
+    let base = format_timestamp(created_at);
+    let mut id = base.clone();
+    let mut suffix = 2;
+    while dir.join(format!("{}.zip", id)).exists() {
+        id = format!("{}-{}", base, suffix);
+        suffix += 1;
+    }
+
+    let mut info = BackupInfo {
+        id: id.clone(),
+        created_at,
+        reason: reason.to_string(),
+        silk_version: version::get_installed_silk_version(game_path).ok(),
+        mod_count: count_mods(&game_dir.join("Silk").join("Mods")),
+        size: 0,
+    };
+
+    // Write under a temporary name so a failed backup is never listed
+    let partial = dir.join(format!("{}.zip.part", id));
+    let output = dir.join(format!("{}.zip", id));
+    if let Err(e) = write_archive(&game_dir, &partial, &info) {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    fs::rename(&partial, &output).map_err(|e| format!("Failed to save backup: {}", e))?;
+
+    info.size = fs::metadata(&output).map(|m| m.len()).unwrap_or(0);
+    info!("Created backup {} ({}): {} bytes", info.id, info.reason, info.size);
+    Ok(info)
+}
+
+/// Take a backup before a change and prune old backups per the retention settings
+pub fn create_backup_before(game_path: &str, reason: &str, retention: &BackupRetention) -> Result<BackupInfo, String> {
+    let info = create_backup(game_path, reason)
+        .map_err(|e| format!("Failed to back up before {}: {}", reason.to_lowercase(), e))?;
+
+    if let Err(e) = prune_backups(game_path, retention) {
+        warn!("Failed to prune old backups: {}", e);
+    }
+    Ok(info)
+}
+
+/// Replace `Silk/Mods` and `Silk/Config` with the contents of a backup. The current state
+/// is backed up first, so a restore can itself be undone.
+pub fn restore_backup(game_path: &str, id: &str, retention: &BackupRetention) -> Result<BackupInfo, String> {
+    let archive_path = backup_path(game_path, id)?;
+    let info = read_info(&archive_path)?;
+    let game_dir = PathBuf::from(game_path);
+
+    // Pruning waits until the restore is done so it can't remove the backup being restored
+    create_backup(game_path, &format!("Before restoring backup {}", info.id))
+        .map_err(|e| format!("Failed to back up the current state: {}", e))?;
+
+    let mut transaction = transaction::Transaction::begin(&game_dir)?;
+    let staging_dir = transaction.staging_dir();
+
+    let file = fs::File::open(&archive_path).map_err(|e| format!("Failed to open backup: {}", e))?;
+    extract::extract_zip(
+        file,
+        &staging_dir,
+        &extract::Limits::default(),
+        |path| path.starts_with("Silk"),
+        None,
+    )
+    .map_err(|e| format!("Failed to extract backup: {}", e))?;
+
+    for dir in BACKED_UP_DIRS {
+        let relative = Path::new(dir);
+        let staged = staging_dir.join(relative);
+        if staged.exists() {
+            transaction.place(&staged, relative)?;
+        } else {
+            transaction.remove(relative)?;
+        }
+    }
+    transaction.commit();
+
+    if let Err(e) = prune_backups(game_path, retention) {
+        warn!("Failed to prune old backups: {}", e);
+    }
+
+    info!("Restored backup {} from {}", info.id, info.reason);
+    Ok(info)
+}
+
+/// Delete a single backup
+pub fn delete_backup(game_path: &str, id: &str) -> Result<(), String> {
+    let path = backup_path(game_path, id)?;
+    fs::remove_file(&path).map_err(|e| format!("Failed to delete backup {}: {}", id, e))
+}
+
+/// Delete the backups the retention settings no longer keep. Returns the deleted ids.
+pub fn prune_backups(game_path: &str, retention: &BackupRetention) -> Result<Vec<String>, String> {
+    let backups = list_backups(game_path)?;
+    let cutoff = (retention.max_age_days > 0)
+        .then(|| now().saturating_sub(u64::from(retention.max_age_days) * 86_400));
+
+    let mut deleted = Vec::new();
+    for (index, backup) in backups.iter().enumerate() {
+        let too_many = retention.max_backups > 0 && index >= retention.max_backups as usize;
+        let too_old = cutoff.is_some_and(|cutoff| backup.created_at < cutoff);
+        if !too_many && !too_old {
+            continue;
+        }
+
+        delete_backup(game_path, &backup.id)?;
+        deleted.push(backup.id.clone());
+    }
+
+    if !deleted.is_empty() {
+        info!("Pruned {} old backups", deleted.len());
+    }
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_game(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("entwine-backup-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn cleanup(game_dir: &Path) {
+        let game_path = game_dir.to_string_lossy();
+        let _ = fs::remove_dir_all(backups_dir(&game_path).unwrap());
+        let _ = fs::remove_dir_all(game_dir);
+    }
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    /// Every file and directory under `root` with the contents of the files
+    fn snapshot(root: &Path) -> Vec<(PathBuf, Option<Vec<u8>>)> {
+        WalkDir::new(root)
+            .min_depth(1)
+            .sort_by_file_name()
+            .into_iter()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let content = entry.file_type().is_file().then(|| fs::read(entry.path()).unwrap());
+                (entry.path().strip_prefix(root).unwrap().to_path_buf(), content)
+            })
+            .collect()
+    }
+
+    /// Write a backup as if it had been taken at `created_at`
+    fn backup_at(game_dir: &Path, created_at: u64) -> String {
+        let game_path = game_dir.to_string_lossy();
+        let dir = backups_dir(&game_path).unwrap();
+        fs::create_dir_all(&dir).unwrap();
+        let info = BackupInfo {
+            id: format_timestamp(created_at),
+            created_at,
+            reason: "Test".to_string(),
+            silk_version: None,
+            mod_count: 0,
+            size: 0,
+        };
+        write_archive(game_dir, &dir.join(format!("{}.zip", info.id)), &info).unwrap();
+        info.id
+    }
+
+    fn ids(game_dir: &Path) -> Vec<String> {
+        list_backups(&game_dir.to_string_lossy())
+            .unwrap()
+            .into_iter()
+            .map(|b| b.id)
+            .collect()
+    }
+
+    #[test]
+    fn formats_timestamps_as_utc() {
+        assert_eq!(format_timestamp(0), "19700101-000000");
+        assert_eq!(format_timestamp(951_827_696), "20000229-123456");
+    }
+
+    #[test]
+    fn restores_mods_and_configs() {
+        let game = temp_game("round-trip");
+        let game_path = game.to_string_lossy().to_string();
+        write(&game.join("Silk/Mods/Alpha.dll"), "alpha");
+        write(&game.join("Silk/Mods/Beta/Beta.dll"), "beta");
+        write(&game.join("Silk/Mods/.entwine_metadata.json"), "{\"Alpha\": {}}");
+        write(&game.join("Silk/Config/Mods/Alpha.yaml"), "speed: 1\n");
+        write(&game.join("Game.exe"), "game");
+        let before = snapshot(&game);
+
+        let backup = create_backup(&game_path, "Test").unwrap();
+        assert_eq!(backup.mod_count, 2);
+
+        fs::remove_file(game.join("Silk/Mods/Alpha.dll")).unwrap();
+        write(&game.join("Silk/Mods/Beta/Beta.dll"), "new beta");
+        write(&game.join("Silk/Mods/Gamma.dll"), "gamma");
+        write(&game.join("Silk/Config/Mods/Alpha.yaml"), "speed: 2\n");
+        write(&game.join("Silk/Config/Mods/Gamma.yaml"), "new: true\n");
+        let changed = snapshot(&game);
+
+        let retention = BackupRetention { max_backups: 0, max_age_days: 0 };
+        restore_backup(&game_path, &backup.id, &retention).unwrap();
+        assert_eq!(snapshot(&game), before);
+
+        // The state the restore replaced was backed up too, so the restore can be undone
+        let undo = list_backups(&game_path)
+            .unwrap()
+            .into_iter()
+            .find(|b| b.id != backup.id)
+            .unwrap();
+        assert!(undo.reason.starts_with("Before restoring backup"));
+        restore_backup(&game_path, &undo.id, &retention).unwrap();
+        assert_eq!(snapshot(&game), changed);
+
+        cleanup(&game);
+    }
+
+    #[test]
+    fn restoring_removes_folders_the_backup_lacks() {
+        let game = temp_game("missing-config");
+        let game_path = game.to_string_lossy().to_string();
+        write(&game.join("Silk/Mods/Alpha.dll"), "alpha");
+        let backup = create_backup(&game_path, "Test").unwrap();
+
+        write(&game.join("Silk/Config/Mods/Alpha.yaml"), "speed: 1\n");
+        restore_backup(&game_path, &backup.id, &BackupRetention::default()).unwrap();
+        assert!(!game.join("Silk/Config").exists());
+        assert!(game.join("Silk/Mods/Alpha.dll").is_file());
+
+        cleanup(&game);
+    }
+
+    #[test]
+    fn rejects_unsafe_backup_ids() {
+        let game = temp_game("unsafe-id");
+        let game_path = game.to_string_lossy().to_string();
+        for id in ["../other", "a/b", ""] {
+            assert!(restore_backup(&game_path, id, &BackupRetention::default()).is_err());
+            assert!(delete_backup(&game_path, id).is_err());
+        }
+        cleanup(&game);
+    }
+
+    #[test]
+    fn prunes_beyond_the_backup_limit() {
+        let game = temp_game("prune-count");
+        write(&game.join("Silk/Mods/Alpha.dll"), "alpha");
+        let now = now();
+        let oldest = backup_at(&game, now - 3 * 86_400);
+        let older = backup_at(&game, now - 2 * 86_400);
+        let newer = backup_at(&game, now - 86_400);
+        let newest = backup_at(&game, now);
+
+        let retention = BackupRetention { max_backups: 2, max_age_days: 0 };
+        let mut deleted = prune_backups(&game.to_string_lossy(), &retention).unwrap();
+        deleted.sort();
+        assert_eq!(deleted, vec![oldest, older]);
+        assert_eq!(ids(&game), vec![newest, newer]);
+
+        cleanup(&game);
+    }
+
+    #[test]
+    fn prunes_backups_past_the_age_limit() {
+        let game = temp_game("prune-age");
+        write(&game.join("Silk/Mods/Alpha.dll"), "alpha");
+        let now = now();
+        let old = backup_at(&game, now - 10 * 86_400);
+        let recent = backup_at(&game, now - 86_400);
+
+        // Zero keeps everything
+        let keep_all = BackupRetention { max_backups: 0, max_age_days: 0 };
+        assert!(prune_backups(&game.to_string_lossy(), &keep_all).unwrap().is_empty());
+
+        let retention = BackupRetention { max_backups: 0, max_age_days: 7 };
+        assert_eq!(prune_backups(&game.to_string_lossy(), &retention).unwrap(), vec![old]);
+        assert_eq!(ids(&game), vec![recent]);
+
+        cleanup(&game);
+    }
+}
@@ -1,101 +1,218 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
 use std::fs;
-use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tauri::Emitter;
+use crate::operations::Operation;
+use crate::settings::ReleaseChannel;
+use crate::version::{self, GitHubClient, ReleaseAsset};
+use crate::doorstop::{self, Loader};
+use crate::{assembly, download, extract, manifest, transaction};
 
-const BEPINEX_VERSION: &str = "5.4.23.4";
-const BEPINEX_DOWNLOAD_URL: &str = "https://github.com/BepInEx/BepInEx/releases/download/v5.4.23.4/BepInEx_win_x64_5.4.23.4.zip";
+/// Installed when no version is chosen
+pub const DEFAULT_BEPINEX_VERSION: &str = "5.4.23.4";
+const BEPINEX_RELEASES_API: &str = "https://api.github.com/repos/BepInEx/BepInEx/releases";
 
-/// Check if BepInEx is installed
+/// The core assembly of BepInEx 5 and BepInEx 6, relative to the game folder
+const CORE_ASSEMBLIES: &[&str] = &["BepInEx/core/BepInEx.dll", "BepInEx/core/BepInEx.Core.dll"];
+
+/// A BepInEx release on GitHub with a build for the game (Windows x64, Mono)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BepInExRelease {
+    /// Tag without the leading `v`
+    pub version: String,
+    pub tag: String,
+    pub name: String,
+    pub prerelease: bool,
+    pub published_at: Option<String>,
+    /// Release notes in Markdown
+    pub body: String,
+    pub html_url: String,
+    pub asset: ReleaseAsset,
+}
+
+impl BepInExRelease {
+    fn from_json(json: &serde_json::Value) -> Option<BepInExRelease> {
+        if json.get("draft").and_then(|v| v.as_bool()).unwrap_or(false) {
+            return None;
+        }
+        let tag = json.get("tag_name")?.as_str()?.to_string();
+        let text = |key: &str| json.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+        let assets: Vec<ReleaseAsset> = json
+            .get("assets")?
+            .as_array()?
+            .iter()
+            .filter_map(ReleaseAsset::from_json)
+            .collect();
+
+        Some(BepInExRelease {
+            version: tag.trim_start_matches('v').to_string(),
+            name: Some(text("name")).filter(|n| !n.is_empty()).unwrap_or_else(|| tag.clone()),
+            prerelease: json.get("prerelease").and_then(|v| v.as_bool()).unwrap_or(false),
+            published_at: json.get("published_at").and_then(|v| v.as_str()).map(|s| s.to_string()),
+            body: text("body"),
+            html_url: text("html_url"),
+            asset: game_asset(&assets)?.clone(),
+            tag,
+        })
+    }
+}
+
+/// The Windows x64 Mono build among a release's assets. BepInEx 5 names it
+/// `BepInEx_win_x64_<v>.zip` (`BepInEx_x64_<v>.zip` before 5.4.22); BepInEx 6 ships a
+/// build per Unity backend, e.g. `BepInEx-Unity.Mono-win-x64-<v>.zip`.
+fn game_asset(assets: &[ReleaseAsset]) -> Option<&ReleaseAsset> {
+    assets.iter().find(|asset| {
+        let name = asset.name.to_lowercase();
+        let is_backend_build = name.contains("unity") || name.contains("il2cpp") || name.contains("netlauncher");
+        name.ends_with(".zip")
+            && name.contains("x64")
+            && !["unix", "linux", "macos"].iter().any(|os| name.contains(os))
+            && (!is_backend_build || (name.contains("mono") && !name.contains("il2cpp")))
+    })
+}
+
+/// Check if BepInEx is installed. Its doorstop may be Silk's, which loads BepInEx itself.
 pub fn is_bepinex_installed(game_path: &str) -> bool {
     let game_dir = PathBuf::from(game_path);
-    let bepinex_dir = game_dir.join("BepInEx");
-    let doorstop_dll = game_dir.join("winhttp.dll");
     
-    bepinex_dir.exists() && doorstop_dll.exists()
+    game_dir.join("BepInEx").join("core").is_dir() && doorstop::detect(&game_dir).is_some()
+}
+
+/// Read the installed version from the BepInEx core assembly, falling back to the
+/// version Entwine recorded when it installed BepInEx
+fn detect_version(game_dir: &Path) -> Option<String> {
+    for relative in CORE_ASSEMBLIES {
+        let path = game_dir.join(relative);
+        if !path.is_file() {
+            continue;
+        }
+        match assembly::read_assembly(&path) {
+            Ok(Some(info)) => return Some(info.mod_version()),
+            Ok(None) => warn!("{} is not a .NET assembly", path.display()),
+            Err(e) => warn!("{}", e),
+        }
+    }
+
+    manifest::load(game_dir, manifest::BEPINEX_COMPONENT).map(|m| m.version)
 }
 
-/// Get BepInEx version info
+/// Get the installed BepInEx version
 pub fn get_bepinex_version(game_path: &str) -> Result<String, String> {
-    if is_bepinex_installed(game_path) {
-        Ok(BEPINEX_VERSION.to_string())
-    } else {
-        Err("BepInEx is not installed".to_string())
+    if !is_bepinex_installed(game_path) {
+        return Err("BepInEx is not installed".to_string());
     }
+    detect_version(Path::new(game_path)).ok_or_else(|| "Could not determine the BepInEx version".to_string())
+}
+
+/// List the BepInEx releases on a channel that have a build for the game, newest first
+pub async fn list_bepinex_releases(channel: ReleaseChannel) -> Result<Vec<BepInExRelease>, String> {
+    let items = GitHubClient::from_settings()
+        .get_all_pages(&format!("{}?per_page=100", BEPINEX_RELEASES_API))
+        .await?;
+
+    let mut releases: Vec<BepInExRelease> = items
+        .iter()
+        .filter_map(BepInExRelease::from_json)
+        .filter(|r| channel == ReleaseChannel::Prerelease || !r.prerelease)
+        .collect();
+    releases.sort_by(|a, b| {
+        match (version::parse_version_lenient(&a.version), version::parse_version_lenient(&b.version)) {
+            (Some(av), Some(bv)) => bv.cmp(&av),
+            _ => b.version.cmp(&a.version),
+        }
+    });
+
+    Ok(releases)
 }
 
-/// Install BepInEx with Silk's doorstop config
-pub async fn install_bepinex(game_path: &str, window: tauri::Window) -> Result<(), String> {
+/// Download the BepInEx release archive, verified against the release asset's size and digest
+pub async fn fetch_bepinex_archive(
+    bepinex_version: &str,
+    window: &tauri::Window,
+    operation: &Operation,
+) -> Result<download::DownloadedFile, String> {
+    let tag = format!("v{}", bepinex_version.trim_start_matches('v'));
+    let json = version::fetch_release_json(BEPINEX_RELEASES_API, &tag)
+        .await
+        .map_err(|e| format!("Could not verify BepInEx {} download: {}", bepinex_version, e))?;
+    let release = BepInExRelease::from_json(&json)
+        .ok_or_else(|| format!("BepInEx {} has no Windows x64 build", bepinex_version))?;
+    
+    let _ = window.emit("install-progress", format!("Downloading BepInEx {}...", release.version));
+    
+    let client = reqwest::Client::new();
+    let asset = &release.asset;
+    let expected = download::ExpectedDownload::new(Some(asset.size), asset.digest.as_deref());
+    download::download_to_file(&client, &asset.download_url, &expected, "BepInEx", window, operation).await
+}
+
+/// Install a BepInEx version with Silk's doorstop config, replacing any installed version
+pub async fn install_bepinex(
+    game_path: &str,
+    bepinex_version: &str,
+    window: tauri::Window,
+    operation: &Operation,
+) -> Result<(), String> {
     let game_dir = PathBuf::from(game_path);
     
     if !game_dir.exists() {
         return Err("Game path does not exist".to_string());
     }
     
-    let _ = window.emit("install-progress", "Downloading BepInEx...");
+    let downloaded = fetch_bepinex_archive(bepinex_version, &window, operation).await?;
     
-    // Download BepInEx
-    let client = reqwest::Client::new();
-    let response = client
-        .get(BEPINEX_DOWNLOAD_URL)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to download BepInEx: {}", e))?;
+    let _ = window.emit("install-progress", "Extracting BepInEx...");
+    download::emit_progress(&window, "BepInEx", download::DownloadPhase::Extracting, downloaded.size, Some(downloaded.size), 0);
     
-    let bytes = response
-        .bytes()
-        .await
-        .map_err(|e| format!("Failed to read BepInEx download: {}", e))?;
+    // Stage the new files next to the game so a failure leaves the current install alone
+    let mut transaction = transaction::Transaction::begin(&game_dir)?;
+    let staging_dir = transaction.staging_dir();
     
-    let _ = window.emit("install-progress", "Extracting BepInEx...");
+    // The BepInEx folder and the doorstop files next to the game executable
+    extract::extract_zip(
+        downloaded.open()?,
+        &staging_dir,
+        &extract::Limits::default(),
+        |path| {
+            path.starts_with("BepInEx")
+                || path.to_str().is_some_and(|p| doorstop::DOORSTOP_FILES.contains(&p))
+        },
+        Some(operation),
+    )
+    .map_err(|e| format!("Failed to extract BepInEx: {}", e))?;
     
-    // Create a temp file for the zip
-    let temp_dir = std::env::temp_dir();
-    let zip_path = temp_dir.join("bepinex_download.zip");
-    
-    fs::write(&zip_path, &bytes)
-        .map_err(|e| format!("Failed to write temp file: {}", e))?;
-    
-    // Extract the zip to game directory
-    let file = fs::File::open(&zip_path)
-        .map_err(|e| format!("Failed to open zip file: {}", e))?;
-    
-    let mut archive = zip::ZipArchive::new(file)
-        .map_err(|e| format!("Failed to read zip archive: {}", e))?;
-    
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i)
-            .map_err(|e| format!("Failed to read zip entry: {}", e))?;
-        
-        let file_path = file.mangled_name();
-        let file_path_str = file_path.to_string_lossy();
-        
-        // Only extract files from the BepInEx folder (skip root directory files)
-        if !file_path_str.starts_with("BepInEx/") && !file_path_str.starts_with("BepInEx\\") {
-            continue;
-        }
-        
-        let outpath = game_dir.join(&file_path);
-        
-        if file.name().ends_with('/') {
-            fs::create_dir_all(&outpath)
-                .map_err(|e| format!("Failed to create directory: {}", e))?;
-        } else {
-            if let Some(parent) = outpath.parent() {
-                fs::create_dir_all(parent)
-                    .map_err(|e| format!("Failed to create parent directory: {}", e))?;
-            }
-            
-            let mut outfile = fs::File::create(&outpath)
-                .map_err(|e| format!("Failed to create file: {}", e))?;
-            
-            io::copy(&mut file, &mut outfile)
-                .map_err(|e| format!("Failed to write file: {}", e))?;
+    if !staging_dir.join("BepInEx").join("core").is_dir() {
+        return Err("The BepInEx archive does not contain BepInEx/core".to_string());
+    }
+    let doorstop_shared = doorstop::prepare_staged(&game_dir, &staging_dir, Loader::BepInEx)?;
+    
+    let mut new_manifest = manifest::InstallManifest::from_staged(
+        manifest::BEPINEX_COMPONENT,
+        bepinex_version.trim_start_matches('v'),
+        &staging_dir,
+        &[PathBuf::new()],
+    )?;
+    
+    // Past this point the install is no longer cancellable; replaced files are restored on failure
+    operation.check()?;
+    transaction.place_tree(&staging_dir)?;
+    
+    // Files the previous version installed that this one doesn't ship
+    if let Some(old_manifest) = manifest::load(&game_dir, manifest::BEPINEX_COMPONENT) {
+        for stale in manifest::stale_files(&game_dir, &old_manifest, &new_manifest) {
+            transaction.remove(&stale)?;
         }
     }
+    if doorstop_shared {
+        doorstop::record_shared(&game_dir, &mut new_manifest, &mut transaction)?;
+    }
+    transaction.back_up(&manifest::manifest_path(manifest::BEPINEX_COMPONENT))?;
+    manifest::save(&game_dir, &new_manifest)?;
+    transaction.commit();
     
-    // Clean up temp file
-    let _ = fs::remove_file(&zip_path);
+    download::emit_progress(&window, "BepInEx", download::DownloadPhase::Done, downloaded.size, Some(downloaded.size), 0);
     
     let _ = window.emit("install-progress", "BepInEx installed successfully!");
     
@@ -103,7 +220,10 @@ pub async fn install_bepinex(game_path: &str, window: tauri::Window) -> Result<(
 }
 
 /// Uninstall BepInEx
-pub async fn uninstall_bepinex(game_path: &str, window: tauri::Window) -> Result<(), String> {
+pub async fn uninstall_bepinex(
+    game_path: &str,
+    window: tauri::Window,
+) -> Result<manifest::UninstallReport, String> {
     let game_dir = PathBuf::from(game_path);
     
     if !game_dir.exists() {
@@ -112,14 +232,42 @@ pub async fn uninstall_bepinex(game_path: &str, window: tauri::Window) -> Result
     
     let _ = window.emit("install-progress", "Uninstalling BepInEx...");
     
+    let report = match manifest::load(&game_dir, manifest::BEPINEX_COMPONENT) {
+        Some(install_manifest) => manifest::uninstall(&game_dir, &install_manifest, None)?,
+        None => uninstall_without_manifest(&game_dir)?,
+    };
+    
+    let _ = window.emit("install-progress", "BepInEx uninstalled successfully!");
+    
+    Ok(report)
+}
+
+/// Uninstall a BepInEx install from before manifests were recorded
+fn uninstall_without_manifest(game_dir: &Path) -> Result<manifest::UninstallReport, String> {
+    let mut report = manifest::UninstallReport::default();
+    
     // Remove BepInEx directory
     let bepinex_dir = game_dir.join("BepInEx");
     if bepinex_dir.exists() {
         fs::remove_dir_all(&bepinex_dir)
             .map_err(|e| format!("Failed to remove BepInEx directory: {}", e))?;
+        report.removed.push("BepInEx".to_string());
     }
     
-    let _ = window.emit("install-progress", "BepInEx uninstalled successfully!");
+    // The doorstop files are Silk's too while Silk is installed
+    let silk_installed = game_dir.join("Silk").exists();
+    for name in doorstop::DOORSTOP_FILES {
+        let path = game_dir.join(name);
+        if !path.exists() {
+            continue;
+        }
+        if silk_installed {
+            report.shared.push(name.to_string());
+        } else {
+            fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {}", name, e))?;
+            report.removed.push(name.to_string());
+        }
+    }
     
-    Ok(())
+    Ok(report)
 }
@@ -0,0 +1,733 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How a setting is edited, from its `# Setting type:` and `# Acceptable values:` comments
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueKind {
+    Boolean,
+    Integer,
+    Float,
+    String,
+    /// One of the acceptable values
+    Enum,
+    /// Any combination of the acceptable values, separated by commas
+    Flags,
+}
+
+/// Inclusive bounds from `# Acceptable value range: From X to Y`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValueRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+/// A `Key = value` line and the comments BepInEx writes above it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigEntry {
+    pub key: String,
+    /// Strings are unescaped; everything else is the text from the file
+    pub value: String,
+    pub description: Option<String>,
+    /// The .NET type, e.g. `Boolean`, `Single` or `KeyCode`
+    pub setting_type: Option<String>,
+    pub kind: ValueKind,
+    pub default_value: Option<String>,
+    pub acceptable_values: Vec<String>,
+    pub range: Option<ValueRange>,
+    #[serde(skip)]
+    line: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigSection {
+    pub name: String,
+    pub entries: Vec<ConfigEntry>,
+}
+
+/// A `.cfg` file under `BepInEx/config`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BepInExConfigFile {
+    /// File name, e.g. `com.example.plugin.cfg`
+    pub file_name: String,
+    pub plugin_name: Option<String>,
+    pub plugin_version: Option<String>,
+    pub plugin_guid: Option<String>,
+    pub sections: Vec<ConfigSection>,
+}
+
+/// Comments collected above an entry
+#[derive(Default)]
+struct PendingComments {
+    description: Vec<String>,
+    setting_type: Option<String>,
+    default_value: Option<String>,
+    acceptable_values: Vec<String>,
+    range: Option<ValueRange>,
+    multiple: bool,
+}
+
+impl PendingComments {
+    /// Read a `# Name: value` comment, ignoring ones BepInEx doesn't write
+    fn read(&mut self, comment: &str) {
+        if comment.starts_with("Multiple values can be set at the same time") {
+            self.multiple = true;
+        } else if let Some(value) = comment.strip_prefix("Setting type:") {
+            self.setting_type = Some(value.trim().to_string());
+        } else if let Some(value) = comment.strip_prefix("Default value:") {
+            self.default_value = Some(value.trim().to_string());
+        } else if let Some(value) = comment.strip_prefix("Acceptable values:") {
+            self.acceptable_values = value.split(',').map(|v| v.trim().to_string()).filter(|v| !v.is_empty()).collect();
+        } else if let Some(value) = comment.strip_prefix("Acceptable value range:") {
+            self.range = parse_range(value);
+        }
+    }
+}
+
+/// Parse `From 0 to 10`
+fn parse_range(text: &str) -> Option<ValueRange> {
+    let (min, max) = text.trim().strip_prefix("From ")?.split_once(" to ")?;
+    Some(ValueRange {
+        min: min.trim().parse().ok()?,
+        max: max.trim().parse().ok()?,
+    })
+}
+
+/// Bounds of the .NET integer types
+fn integer_bounds(setting_type: &str) -> Option<(i128, i128)> {
+    let bounds = match setting_type {
+        "Byte" => (u8::MIN as i128, u8::MAX as i128),
+        "SByte" => (i8::MIN as i128, i8::MAX as i128),
+        "Int16" => (i16::MIN as i128, i16::MAX as i128),
+        "UInt16" => (u16::MIN as i128, u16::MAX as i128),
+        "Int32" => (i32::MIN as i128, i32::MAX as i128),
+        "UInt32" => (u32::MIN as i128, u32::MAX as i128),
+        "Int64" => (i64::MIN as i128, i64::MAX as i128),
+        "UInt64" => (u64::MIN as i128, u64::MAX as i128),
+        _ => return None,
+    };
+    Some(bounds)
+}
+
+fn value_kind(comments: &PendingComments) -> ValueKind {
+    let setting_type = comments.setting_type.as_deref().unwrap_or_default();
+    if !comments.acceptable_values.is_empty() {
+        return if comments.multiple { ValueKind::Flags } else { ValueKind::Enum };
+    }
+    match setting_type {
+        "Boolean" => ValueKind::Boolean,
+        "Single" | "Double" | "Decimal" => ValueKind::Float,
+        _ if integer_bounds(setting_type).is_some() => ValueKind::Integer,
+        _ => ValueKind::String,
+    }
+}
+
+/// Undo the escaping BepInEx applies to string values
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('t') => result.push('\t'),
+            Some('0') => result.push('\0'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            '\0' => result.push_str("\\0"),
+            '"' => result.push_str("\\\""),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+fn is_string_type(setting_type: Option<&str>) -> bool {
+    setting_type == Some("String")
+}
+
+/// A parsed `.cfg` file that remembers its lines, so writes only touch the changed value
+struct ConfigDocument {
+    lines: Vec<String>,
+    line_ending: &'static str,
+    trailing_newline: bool,
+    file: BepInExConfigFile,
+}
+
+impl ConfigDocument {
+    fn parse(file_name: &str, content: &str) -> ConfigDocument {
+        let lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
+        let mut file = BepInExConfigFile {
+            file_name: file_name.to_string(),
+            plugin_name: None,
+            plugin_version: None,
+            plugin_guid: None,
+            sections: Vec::new(),
+        };
+        let mut pending = PendingComments::default();
+
+        for (index, line) in lines.iter().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                pending = PendingComments::default();
+            } else if let Some(text) = trimmed.strip_prefix("##") {
+                let text = text.trim();
+                if file.sections.is_empty() {
+                    // File header written by the plugin's ConfigFile
+                    if let Some(plugin) = text.strip_prefix("Settings file was created by plugin ") {
+                        match plugin.rsplit_once(" v") {
+                            Some((name, version)) => {
+                                file.plugin_name = Some(name.to_string());
+                                file.plugin_version = Some(version.to_string());
+                            }
+                            None => file.plugin_name = Some(plugin.to_string()),
+                        }
+                        continue;
+                    }
+                    if let Some(guid) = text.strip_prefix("Plugin GUID:") {
+                        file.plugin_guid = Some(guid.trim().to_string());
+                        continue;
+                    }
+                }
+                pending.description.push(text.to_string());
+            } else if let Some(comment) = trimmed.strip_prefix('#') {
+                pending.read(comment.trim());
+            } else if let Some(name) = trimmed.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+                file.sections.push(ConfigSection {
+                    name: name.trim().to_string(),
+                    entries: Vec::new(),
+                });
+                pending = PendingComments::default();
+            } else if let Some((key, value)) = trimmed.split_once('=') {
+                let comments = std::mem::take(&mut pending);
+                let string_type = is_string_type(comments.setting_type.as_deref());
+                let read_value = |v: &str| if string_type { unescape(v) } else { v.to_string() };
+
+                if file.sections.is_empty() {
+                    file.sections.push(ConfigSection {
+                        name: String::new(),
+                        entries: Vec::new(),
+                    });
+                }
+                let entry = ConfigEntry {
+                    key: key.trim().to_string(),
+                    value: read_value(value.trim()),
+                    description: Some(comments.description.join("\n")).filter(|d| !d.is_empty()),
+                    kind: value_kind(&comments),
+                    default_value: comments.default_value.as_deref().map(read_value),
+                    setting_type: comments.setting_type,
+                    acceptable_values: comments.acceptable_values,
+                    range: comments.range,
+                    line: index,
+                };
+                if let Some(section) = file.sections.last_mut() {
+                    section.entries.push(entry);
+                }
+            }
+        }
+
+        ConfigDocument {
+            lines,
+            line_ending: if content.contains("\r\n") { "\r\n" } else { "\n" },
+            trailing_newline: content.ends_with('\n'),
+            file,
+        }
+    }
+
+    fn load(path: &Path) -> Result<ConfigDocument, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("Failed to read config file: {}", e))?;
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        Ok(ConfigDocument::parse(&file_name, &content))
+    }
+
+    fn save(&self, path: &Path) -> Result<(), String> {
+        let mut content = self.lines.join(self.line_ending);
+        if self.trailing_newline {
+            content.push_str(self.line_ending);
+        }
+        fs::write(path, content).map_err(|e| format!("Failed to write config file: {}", e))
+    }
+
+    fn find(&self, section: &str, key: &str) -> Option<&ConfigEntry> {
+        self.file
+            .sections
+            .iter()
+            .find(|s| s.name == section)?
+            .entries
+            .iter()
+            .find(|e| e.key == key)
+    }
+
+    /// Rewrite an entry's line with a value that has already been validated
+    fn write_value(&mut self, line: usize, key: &str, value: &str) {
+        self.lines[line] = format!("{} = {}", key, value);
+    }
+}
+
+/// The text to store for `value`, checked against the entry's type, range and acceptable values
+fn validate(entry: &ConfigEntry, value: &JsonValue) -> Result<String, String> {
+    let text = match value {
+        JsonValue::String(s) if entry.kind == ValueKind::String => s.clone(),
+        JsonValue::String(s) => s.trim().to_string(),
+        JsonValue::Bool(b) => b.to_string(),
+        JsonValue::Number(n) => n.to_string(),
+        _ => return Err(format!("{} must be a single value", entry.key)),
+    };
+
+    let check_range = |number: f64| match &entry.range {
+        Some(range) if number < range.min || number > range.max => Err(format!(
+            "{} must be between {} and {}",
+            entry.key, range.min, range.max
+        )),
+        _ => Ok(()),
+    };
+    let acceptable = |candidate: &str| {
+        entry
+            .acceptable_values
+            .iter()
+            .find(|v| v.eq_ignore_ascii_case(candidate.trim()))
+            .cloned()
+            .ok_or_else(|| {
+                format!(
+                    "{} is not an acceptable value for {}; expected one of: {}",
+                    candidate.trim(),
+                    entry.key,
+                    entry.acceptable_values.join(", ")
+                )
+            })
+    };
+
+    match entry.kind {
+        ValueKind::Boolean => match text.to_lowercase().as_str() {
+            "true" | "false" => Ok(text.to_lowercase()),
+            _ => Err(format!("{} must be true or false", entry.key)),
+        },
+        ValueKind::Integer => {
+            let number: i128 = text
+                .parse()
+                .map_err(|_| format!("{} must be a whole number", entry.key))?;
+            if let Some((min, max)) = entry.setting_type.as_deref().and_then(integer_bounds) {
+                if number < min || number > max {
+                    return Err(format!("{} must be between {} and {}", entry.key, min, max));
+                }
+            }
+            check_range(number as f64)?;
+            Ok(number.to_string())
+        }
+        ValueKind::Float => {
+            let number: f64 = text
+                .parse()
+                .ok()
+                .filter(|n: &f64| n.is_finite())
+                .ok_or_else(|| format!("{} must be a number", entry.key))?;
+            check_range(number)?;
+            Ok(text)
+        }
+        ValueKind::Enum => acceptable(&text),
+        ValueKind::Flags => {
+            let values = text
+                .split(',')
+                .filter(|v| !v.trim().is_empty())
+                .map(acceptable)
+                .collect::<Result<Vec<_>, _>>()?;
+            if values.is_empty() {
+                return Err(format!("{} needs at least one value", entry.key));
+            }
+            Ok(values.join(", "))
+        }
+        ValueKind::String if is_string_type(entry.setting_type.as_deref()) => Ok(escape(&text)),
+        ValueKind::String => {
+            if text.contains(['\n', '\r']) {
+                return Err(format!("{} must be on a single line", entry.key));
+            }
+            Ok(text)
+        }
+    }
+}
+
+/// Get the path to the BepInEx config directory
+pub fn get_bepinex_config_path(game_path: &str) -> PathBuf {
+    PathBuf::from(game_path).join("BepInEx").join("config")
+}
+
+/// The path of a config file, refusing names that would leave the config directory
+fn config_file_path(game_path: &str, file_name: &str) -> Result<PathBuf, String> {
+    let is_plain_name = Path::new(file_name).file_name().is_some_and(|n| n == file_name);
+    if !is_plain_name || !file_name.ends_with(".cfg") {
+        return Err(format!("Invalid BepInEx config file name: {}", file_name));
+    }
+
+    let path = get_bepinex_config_path(game_path).join(file_name);
+    if !path.is_file() {
+        return Err(format!("BepInEx config file not found: {}", file_name));
+    }
+    Ok(path)
+}
+
+/// List every BepInEx config file with its settings
+pub fn list_bepinex_configs(game_path: &str) -> Result<Vec<BepInExConfigFile>, String> {
+    let config_dir = get_bepinex_config_path(game_path);
+    if !config_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries = fs::read_dir(&config_dir)
+        .map_err(|e| format!("Failed to read BepInEx config directory: {}", e))?;
+
+    let mut configs = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?.path();
+        if !path.is_file() || path.extension().and_then(|s| s.to_str()) != Some("cfg") {
+            continue;
+        }
+        match ConfigDocument::load(&path) {
+            Ok(document) => configs.push(document.file),
+            Err(e) => warn!("Failed to load {}: {}", path.display(), e),
+        }
+    }
+
+    configs.sort_by_key(|c| c.file_name.to_lowercase());
+    Ok(configs)
+}
+
+/// Load a specific BepInEx config file
+pub fn load_bepinex_config(game_path: &str, file_name: &str) -> Result<BepInExConfigFile, String> {
+    ConfigDocument::load(&config_file_path(game_path, file_name)?).map(|d| d.file)
+}
+
+/// Set one setting, leaving every other line of the file as it was
+pub fn set_bepinex_config_value(
+    game_path: &str,
+    file_name: &str,
+    section: &str,
+    key: &str,
+    value: JsonValue,
+) -> Result<(), String> {
+    let path = config_file_path(game_path, file_name)?;
+    let mut document = ConfigDocument::load(&path)?;
+
+    let entry = document
+        .find(section, key)
+        .ok_or_else(|| format!("No setting {} in [{}] of {}", key, section, file_name))?;
+    let text = validate(entry, &value)?;
+    let (line, key) = (entry.line, entry.key.clone());
+
+    document.write_value(line, &key, &text);
+    document.save(&path)
+}
+
+/// Reset settings that declare a default value back to it, keeping the file's comments.
+/// `section` limits the reset to one section, and `key` to one setting in that section.
+pub fn reset_bepinex_config(
+    game_path: &str,
+    file_name: &str,
+    section: Option<&str>,
+    key: Option<&str>,
+) -> Result<(), String> {
+    let path = config_file_path(game_path, file_name)?;
+    let mut document = ConfigDocument::load(&path)?;
+
+    let entries: Vec<&ConfigEntry> = match (section, key) {
+        (Some(section), Some(key)) => {
+            let entry = document
+                .find(section, key)
+                .ok_or_else(|| format!("No setting {} in [{}] of {}", key, section, file_name))?;
+            if entry.default_value.is_none() {
+                return Err(format!("{} has no default value", key));
+            }
+            vec![entry]
+        }
+        (Some(section), None) => document
+            .file
+            .sections
+            .iter()
+            .find(|s| s.name == section)
+            .ok_or_else(|| format!("No section [{}] in {}", section, file_name))?
+            .entries
+            .iter()
+            .collect(),
+        (None, None) => document.file.sections.iter().flat_map(|s| &s.entries).collect(),
+        (None, Some(key)) => return Err(format!("No section given for {}", key)),
+    };
+
+    let defaults: Vec<(usize, String, String)> = entries
+        .into_iter()
+        .filter_map(|entry| {
+            let default = entry.default_value.as_ref()?;
+            let text = if is_string_type(entry.setting_type.as_deref()) {
+                escape(default)
+            } else {
+                default.clone()
+            };
+            Some((entry.line, entry.key.clone(), text))
+        })
+        .collect();
+
+    for (line, key, text) in defaults {
+        document.write_value(line, &key, &text);
+    }
+    document.save(&path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const SAMPLE: &str = "## Settings file was created by plugin Example Plugin v1.2.0\r
+## Plugin GUID: com.example.plugin\r
+\r
+[General]\r
+\r
+## Whether the plugin runs\r
+# Setting type: Boolean\r
+# Default value: true\r
+Enabled = false\r
+\r
+## How fast things move\r
+# Setting type: Single\r
+# Default value: 1.5\r
+# Acceptable value range: From 0 to 10\r
+Speed = 2\r
+\r
+# Setting type: Byte\r
+# Default value: 3\r
+Lives = 3\r
+\r
+[Display]\r
+\r
+# Setting type: KeyCode\r
+# Default value: F1\r
+# Acceptable values: None, F1, F2, Escape\r
+Toggle = F2\r
+\r
+# Setting type: LogLevel\r
+# Default value: Error, Warning\r
+# Acceptable values: None, Error, Warning, Info, All\r
+# Multiple values can be set at the same time by separating them with , (e.g. Debug, Warning)\r
+Levels = Error, Warning, Info\r
+\r
+# Setting type: String\r
+# Default value: Hello\\nWorld\r
+Greeting = Line one\\nLine \\\"two\\\"\r
+";
+
+    fn document() -> ConfigDocument {
+        ConfigDocument::parse("com.example.plugin.cfg", SAMPLE)
+    }
+
+    fn entry<'a>(document: &'a ConfigDocument, section: &str, key: &str) -> &'a ConfigEntry {
+        document.find(section, key).unwrap()
+    }
+
+    fn temp_game(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("entwine-bepinex-config-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("BepInEx").join("config")).unwrap();
+        fs::write(dir.join("BepInEx/config/com.example.plugin.cfg"), SAMPLE).unwrap();
+        dir
+    }
+
+    fn reload(game_dir: &Path) -> ConfigDocument {
+        ConfigDocument::load(&game_dir.join("BepInEx/config/com.example.plugin.cfg")).unwrap()
+    }
+
+    #[test]
+    fn parses_header_and_sections() {
+        let document = document();
+        assert_eq!(document.file.plugin_name.as_deref(), Some("Example Plugin"));
+        assert_eq!(document.file.plugin_version.as_deref(), Some("1.2.0"));
+        assert_eq!(document.file.plugin_guid.as_deref(), Some("com.example.plugin"));
+
+        let sections: Vec<(&str, usize)> =
+            document.file.sections.iter().map(|s| (s.name.as_str(), s.entries.len())).collect();
+        assert_eq!(sections, vec![("General", 3), ("Display", 3)]);
+    }
+
+    #[test]
+    fn reads_entry_kinds_from_comments() {
+        let document = document();
+
+        let enabled = entry(&document, "General", "Enabled");
+        assert_eq!(enabled.kind, ValueKind::Boolean);
+        assert_eq!(enabled.description.as_deref(), Some("Whether the plugin runs"));
+        assert_eq!(enabled.default_value.as_deref(), Some("true"));
+
+        let speed = entry(&document, "General", "Speed");
+        assert_eq!(speed.kind, ValueKind::Float);
+        let range = speed.range.as_ref().unwrap();
+        assert_eq!((range.min, range.max), (0.0, 10.0));
+
+        assert_eq!(entry(&document, "General", "Lives").kind, ValueKind::Integer);
+
+        let toggle = entry(&document, "Display", "Toggle");
+        assert_eq!(toggle.kind, ValueKind::Enum);
+        assert_eq!(toggle.acceptable_values, vec!["None", "F1", "F2", "Escape"]);
+
+        assert_eq!(entry(&document, "Display", "Levels").kind, ValueKind::Flags);
+
+        let greeting = entry(&document, "Display", "Greeting");
+        assert_eq!(greeting.kind, ValueKind::String);
+        assert_eq!(greeting.value, "Line one\nLine \"two\"");
+        assert_eq!(greeting.default_value.as_deref(), Some("Hello\nWorld"));
+    }
+
+    #[test]
+    fn parses_ranges() {
+        let range = parse_range(" From -1.5 to 2").unwrap();
+        assert_eq!((range.min, range.max), (-1.5, 2.0));
+        assert!(parse_range("Between 1 and 2").is_none());
+        assert!(parse_range("From a to b").is_none());
+    }
+
+    #[test]
+    fn escapes_round_trip() {
+        for text in ["plain", "two\nlines", "tab\there", "back\\slash", "\"quoted\"", "cr\r\n", "nul\0"] {
+            assert_eq!(unescape(&escape(text)), text);
+        }
+        assert_eq!(escape("a\"b\\c\n"), "a\\\"b\\\\c\\n");
+        // A trailing backslash is kept as is
+        assert_eq!(unescape("end\\"), "end\\");
+    }
+
+    #[test]
+    fn validates_booleans_and_numbers() {
+        let document = document();
+        let enabled = entry(&document, "General", "Enabled");
+        assert_eq!(validate(enabled, &json!(true)).unwrap(), "true");
+        assert_eq!(validate(enabled, &json!("FALSE")).unwrap(), "false");
+        assert!(validate(enabled, &json!("yes")).is_err());
+
+        let speed = entry(&document, "General", "Speed");
+        assert_eq!(validate(speed, &json!(2.5)).unwrap(), "2.5");
+        assert_eq!(validate(speed, &json!("10")).unwrap(), "10");
+        assert!(validate(speed, &json!(10.5)).unwrap_err().contains("between 0 and 10"));
+        assert!(validate(speed, &json!("NaN")).is_err());
+
+        // Byte bounds apply even without a declared range
+        let lives = entry(&document, "General", "Lives");
+        assert_eq!(validate(lives, &json!(255)).unwrap(), "255");
+        assert!(validate(lives, &json!(256)).is_err());
+        assert!(validate(lives, &json!(-1)).is_err());
+        assert!(validate(lives, &json!(1.5)).is_err());
+    }
+
+    #[test]
+    fn validates_enums_and_flags() {
+        let document = document();
+        let toggle = entry(&document, "Display", "Toggle");
+        assert_eq!(validate(toggle, &json!("escape")).unwrap(), "Escape");
+        assert!(validate(toggle, &json!("F3")).unwrap_err().contains("None, F1, F2, Escape"));
+
+        let levels = entry(&document, "Display", "Levels");
+        assert_eq!(validate(levels, &json!("info,error")).unwrap(), "Info, Error");
+        assert!(validate(levels, &json!("Error, Debug")).is_err());
+        assert!(validate(levels, &json!(" , ")).is_err());
+        assert!(validate(levels, &json!(["Error"])).is_err());
+    }
+
+    #[test]
+    fn string_values_are_escaped() {
+        let document = document();
+        let greeting = entry(&document, "Display", "Greeting");
+        assert_eq!(validate(greeting, &json!("a\nb")).unwrap(), "a\\nb");
+
+        // Other types shown as text can't hold line breaks
+        let mut other = greeting.clone();
+        other.setting_type = Some("Color".to_string());
+        assert_eq!(validate(&other, &json!("FF0000FF")).unwrap(), "FF0000FF");
+        assert!(validate(&other, &json!("a\nb")).is_err());
+    }
+
+    #[test]
+    fn writes_only_the_changed_line() {
+        let game_dir = temp_game("set");
+        let game_path = game_dir.to_string_lossy();
+
+        set_bepinex_config_value(&game_path, "com.example.plugin.cfg", "Display", "Greeting", json!("Hi \"there\""))
+            .unwrap();
+
+        let written = fs::read_to_string(game_dir.join("BepInEx/config/com.example.plugin.cfg")).unwrap();
+        assert_eq!(written, SAMPLE.replace("Line one\\nLine \\\"two\\\"", "Hi \\\"there\\\""));
+        assert_eq!(entry(&reload(&game_dir), "Display", "Greeting").value, "Hi \"there\"");
+        fs::remove_dir_all(&game_dir).unwrap();
+    }
+
+    #[test]
+    fn resets_one_setting() {
+        let game_dir = temp_game("reset-key");
+        let game_path = game_dir.to_string_lossy();
+
+        reset_bepinex_config(&game_path, "com.example.plugin.cfg", Some("Display"), Some("Greeting")).unwrap();
+
+        let document = reload(&game_dir);
+        assert_eq!(entry(&document, "Display", "Greeting").value, "Hello\nWorld");
+        assert_eq!(entry(&document, "Display", "Toggle").value, "F2");
+        assert_eq!(entry(&document, "General", "Enabled").value, "false");
+        fs::remove_dir_all(&game_dir).unwrap();
+    }
+
+    #[test]
+    fn resets_one_section() {
+        let game_dir = temp_game("reset-section");
+        let game_path = game_dir.to_string_lossy();
+
+        reset_bepinex_config(&game_path, "com.example.plugin.cfg", Some("General"), None).unwrap();
+
+        let document = reload(&game_dir);
+        assert_eq!(entry(&document, "General", "Enabled").value, "true");
+        assert_eq!(entry(&document, "General", "Speed").value, "1.5");
+        assert_eq!(entry(&document, "Display", "Toggle").value, "F2");
+        fs::remove_dir_all(&game_dir).unwrap();
+    }
+
+    #[test]
+    fn resets_the_whole_file() {
+        let game_dir = temp_game("reset-all");
+        let game_path = game_dir.to_string_lossy();
+
+        reset_bepinex_config(&game_path, "com.example.plugin.cfg", None, None).unwrap();
+
+        let document = reload(&game_dir);
+        assert_eq!(entry(&document, "Display", "Toggle").value, "F1");
+        assert_eq!(entry(&document, "Display", "Levels").value, "Error, Warning");
+        assert!(document.lines.iter().any(|l| l == "## How fast things move"));
+        fs::remove_dir_all(&game_dir).unwrap();
+    }
+
+    #[test]
+    fn reset_rejects_unknown_settings() {
+        let game_dir = temp_game("reset-unknown");
+        let game_path = game_dir.to_string_lossy();
+
+        assert!(reset_bepinex_config(&game_path, "com.example.plugin.cfg", Some("General"), Some("Missing")).is_err());
+        assert!(reset_bepinex_config(&game_path, "com.example.plugin.cfg", Some("Missing"), None).is_err());
+        assert!(reset_bepinex_config(&game_path, "com.example.plugin.cfg", None, Some("Enabled")).is_err());
+        assert!(reset_bepinex_config(&game_path, "../com.example.plugin.cfg", None, None).is_err());
+        fs::remove_dir_all(&game_dir).unwrap();
+    }
+}
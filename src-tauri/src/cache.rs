@@ -0,0 +1,199 @@
+use crate::download::to_hex;
+use crate::Mod;
+use base64::Engine;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const REGISTRY_FILE: &str = "registry.json";
+
+/// The mod list returned by `fetch_mods`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModRegistry {
+    pub mods: Vec<Mod>,
+    /// Seconds since the Unix epoch of the last successful fetch, when the registry
+    /// couldn't be reached and the cached copy is shown instead
+    pub stale_since: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedRegistry {
+    fetched_at: u64,
+    mods: Vec<Mod>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// A folder under Entwine's cache directory, created if needed
+pub fn cache_dir(name: &str) -> Result<PathBuf, String> {
+    let dir = dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("entwine")
+        .join(name);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create cache directory {}: {}", name, e))?;
+    Ok(dir)
+}
+
+/// File name used to cache something fetched from `url`
+pub fn cache_key(url: &str) -> String {
+    to_hex(&Sha256::digest(url.as_bytes()))[..32].to_string()
+}
+
+/// Remember the registry from a successful fetch
+pub fn save_registry(mods: &[Mod]) {
+    let path = match cache_dir("") {
+        Ok(dir) => dir.join(REGISTRY_FILE),
+        Err(e) => {
+            warn!("Failed to cache the mod registry: {}", e);
+            return;
+        }
+    };
+
+    let cached = CachedRegistry {
+        fetched_at: now(),
+        mods: mods.to_vec(),
+    };
+    match serde_json::to_string(&cached) {
+        Ok(json) => {
+            if let Err(e) = fs::write(&path, json) {
+                warn!("Failed to cache the mod registry: {}", e);
+            }
+        }
+        Err(e) => warn!("Failed to serialize the mod registry: {}", e),
+    }
+}
+
+/// The registry from the last successful fetch, used while the registry can't be reached
+pub fn load_registry() -> Option<ModRegistry> {
+    let content = fs::read_to_string(cache_dir("").ok()?.join(REGISTRY_FILE)).ok()?;
+    let cached: CachedRegistry = match serde_json::from_str(&content) {
+        Ok(cached) => cached,
+        Err(e) => {
+            warn!("Ignoring unreadable registry cache: {}", e);
+            return None;
+        }
+    };
+
+    Some(ModRegistry {
+        mods: cached.mods,
+        stale_since: Some(cached.fetched_at),
+    })
+}
+
+fn image_type(url: &str, content_type: Option<&str>) -> String {
+    if let Some(content_type) = content_type.filter(|t| t.starts_with("image/")) {
+        return content_type.to_string();
+    }
+
+    let lower = url.to_lowercase();
+    let extension = lower.rsplit('.').next().unwrap_or_default();
+    match extension {
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        _ => "image/png",
+    }
+    .to_string()
+}
+
+/// Image bytes as a `data:` URL, typed from `content_type` or the file extension
+pub fn data_url(path: &str, content_type: Option<&str>, bytes: &[u8]) -> String {
+    format!(
+        "data:{};base64,{}",
+        image_type(path, content_type),
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    )
+}
+
+/// A mod icon as a `data:` URL, downloaded once and then served from the cache
+pub async fn mod_icon(client: &reqwest::Client, url: &str) -> Result<String, String> {
+    let dir = cache_dir("icons")?;
+    let key = cache_key(url);
+    let data_path = dir.join(&key);
+    let type_path = dir.join(format!("{}.type", key));
+
+    let (bytes, mime) = match fs::read(&data_path) {
+        Ok(bytes) => {
+            let mime = fs::read_to_string(&type_path).unwrap_or_else(|_| image_type(url, None));
+            (bytes, mime)
+        }
+        Err(_) => {
+            let response = client
+                .get(url)
+                .header("User-Agent", "entwine-app")
+                .send()
+                .await
+                .map_err(|e| format!("Failed to fetch icon: {}", e))?;
+            if !response.status().is_success() {
+                return Err(format!("Failed to fetch icon: server responded with {}", response.status()));
+            }
+
+            let content_type = response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string());
+            let mime = image_type(url, content_type.as_deref());
+            let bytes = response
+                .bytes()
+                .await
+                .map_err(|e| format!("Failed to read icon: {}", e))?
+                .to_vec();
+
+            if let Err(e) = fs::write(&data_path, &bytes).and_then(|_| fs::write(&type_path, &mime)) {
+                warn!("Failed to cache icon {}: {}", url, e);
+            }
+            (bytes, mime)
+        }
+    };
+
+    Ok(data_url(url, Some(&mime), &bytes))
+}
+
+/// Remove the oldest files in a cache folder until it fits in `max_size` bytes
+pub fn trim(name: &str, max_size: u64) {
+    let Ok(dir) = cache_dir(name) else {
+        return;
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return;
+    };
+
+    let mut files: Vec<(PathBuf, u64, SystemTime)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let meta = entry.metadata().ok()?;
+            meta.is_file()
+                .then(|| (entry.path(), meta.len(), meta.modified().unwrap_or(UNIX_EPOCH)))
+        })
+        .collect();
+
+    let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+    if total <= max_size {
+        return;
+    }
+
+    files.sort_by_key(|(_, _, modified)| *modified);
+    let mut removed = 0;
+    for (path, size, _) in files {
+        if total <= max_size {
+            break;
+        }
+        if fs::remove_file(&path).is_ok() {
+            total -= size;
+            removed += 1;
+        }
+    }
+    info!("Removed {} old files from the {} cache", removed, name);
+}
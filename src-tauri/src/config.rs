@@ -1,8 +1,9 @@
+use log::warn;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -10,6 +11,132 @@ pub struct ModConfigFile {
     pub mod_id: String,
     pub mod_name: String,
     pub config: HashMap<String, JsonValue>,
+    /// The schema the mod ships for its config, if any
+    #[serde(default)]
+    pub schema: Option<ConfigSchema>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SchemaType {
+    Boolean,
+    Integer,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+/// A mod's description of its config: a subset of JSON Schema with a type, description,
+/// default, range and allowed values per setting, and nested settings under `properties`
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigSchema {
+    #[serde(rename = "type")]
+    pub value_type: Option<SchemaType>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub default: Option<JsonValue>,
+    pub minimum: Option<f64>,
+    pub maximum: Option<f64>,
+    #[serde(rename = "enum")]
+    pub allowed_values: Option<Vec<JsonValue>>,
+    /// Schema for each item of an array
+    pub items: Option<Box<ConfigSchema>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, ConfigSchema>,
+    /// Whether an object may have keys not listed in `properties`
+    #[serde(default)]
+    pub additional_properties: bool,
+}
+
+impl ConfigSchema {
+    /// The schema for a dotted key path; `Ok(None)` when the path is inside an object
+    /// that allows keys it doesn't describe
+    fn lookup(&self, keys: &[&str]) -> Result<Option<&ConfigSchema>, String> {
+        let mut current = self;
+        for (depth, key) in keys.iter().enumerate() {
+            match current.properties.get(*key) {
+                Some(next) => current = next,
+                None if current.additional_properties => return Ok(None),
+                None => return Err(format!("Unknown config key: {}", keys[..=depth].join("."))),
+            }
+        }
+        Ok(Some(current))
+    }
+
+    /// Check a value against this schema; `path` names it in errors
+    fn validate(&self, path: &str, value: &JsonValue) -> Result<(), String> {
+        if let Some(value_type) = self.value_type {
+            let matches = match value_type {
+                SchemaType::Boolean => value.is_boolean(),
+                SchemaType::Integer => value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0),
+                SchemaType::Number => value.is_number(),
+                SchemaType::String => value.is_string(),
+                SchemaType::Array => value.is_array(),
+                SchemaType::Object => value.is_object(),
+            };
+            if !matches {
+                return Err(format!("{} must be of type {}", path, format!("{:?}", value_type).to_lowercase()));
+            }
+        }
+
+        if let Some(allowed) = &self.allowed_values {
+            if !allowed.contains(value) {
+                let listed: Vec<String> = allowed.iter().map(|v| v.to_string()).collect();
+                return Err(format!("{} must be one of: {}", path, listed.join(", ")));
+            }
+        }
+
+        if let Some(number) = value.as_f64() {
+            if self.minimum.is_some_and(|min| number < min) || self.maximum.is_some_and(|max| number > max) {
+                let bound = |b: Option<f64>| b.map(|b| b.to_string()).unwrap_or_else(|| "any".to_string());
+                return Err(format!(
+                    "{} must be between {} and {}",
+                    path,
+                    bound(self.minimum),
+                    bound(self.maximum)
+                ));
+            }
+        }
+
+        match value {
+            JsonValue::Array(items) => {
+                if let Some(item_schema) = &self.items {
+                    for (i, item) in items.iter().enumerate() {
+                        item_schema.validate(&format!("{}[{}]", path, i), item)?;
+                    }
+                }
+            }
+            JsonValue::Object(map) if self.value_type == Some(SchemaType::Object) || !self.properties.is_empty() => {
+                for (key, item) in map {
+                    let item_path = format!("{}.{}", path, key);
+                    match self.properties.get(key) {
+                        Some(item_schema) => item_schema.validate(&item_path, item)?,
+                        None if self.additional_properties => {}
+                        None => return Err(format!("Unknown config key: {}", item_path)),
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Every setting below this one with a default, as dotted key paths
+    fn defaults(&self, prefix: &str, out: &mut Vec<(String, JsonValue)>) {
+        if self.properties.is_empty() {
+            if let Some(default) = &self.default {
+                out.push((prefix.to_string(), default.clone()));
+            }
+            return;
+        }
+        for (key, schema) in &self.properties {
+            let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+            schema.defaults(&path, out);
+        }
+    }
 }
 
 /// Get the path to the Silk config directory
@@ -18,10 +145,51 @@ fn get_config_path(game_path: &str) -> PathBuf {
 }
 
 /// Get the path to the mods config directory
-fn get_mods_config_path(game_path: &str) -> PathBuf {
+pub fn get_mods_config_path(game_path: &str) -> PathBuf {
     get_config_path(game_path).join("Mods")
 }
 
+/// Load the schema a mod ships for its config: `Silk/Config/Mods/<mod id>.schema.json`,
+/// or `config.schema.json` in the mod's folder under `Silk/Mods`
+pub fn load_config_schema(game_path: &str, mod_id: &str) -> Result<Option<ConfigSchema>, String> {
+    let candidates = [
+        get_mods_config_path(game_path).join(format!("{}.schema.json", mod_id)),
+        Path::new(game_path).join("Silk").join("Mods").join(mod_id).join("config.schema.json"),
+    ];
+    let Some(path) = candidates.iter().find(|p| p.is_file()) else {
+        return Ok(None);
+    };
+
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read config schema: {}", e))?;
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| format!("Invalid config schema for {}: {}", mod_id, e))
+}
+
+/// The raw YAML of every mod config, keyed by mod id
+pub fn read_config_files(game_path: &str) -> Result<HashMap<String, String>, String> {
+    let config_dir = get_mods_config_path(game_path);
+    let mut configs = HashMap::new();
+
+    if !config_dir.exists() {
+        return Ok(configs);
+    }
+
+    for entry in fs::read_dir(&config_dir).map_err(|e| format!("Failed to read config directory: {}", e))? {
+        let path = entry.map_err(|e| format!("Failed to read entry: {}", e))?.path();
+        if path.extension().and_then(|s| s.to_str()) != Some("yaml") {
+            continue;
+        }
+        if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+            let content = fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read config {}: {}", stem, e))?;
+            configs.insert(stem.to_string(), content);
+        }
+    }
+
+    Ok(configs)
+}
+
 /// List all available mod config files
 pub fn list_mod_configs(game_path: &str) -> Result<Vec<ModConfigFile>, String> {
     let mods_config_path = get_mods_config_path(game_path);
@@ -44,11 +212,19 @@ pub fn list_mod_configs(game_path: &str) -> Result<Vec<ModConfigFile>, String> {
                 match load_mod_config(game_path, file_stem) {
                     Ok(config) => {
                         // Try to get a friendly name from the config, otherwise use the file name
-                        let mod_name = file_stem.to_string();
+                        let schema = load_config_schema(game_path, file_stem).unwrap_or_else(|e| {
+                            warn!("{}", e);
+                            None
+                        });
+                        let mod_name = schema
+                            .as_ref()
+                            .and_then(|s| s.title.clone())
+                            .unwrap_or_else(|| file_stem.to_string());
                         configs.push(ModConfigFile {
                             mod_id: file_stem.to_string(),
                             mod_name,
                             config,
+                            schema,
                         });
                     }
                     Err(e) => {
@@ -90,7 +266,9 @@ pub fn load_mod_config(game_path: &str, mod_id: &str) -> Result<HashMap<String,
     }
 }
 
-/// Set a specific value in a mod's config
+/// Set a specific value in a mod's config, rewriting only the lines that hold it.
+/// With a schema, the key must be described by it and the value must match; without
+/// one, the key must already exist in the file.
 pub fn set_mod_config_value(
     game_path: &str,
     mod_id: &str,
@@ -103,22 +281,57 @@ pub fn set_mod_config_value(
         return Err(format!("Config file not found for mod: {}", mod_id));
     }
 
+    let keys: Vec<&str> = key.split('.').collect();
+    match load_config_schema(game_path, mod_id)? {
+        Some(schema) => {
+            if let Some(setting) = schema.lookup(&keys)? {
+                setting.validate(key, &value)?;
+            }
+        }
+        None => {
+            let config = load_mod_config(game_path, mod_id)?;
+            let mut current = config.get(keys[0]);
+            for k in &keys[1..] {
+                current = current.and_then(|c| c.get(*k));
+            }
+            if current.is_none() {
+                return Err(format!("Unknown config key: {}", key));
+            }
+        }
+    }
+
+    write_config_value(&config_path, &keys, value)
+}
+
+/// Write one value to a config file, editing it in place where possible
+fn write_config_value(config_path: &Path, keys: &[&str], value: JsonValue) -> Result<(), String> {
     // Load current config
-    let yaml_content = fs::read_to_string(&config_path)
+    let yaml_content = fs::read_to_string(config_path)
         .map_err(|e| format!("Failed to read config file: {}", e))?;
 
     let mut yaml_value: serde_yaml::Value = serde_yaml::from_str(&yaml_content)
         .map_err(|e| format!("Failed to parse YAML: {}", e))?;
 
     // Navigate to the key and set the value
-    let keys: Vec<&str> = key.split('.').collect();
-    set_nested_value(&mut yaml_value, &keys, value)?;
+    let new_value: serde_yaml::Value = serde_json::from_value(value)
+        .map_err(|e| format!("Failed to convert value: {}", e))?;
+    set_nested_value(&mut yaml_value, keys, new_value.clone())?;
 
-    // Write back to file
-    let yaml_string = serde_yaml::to_string(&yaml_value)
-        .map_err(|e| format!("Failed to serialize YAML: {}", e))?;
+    // Edit the file in place when the result reads back as the expected document,
+    // otherwise write the whole document out again
+    let edited = crate::yaml_edit::set_value(&yaml_content, keys, &new_value).filter(|edited| {
+        serde_yaml::from_str::<serde_yaml::Value>(edited).is_ok_and(|parsed| parsed == yaml_value)
+    });
+    let yaml_string = match edited {
+        Some(edited) => edited,
+        None => {
+            warn!("Could not edit {} in place; rewriting {}", keys.join("."), config_path.display());
+            serde_yaml::to_string(&yaml_value)
+                .map_err(|e| format!("Failed to serialize YAML: {}", e))?
+        }
+    };
 
-    fs::write(&config_path, yaml_string)
+    fs::write(config_path, yaml_string)
         .map_err(|e| format!("Failed to write config file: {}", e))?;
 
     Ok(())
@@ -128,21 +341,17 @@ pub fn set_mod_config_value(
 fn set_nested_value(
     current: &mut serde_yaml::Value,
     keys: &[&str],
-    value: JsonValue,
+    value: serde_yaml::Value,
 ) -> Result<(), String> {
     if keys.is_empty() {
         return Err("Empty key path".to_string());
     }
 
     if keys.len() == 1 {
-        // Convert JsonValue to serde_yaml::Value
-        let yaml_value: serde_yaml::Value = serde_json::from_value(value)
-            .map_err(|e| format!("Failed to convert value: {}", e))?;
-        
         if let serde_yaml::Value::Mapping(ref mut map) = current {
             map.insert(
                 serde_yaml::Value::String(keys[0].to_string()),
-                yaml_value,
+                value,
             );
             Ok(())
         } else {
@@ -167,15 +376,234 @@ fn set_nested_value(
     }
 }
 
-/// Reset a mod's config by deleting it (will be recreated with defaults on next load)
-pub fn reset_mod_config(game_path: &str, mod_id: &str) -> Result<(), String> {
+/// Reset one key, or the whole config, to the defaults from the mod's schema. Without a
+/// schema the whole config is reset by deleting it (it is recreated with defaults on next load).
+pub fn reset_mod_config(game_path: &str, mod_id: &str, key: Option<&str>) -> Result<(), String> {
     let config_path = get_mods_config_path(game_path).join(format!("{}.yaml", mod_id));
     
-    if config_path.exists() {
-        fs::remove_file(&config_path)
-            .map_err(|e| format!("Failed to delete config file: {}", e))?;
-        Ok(())
-    } else {
-        Err(format!("Config file not found for mod: {}", mod_id))
+    if !config_path.exists() {
+        return Err(format!("Config file not found for mod: {}", mod_id));
+    }
+
+    let schema = load_config_schema(game_path, mod_id)?;
+    match (schema, key) {
+        (Some(schema), key) => {
+            let mut defaults = Vec::new();
+            match key {
+                Some(key) => {
+                    if let Some(setting) = schema.lookup(&key.split('.').collect::<Vec<_>>())? {
+                        setting.defaults(key, &mut defaults);
+                    }
+                    if defaults.is_empty() {
+                        return Err(format!("{} has no default value", key));
+                    }
+                }
+                None => schema.defaults("", &mut defaults),
+            }
+            for (path, default) in defaults {
+                write_config_value(&config_path, &path.split('.').collect::<Vec<_>>(), default)?;
+            }
+            Ok(())
+        }
+        (None, Some(key)) => Err(format!("{} has no default value: {} doesn't ship a config schema", key, mod_id)),
+        (None, None) => fs::remove_file(&config_path)
+            .map_err(|e| format!("Failed to delete config file: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const CONFIG: &str = "# Example mod\nenabled: true\nplayer:\n  speed: 3 # tiles per second\n  jump: 1.5\nmode: easy\nextra:\n  note: hi\n";
+
+    fn schema() -> ConfigSchema {
+        serde_json::from_value(json!({
+            "title": "Example Mod",
+            "type": "object",
+            "properties": {
+                "enabled": { "type": "boolean", "default": true },
+                "player": {
+                    "type": "object",
+                    "properties": {
+                        "speed": { "type": "integer", "minimum": 1, "maximum": 10, "default": 5 },
+                        "jump": { "type": "number", "default": 2.5 }
+                    }
+                },
+                "mode": { "type": "string", "enum": ["easy", "hard"], "default": "easy" },
+                "extra": { "type": "object", "additionalProperties": true },
+                "tags": { "type": "array", "items": { "type": "string" } }
+            }
+        }))
+        .unwrap()
+    }
+
+    fn temp_game(name: &str, schema: Option<&ConfigSchema>) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("entwine-config-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mods_config = dir.join("Silk").join("Config").join("Mods");
+        fs::create_dir_all(&mods_config).unwrap();
+        fs::write(mods_config.join("example.yaml"), CONFIG).unwrap();
+        if let Some(schema) = schema {
+            fs::write(mods_config.join("example.schema.json"), serde_json::to_string(schema).unwrap()).unwrap();
+        }
+        dir
+    }
+
+    fn read_config(game_dir: &Path) -> String {
+        fs::read_to_string(game_dir.join("Silk/Config/Mods/example.yaml")).unwrap()
+    }
+
+    #[test]
+    fn lookup_rejects_unknown_keys() {
+        let schema = schema();
+        assert!(schema.lookup(&["player", "speed"]).unwrap().is_some());
+        assert_eq!(schema.lookup(&["player", "health"]).unwrap_err(), "Unknown config key: player.health");
+        assert_eq!(schema.lookup(&["missing", "deeper"]).unwrap_err(), "Unknown config key: missing");
+    }
+
+    #[test]
+    fn additional_properties_allow_undescribed_keys() {
+        let schema = schema();
+        assert!(schema.lookup(&["extra", "anything"]).unwrap().is_none());
+
+        let extra = schema.lookup(&["extra"]).unwrap().unwrap();
+        assert!(extra.validate("extra", &json!({ "anything": 1 })).is_ok());
+        let player = schema.lookup(&["player"]).unwrap().unwrap();
+        assert_eq!(
+            player.validate("player", &json!({ "speed": 2, "health": 1 })).unwrap_err(),
+            "Unknown config key: player.health"
+        );
+    }
+
+    #[test]
+    fn integers_and_numbers_differ() {
+        let schema = schema();
+        let speed = schema.lookup(&["player", "speed"]).unwrap().unwrap();
+        assert!(speed.validate("player.speed", &json!(4)).is_ok());
+        assert!(speed.validate("player.speed", &json!(4.0)).is_ok());
+        assert_eq!(speed.validate("player.speed", &json!(4.5)).unwrap_err(), "player.speed must be of type integer");
+        assert!(speed.validate("player.speed", &json!("4")).is_err());
+
+        let jump = schema.lookup(&["player", "jump"]).unwrap().unwrap();
+        assert!(jump.validate("player.jump", &json!(4.5)).is_ok());
+        assert!(jump.validate("player.jump", &json!(4)).is_ok());
+    }
+
+    #[test]
+    fn checks_minimum_and_maximum() {
+        let schema = schema();
+        let speed = schema.lookup(&["player", "speed"]).unwrap().unwrap();
+        assert!(speed.validate("player.speed", &json!(1)).is_ok());
+        assert!(speed.validate("player.speed", &json!(10)).is_ok());
+        assert_eq!(speed.validate("player.speed", &json!(0)).unwrap_err(), "player.speed must be between 1 and 10");
+        assert!(speed.validate("player.speed", &json!(11)).is_err());
+
+        let open_ended = ConfigSchema {
+            minimum: Some(0.5),
+            ..Default::default()
+        };
+        assert_eq!(open_ended.validate("x", &json!(0)).unwrap_err(), "x must be between 0.5 and any");
+    }
+
+    #[test]
+    fn checks_allowed_values_and_items() {
+        let schema = schema();
+        let mode = schema.lookup(&["mode"]).unwrap().unwrap();
+        assert!(mode.validate("mode", &json!("hard")).is_ok());
+        assert_eq!(mode.validate("mode", &json!("HARD")).unwrap_err(), "mode must be one of: \"easy\", \"hard\"");
+
+        let tags = schema.lookup(&["tags"]).unwrap().unwrap();
+        assert!(tags.validate("tags", &json!(["a", "b"])).is_ok());
+        assert_eq!(tags.validate("tags", &json!(["a", 1])).unwrap_err(), "tags[1] must be of type string");
+    }
+
+    #[test]
+    fn collects_nested_defaults() {
+        let mut defaults = Vec::new();
+        schema().defaults("", &mut defaults);
+        assert_eq!(
+            defaults,
+            vec![
+                ("enabled".to_string(), json!(true)),
+                ("mode".to_string(), json!("easy")),
+                ("player.jump".to_string(), json!(2.5)),
+                ("player.speed".to_string(), json!(5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn set_validates_against_the_schema() {
+        let game_dir = temp_game("set-schema", Some(&schema()));
+        let game_path = game_dir.to_string_lossy();
+
+        set_mod_config_value(&game_path, "example", "player.speed", json!(7)).unwrap();
+        assert!(read_config(&game_dir).contains("  speed: 7 # tiles per second\n"));
+
+        assert!(set_mod_config_value(&game_path, "example", "player.speed", json!(70)).is_err());
+        assert!(set_mod_config_value(&game_path, "example", "player.health", json!(1)).is_err());
+        // Keys the schema describes can be added even if the file doesn't have them yet
+        set_mod_config_value(&game_path, "example", "tags", json!(["fast"])).unwrap();
+        assert!(read_config(&game_dir).ends_with("tags: [\"fast\"]\n"));
+        fs::remove_dir_all(&game_dir).unwrap();
+    }
+
+    #[test]
+    fn set_without_schema_needs_an_existing_key() {
+        let game_dir = temp_game("set-no-schema", None);
+        let game_path = game_dir.to_string_lossy();
+
+        set_mod_config_value(&game_path, "example", "mode", json!("anything")).unwrap();
+        assert!(read_config(&game_dir).contains("mode: anything\n"));
+        assert_eq!(
+            set_mod_config_value(&game_path, "example", "player.health", json!(1)).unwrap_err(),
+            "Unknown config key: player.health"
+        );
+        fs::remove_dir_all(&game_dir).unwrap();
+    }
+
+    #[test]
+    fn resets_a_nested_mapping_to_its_defaults() {
+        let game_dir = temp_game("reset-nested", Some(&schema()));
+        let game_path = game_dir.to_string_lossy();
+
+        reset_mod_config(&game_path, "example", Some("player")).unwrap();
+
+        let content = read_config(&game_dir);
+        assert!(content.contains("  speed: 5 # tiles per second\n  jump: 2.5\n"), "{}", content);
+        assert!(content.starts_with("# Example mod\nenabled: true\n"));
+        assert!(content.contains("mode: easy\n"));
+        fs::remove_dir_all(&game_dir).unwrap();
+    }
+
+    #[test]
+    fn resets_a_single_key() {
+        let game_dir = temp_game("reset-key", Some(&schema()));
+        let game_path = game_dir.to_string_lossy();
+
+        reset_mod_config(&game_path, "example", Some("player.jump")).unwrap();
+        let content = read_config(&game_dir);
+        assert!(content.contains("  speed: 3 # tiles per second\n  jump: 2.5\n"), "{}", content);
+
+        assert_eq!(reset_mod_config(&game_path, "example", Some("extra")).unwrap_err(), "extra has no default value");
+        assert!(reset_mod_config(&game_path, "example", Some("player.health")).is_err());
+        fs::remove_dir_all(&game_dir).unwrap();
+    }
+
+    #[test]
+    fn reset_without_schema() {
+        let game_dir = temp_game("reset-no-schema", None);
+        let game_path = game_dir.to_string_lossy();
+
+        // A single key has no default to go back to
+        assert!(reset_mod_config(&game_path, "example", Some("mode")).is_err());
+        assert_eq!(read_config(&game_dir), CONFIG);
+
+        // The whole file is deleted so the mod writes its defaults again
+        reset_mod_config(&game_path, "example", None).unwrap();
+        assert!(!game_dir.join("Silk/Config/Mods/example.yaml").exists());
+        fs::remove_dir_all(&game_dir).unwrap();
     }
 }
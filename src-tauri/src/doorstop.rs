@@ -0,0 +1,362 @@
+use crate::manifest::{self, InstallManifest};
+use crate::transaction::Transaction;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path};
+
+/// The proxy DLL Windows loads from the game folder, which boots the target assembly
+pub const PROXY_DLL: &str = "winhttp.dll";
+pub const CONFIG_FILE: &str = "doorstop_config.ini";
+pub const VERSION_FILE: &str = ".doorstop_version";
+
+/// Doorstop files that sit next to the game executable
+pub const DOORSTOP_FILES: &[&str] = &[PROXY_DLL, CONFIG_FILE, VERSION_FILE];
+
+/// Key naming the assembly doorstop boots: `target_assembly` in doorstop 4, `targetAssembly` in doorstop 3
+const TARGET_KEYS: &[&str] = &["target_assembly", "targetAssembly"];
+/// Semicolon-separated folders searched for assemblies before the game's own
+const SEARCH_PATH_KEYS: &[&str] = &["dll_search_path_override", "dllSearchPathOverride"];
+
+/// The BepInEx 5 and BepInEx 6 preloaders, relative to the game folder
+const BEPINEX_PRELOADERS: &[&str] = &[
+    "BepInEx/core/BepInEx.Preloader.dll",
+    "BepInEx/core/BepInEx.Unity.Mono.Preloader.dll",
+];
+
+/// A mod loader that ships its own doorstop
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Loader {
+    Silk,
+    #[serde(rename = "bepinex")]
+    BepInEx,
+    /// A doorstop that boots something other than Silk or BepInEx
+    Unknown,
+}
+
+/// The doorstop installed in a game folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DoorstopInfo {
+    /// The loader the config boots, i.e. the one `winhttp.dll` belongs to
+    pub owner: Loader,
+    /// From `.doorstop_version`
+    pub version: Option<String>,
+    pub target_assembly: Option<String>,
+    pub enabled: bool,
+    /// Whether the installs of both Silk and BepInEx recorded the proxy
+    pub shared: bool,
+}
+
+/// A doorstop config, edited line by line so comments and layout survive
+struct DoorstopConfig {
+    lines: Vec<String>,
+}
+
+impl DoorstopConfig {
+    fn parse(content: &str) -> DoorstopConfig {
+        DoorstopConfig {
+            lines: content.lines().map(|l| l.to_string()).collect(),
+        }
+    }
+
+    fn load(path: &Path) -> Option<DoorstopConfig> {
+        fs::read_to_string(path).ok().map(|c| DoorstopConfig::parse(&c))
+    }
+
+    fn save(&self, path: &Path) -> Result<(), String> {
+        let mut content = self.lines.join("\r\n");
+        content.push_str("\r\n");
+        fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", CONFIG_FILE, e))
+    }
+
+    /// Every `(section, key, value)`, in file order
+    fn entries(&self) -> Vec<(String, String, String)> {
+        let mut section = String::new();
+        let mut entries = Vec::new();
+        for line in &self.lines {
+            let trimmed = line.trim();
+            if trimmed.starts_with('#') || trimmed.starts_with(';') {
+                continue;
+            }
+            if let Some(name) = trimmed.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+                section = name.trim().to_string();
+            } else if let Some((key, value)) = trimmed.split_once('=') {
+                entries.push((section.clone(), key.trim().to_string(), value.trim().to_string()));
+            }
+        }
+        entries
+    }
+
+    fn get(&self, keys: &[&str]) -> Option<String> {
+        self.entries()
+            .into_iter()
+            .find(|(_, key, _)| keys.contains(&key.as_str()))
+            .map(|(_, _, value)| value)
+    }
+
+    fn has(&self, section: &str, key: &str) -> bool {
+        self.entries().iter().any(|(s, k, _)| s == section && k == key)
+    }
+
+    /// Replace the value of `key` in `section`, adding the key (and section) if missing
+    fn set(&mut self, section: &str, key: &str, value: &str) {
+        let mut current = String::new();
+        let mut section_end = None;
+        for (i, line) in self.lines.iter_mut().enumerate() {
+            let trimmed = line.trim();
+            if let Some(name) = trimmed.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+                current = name.trim().to_string();
+                continue;
+            }
+            if current != section {
+                continue;
+            }
+            if !trimmed.is_empty() {
+                section_end = Some(i + 1);
+            }
+            if trimmed.starts_with('#') || trimmed.starts_with(';') {
+                continue;
+            }
+            if let Some((k, _)) = trimmed.split_once('=') {
+                if k.trim() == key {
+                    *line = format!("{} = {}", key, value);
+                    return;
+                }
+            }
+        }
+
+        let entry = format!("{} = {}", key, value);
+        match section_end {
+            Some(index) => self.lines.insert(index, entry),
+            None => {
+                let header = format!("[{}]", section);
+                match self.lines.iter().position(|l| l.trim() == header) {
+                    Some(index) => self.lines.insert(index + 1, entry),
+                    None => {
+                        if self.lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                            self.lines.push(String::new());
+                        }
+                        self.lines.push(header);
+                        self.lines.push(entry);
+                    }
+                }
+            }
+        }
+    }
+
+    fn target_assembly(&self) -> Option<String> {
+        self.get(TARGET_KEYS).filter(|t| !t.is_empty())
+    }
+
+    /// Point doorstop at another assembly, keeping whichever key name the config uses
+    fn set_target_assembly(&mut self, target: &str) {
+        let existing = self
+            .entries()
+            .into_iter()
+            .find(|(_, key, _)| TARGET_KEYS.contains(&key.as_str()));
+        match existing {
+            Some((section, key, _)) => self.set(&section, &key, target),
+            None => self.set("General", TARGET_KEYS[0], target),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.get(&["enabled"]).is_none_or(|v| v.eq_ignore_ascii_case("true"))
+    }
+
+    /// Add the settings from `other` this config doesn't have, keeping this config's
+    /// target assembly. Search paths from both are kept.
+    fn merge(&mut self, other: &DoorstopConfig) {
+        for (section, key, value) in other.entries() {
+            if TARGET_KEYS.contains(&key.as_str()) {
+                continue;
+            }
+            if SEARCH_PATH_KEYS.contains(&key.as_str()) {
+                let current = self.get(&[key.as_str()]).unwrap_or_default();
+                let mut paths: Vec<&str> = current.split(';').filter(|p| !p.is_empty()).collect();
+                for path in value.split(';').filter(|p| !p.is_empty()) {
+                    if !paths.iter().any(|p| p.eq_ignore_ascii_case(path)) {
+                        paths.push(path);
+                    }
+                }
+                let merged = paths.join(";");
+                if merged != current {
+                    self.set(&section, &key, &merged);
+                }
+            } else if !self.has(&section, &key) {
+                self.set(&section, &key, &value);
+            }
+        }
+    }
+}
+
+/// The loader a target assembly path belongs to, from its top folder
+fn loader_for_target(target: &str) -> Loader {
+    let normalized = target.replace('\\', "/");
+    let top = Path::new(&normalized)
+        .components()
+        .find_map(|c| match c {
+            Component::Normal(name) => Some(name.to_string_lossy().to_lowercase()),
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    match top.as_str() {
+        "silk" => Loader::Silk,
+        "bepinex" => Loader::BepInEx,
+        _ => Loader::Unknown,
+    }
+}
+
+/// Whether a component's manifest recorded the proxy DLL
+fn recorded_by(game_dir: &Path, component: &str) -> bool {
+    manifest::load(game_dir, component).is_some_and(|m| m.files.iter().any(|f| f.path == PROXY_DLL))
+}
+
+/// Which loader owns the doorstop in a game folder; `None` when there is no proxy DLL
+pub fn detect(game_dir: &Path) -> Option<DoorstopInfo> {
+    if !game_dir.join(PROXY_DLL).is_file() {
+        return None;
+    }
+
+    let config = DoorstopConfig::load(&game_dir.join(CONFIG_FILE));
+    let target_assembly = config.as_ref().and_then(|c| c.target_assembly());
+    let by_silk = recorded_by(game_dir, manifest::SILK_COMPONENT);
+    let by_bepinex = recorded_by(game_dir, manifest::BEPINEX_COMPONENT);
+
+    let owner = match target_assembly.as_deref().map(loader_for_target) {
+        Some(loader) if loader != Loader::Unknown => loader,
+        // Silk boots BepInEx itself, so a proxy both recorded is Silk's
+        _ if by_silk => Loader::Silk,
+        _ if by_bepinex => Loader::BepInEx,
+        _ => Loader::Unknown,
+    };
+
+    Some(DoorstopInfo {
+        owner,
+        version: fs::read_to_string(game_dir.join(VERSION_FILE))
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty()),
+        target_assembly,
+        enabled: config.as_ref().is_none_or(|c| c.enabled()),
+        shared: by_silk && by_bepinex,
+    })
+}
+
+/// Reconcile the doorstop files staged by an install of `incoming` with the doorstop
+/// already in the game folder, before they are moved into place.
+///
+/// Only one assembly can be booted, so Silk stays the target whenever it is installed and
+/// loads BepInEx itself; the config gains BepInEx's settings and search paths. The proxy
+/// DLL kept is staged by both installs, so each manifest records it and uninstalling one
+/// loader leaves it for the other.
+///
+/// Returns whether the staged doorstop files are now shared by both loaders, in which case
+/// [`record_shared`] has to be called with the incoming loader's manifest.
+pub fn prepare_staged(game_dir: &Path, staging_dir: &Path, incoming: Loader) -> Result<bool, String> {
+    if !staging_dir.join(PROXY_DLL).is_file() {
+        return Ok(false);
+    }
+    let Some(existing) = detect(game_dir) else {
+        return Ok(false);
+    };
+
+    let staged_config = staging_dir.join(CONFIG_FILE);
+    match (incoming, existing.owner) {
+        (Loader::BepInEx, Loader::Silk) => {
+            info!("Keeping Silk's doorstop and merging BepInEx's config into it");
+            for name in [PROXY_DLL, VERSION_FILE] {
+                let current = game_dir.join(name);
+                let staged = staging_dir.join(name);
+                if current.is_file() {
+                    fs::copy(&current, &staged).map_err(|e| format!("Failed to stage {}: {}", name, e))?;
+                } else if staged.exists() {
+                    fs::remove_file(&staged).map_err(|e| format!("Failed to remove staged {}: {}", name, e))?;
+                }
+            }
+            if game_dir.join(CONFIG_FILE).is_file() {
+                merge_config(&game_dir.join(CONFIG_FILE), &staged_config, &staged_config)?;
+            } else if staged_config.exists() {
+                // BepInEx's own config would boot BepInEx instead; Silk's install puts its config back
+                fs::remove_file(&staged_config).map_err(|e| format!("Failed to remove staged {}: {}", CONFIG_FILE, e))?;
+            }
+            Ok(true)
+        }
+        // Also covers reinstalling Silk over a config BepInEx's settings were merged into
+        (Loader::Silk, _) if game_dir.join("BepInEx").join("core").is_dir() => {
+            info!("Merging BepInEx's doorstop config into Silk's");
+            merge_config(&staged_config, &game_dir.join(CONFIG_FILE), &staged_config)?;
+            Ok(true)
+        }
+        (_, Loader::Unknown) => {
+            warn!("Replacing a doorstop that boots {:?}", existing.target_assembly);
+            Ok(false)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Mark the doorstop files as shared in the incoming loader's manifest and in the other
+/// loader's, whose recorded hashes no longer match once the files are merged
+pub fn record_shared(
+    game_dir: &Path,
+    install_manifest: &mut InstallManifest,
+    transaction: &mut Transaction,
+) -> Result<(), String> {
+    install_manifest.mark_shared(DOORSTOP_FILES);
+
+    let other = if install_manifest.component == manifest::SILK_COMPONENT {
+        manifest::BEPINEX_COMPONENT
+    } else {
+        manifest::SILK_COMPONENT
+    };
+    let Some(mut other_manifest) = manifest::load(game_dir, other) else {
+        return Ok(());
+    };
+    let unmarked = other_manifest
+        .files
+        .iter()
+        .any(|f| !f.shared && DOORSTOP_FILES.contains(&f.path.as_str()));
+    if unmarked {
+        other_manifest.mark_shared(DOORSTOP_FILES);
+        transaction.back_up(&manifest::manifest_path(other))?;
+        manifest::save(game_dir, &other_manifest)?;
+    }
+    Ok(())
+}
+
+/// Merge the config at `secondary` into the one at `primary` and write the result to `output`
+fn merge_config(primary: &Path, secondary: &Path, output: &Path) -> Result<(), String> {
+    let Some(mut merged) = DoorstopConfig::load(primary) else {
+        return Ok(());
+    };
+    if let Some(other) = DoorstopConfig::load(secondary) {
+        merged.merge(&other);
+    }
+    merged.save(output)
+}
+
+/// After a loader is uninstalled, point a doorstop that still boots it at BepInEx
+/// when BepInEx is installed
+pub fn retarget_after_uninstall(game_dir: &Path) -> Result<(), String> {
+    let config_path = game_dir.join(CONFIG_FILE);
+    let Some(mut config) = DoorstopConfig::load(&config_path) else {
+        return Ok(());
+    };
+    let Some(target) = config.target_assembly() else {
+        return Ok(());
+    };
+    if game_dir.join(target.replace('\\', "/")).is_file() {
+        return Ok(());
+    }
+
+    if let Some(preloader) = BEPINEX_PRELOADERS.iter().find(|p| game_dir.join(p).is_file()) {
+        info!("Pointing doorstop at {} now that {} is gone", preloader, target);
+        config.set_target_assembly(&preloader.replace('/', "\\"));
+        config.save(&config_path)?;
+    }
+    Ok(())
+}
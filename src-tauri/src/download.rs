@@ -0,0 +1,489 @@
+use crate::cache;
+use crate::operations::{Operation, CANCELLED_ERROR};
+use futures_util::StreamExt;
+use log::{info, warn};
+use reqwest::header::{CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use reqwest::StatusCode;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+use tokio::io::AsyncWriteExt;
+
+pub const PROGRESS_EVENT: &str = "download-progress";
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Most space finished downloads may take up in the archive cache
+const MAX_ARCHIVE_CACHE_SIZE: u64 = 2 * 1024 * 1024 * 1024;
+
+/// What a download is expected to look like once it has finished
+#[derive(Debug, Clone, Default)]
+pub struct ExpectedDownload {
+    pub size: Option<u64>,
+    /// Hex SHA-256, optionally prefixed with `sha256:` as GitHub reports asset digests
+    pub sha256: Option<String>,
+}
+
+impl ExpectedDownload {
+    pub fn new(size: Option<u64>, sha256: Option<&str>) -> Self {
+        ExpectedDownload {
+            // Registries report 0 when they don't know the size
+            size: size.filter(|s| *s > 0),
+            sha256: sha256
+                .map(|h| h.trim().trim_start_matches("sha256:").to_lowercase())
+                .filter(|h| !h.is_empty()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadPhase {
+    Downloading,
+    Verifying,
+    Extracting,
+    Done,
+}
+
+/// Payload of the `download-progress` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadProgress {
+    pub label: String,
+    pub phase: DownloadPhase,
+    pub bytes_done: u64,
+    pub total_bytes: Option<u64>,
+    pub bytes_per_second: u64,
+}
+
+/// Emit a progress event for a download or install step
+pub fn emit_progress(
+    window: &tauri::Window,
+    label: &str,
+    phase: DownloadPhase,
+    bytes_done: u64,
+    total_bytes: Option<u64>,
+    bytes_per_second: u64,
+) {
+    let _ = window.emit(
+        PROGRESS_EVENT,
+        DownloadProgress {
+            label: label.to_string(),
+            phase,
+            bytes_done,
+            total_bytes,
+            bytes_per_second,
+        },
+    );
+}
+
+/// A finished, verified download. It stays in the archive cache so the same file can be
+/// installed again without a network connection.
+pub struct DownloadedFile {
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+impl DownloadedFile {
+    pub fn open(&self) -> Result<fs::File, String> {
+        fs::File::open(&self.path).map_err(|e| format!("Failed to open downloaded file: {}", e))
+    }
+
+    /// Copy the download to its final location
+    pub fn copy_to(&self, destination: &Path) -> Result<(), String> {
+        fs::copy(&self.path, destination)
+            .map(|_| ())
+            .map_err(|e| format!("Failed to write {}: {}", destination.display(), e))
+    }
+}
+
+/// Hex-encode a SHA-256 digest
+pub fn to_hex(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Check a finished download against the expected size and checksum
+fn verify(size: u64, sha256: &str, expected: &ExpectedDownload, label: &str) -> Result<(), String> {
+    if let Some(expected_size) = expected.size {
+        if size != expected_size {
+            return Err(format!(
+                "Size mismatch for {}: expected {} bytes but received {}. The download may be incomplete or corrupted.",
+                label, expected_size, size
+            ));
+        }
+    }
+
+    match &expected.sha256 {
+        Some(expected_hash) => {
+            if sha256 != expected_hash {
+                return Err(format!(
+                    "Checksum mismatch for {}: expected SHA-256 {} but got {}. Refusing to install.",
+                    label, expected_hash, sha256
+                ));
+            }
+            info!("Verified SHA-256 of {}", label);
+        }
+        None => warn!("No checksum available for {}; only the size was checked", label),
+    }
+
+    Ok(())
+}
+
+/// A previously finished download of the same URL that still matches what is expected
+fn cached_archive(archive_path: &Path, expected: &ExpectedDownload, label: &str) -> Option<DownloadedFile> {
+    let (size, sha256) = crate::manifest::hash_file(archive_path).ok()?;
+    if let Err(e) = verify(size, &sha256, expected, label) {
+        warn!("Ignoring cached copy of {}: {}", label, e);
+        return None;
+    }
+
+    Some(DownloadedFile {
+        path: archive_path.to_path_buf(),
+        size,
+    })
+}
+
+/// A file that is already on disk, such as one from a local registry, checked like a download
+pub fn local_file(path: &Path, expected: &ExpectedDownload, label: &str) -> Result<DownloadedFile, String> {
+    let (size, sha256) = crate::manifest::hash_file(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    verify(size, &sha256, expected, label)?;
+
+    Ok(DownloadedFile {
+        path: path.to_path_buf(),
+        size,
+    })
+}
+
+fn discard_partial(part_path: &Path, validator_path: &Path) {
+    let _ = fs::remove_file(part_path);
+    let _ = fs::remove_file(validator_path);
+}
+
+/// Start offset of a `Content-Range: bytes <start>-<end>/<total>` header, and the total if known
+fn parse_content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let range = value.trim().strip_prefix("bytes ")?;
+    let (span, total) = range.split_once('/')?;
+    let start = span.split_once('-')?.0.trim().parse().ok()?;
+    Some((start, total.trim().parse().ok()))
+}
+
+/// Where a response to a request for the bytes after the `existing` ones in the partial
+/// file continues from: `existing` when it is the matching partial response, 0 when the
+/// server sent the whole file, and `None` when it sent some other range
+fn resume_offset(status: StatusCode, content_range: Option<&str>, existing: u64) -> Option<u64> {
+    if status != StatusCode::PARTIAL_CONTENT || existing == 0 {
+        return Some(0);
+    }
+    let start = content_range.and_then(parse_content_range).map(|(start, _)| start);
+    (start == Some(existing)).then_some(existing)
+}
+
+/// Size of the whole file, from the total in `Content-Range` when resuming, otherwise
+/// from the length of what is still to come
+fn total_size(resume_from: u64, content_range: Option<&str>, content_length: Option<u64>) -> Option<u64> {
+    if resume_from == 0 {
+        return content_length;
+    }
+    content_range
+        .and_then(parse_content_range)
+        .and_then(|(_, total)| total)
+        .or_else(|| content_length.map(|len| len + resume_from))
+}
+
+/// Stream a download into the download cache, resuming a previous partial download of the
+/// same URL with an HTTP Range request. The partial file is kept when the download is
+/// interrupted or cancelled, and only discarded once it fails verification.
+pub async fn download_to_file(
+    client: &reqwest::Client,
+    url: &str,
+    expected: &ExpectedDownload,
+    label: &str,
+    window: &tauri::Window,
+    operation: &Operation,
+) -> Result<DownloadedFile, String> {
+    // Partial downloads are kept apart from finished ones so a resume can't replace a good copy
+    let cache_dir = cache::cache_dir("downloads")?;
+    let key = cache::cache_key(url);
+    let part_path = cache_dir.join(format!("{}.part", key));
+    let validator_path = cache_dir.join(format!("{}.validator", key));
+    let archive_path = cache::cache_dir("archives")?.join(&key);
+
+    // With a checksum to compare against, a cached copy is as good as a fresh download
+    if expected.sha256.is_some() && archive_path.exists() {
+        if let Some(cached) = cached_archive(&archive_path, expected, label) {
+            info!("Using cached download of {}", label);
+            return Ok(cached);
+        }
+    }
+
+    // A second attempt is only made when the server rejects the range we asked for
+    let mut allow_resume = true;
+    let (response, resume_from) = loop {
+        operation.check()?;
+
+        let existing = if allow_resume {
+            fs::metadata(&part_path).map(|m| m.len()).unwrap_or(0)
+        } else {
+            0
+        };
+
+        let mut request = client.get(url).header("User-Agent", "entwine-app");
+        if existing > 0 {
+            request = request.header(RANGE, format!("bytes={}-", existing));
+            // Only accept a partial response if the file hasn't changed since we started
+            if let Ok(validator) = fs::read_to_string(&validator_path) {
+                request = request.header(IF_RANGE, validator.trim());
+            }
+        }
+
+        let response = tokio::select! {
+            result = request.send() => match result {
+                Ok(response) => response,
+                Err(e) => {
+                    // Offline: fall back to the copy from the last time this was downloaded
+                    let cached = if archive_path.exists() {
+                        cached_archive(&archive_path, expected, label)
+                    } else {
+                        None
+                    };
+                    return match cached {
+                        Some(cached) => {
+                            warn!("Could not reach the server for {} ({}); using the cached download", label, e);
+                            Ok(cached)
+                        }
+                        None => Err(format!("Failed to download {}: {}", label, e)),
+                    };
+                }
+            },
+            _ = operation.cancelled() => return Err(CANCELLED_ERROR.to_string()),
+        };
+
+        let status = response.status();
+        if status == StatusCode::RANGE_NOT_SATISFIABLE && allow_resume {
+            warn!("Server rejected resume of {} at {} bytes; starting over", label, existing);
+            discard_partial(&part_path, &validator_path);
+            allow_resume = false;
+            continue;
+        }
+        if !status.is_success() {
+            return Err(format!("Failed to download {}: server responded with {}", label, status));
+        }
+
+        let content_range = response.headers().get(CONTENT_RANGE).and_then(|v| v.to_str().ok());
+        match resume_offset(status, content_range, existing) {
+            Some(0) => break (response, 0),
+            Some(offset) => {
+                info!("Resuming download of {} from {} bytes", label, offset);
+                break (response, offset);
+            }
+            None => {
+                discard_partial(&part_path, &validator_path);
+                return Err(format!(
+                    "Failed to resume {}: the server returned an unexpected range. Please try again.",
+                    label
+                ));
+            }
+        }
+    };
+
+    let content_range = response.headers().get(CONTENT_RANGE).and_then(|v| v.to_str().ok());
+    let total_bytes = total_size(resume_from, content_range, response.content_length()).or(expected.size);
+
+    let mut hasher = Sha256::new();
+    let mut file = if resume_from > 0 {
+        // Hash what is already on disk so the checksum covers the whole file
+        let mut existing = fs::File::open(&part_path)
+            .map_err(|e| format!("Failed to read partial download: {}", e))?;
+        io::copy(&mut existing, &mut hasher)
+            .map_err(|e| format!("Failed to read partial download: {}", e))?;
+
+        tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(&part_path)
+            .await
+            .map_err(|e| format!("Failed to open partial download: {}", e))?
+    } else {
+        // Remember what identifies this version of the file for a later resume
+        let validator = response
+            .headers()
+            .get(ETAG)
+            .and_then(|v| v.to_str().ok())
+            .filter(|etag| !etag.starts_with("W/"))
+            .or_else(|| response.headers().get(LAST_MODIFIED).and_then(|v| v.to_str().ok()));
+        match validator {
+            Some(validator) => {
+                let _ = fs::write(&validator_path, validator);
+            }
+            None => {
+                let _ = fs::remove_file(&validator_path);
+            }
+        }
+
+        tokio::fs::File::create(&part_path)
+            .await
+            .map_err(|e| format!("Failed to create download file: {}", e))?
+    };
+
+    let mut bytes_done = resume_from;
+    let started = Instant::now();
+    let mut last_emit = Instant::now();
+    let mut stream = response.bytes_stream();
+
+    emit_progress(window, label, DownloadPhase::Downloading, bytes_done, total_bytes, 0);
+
+    loop {
+        let chunk = tokio::select! {
+            chunk = stream.next() => chunk,
+            _ = operation.cancelled() => {
+                let _ = file.flush().await;
+                info!("Download of {} cancelled at {} bytes; keeping it to resume later", label, bytes_done);
+                return Err(CANCELLED_ERROR.to_string());
+            }
+        };
+        let Some(chunk) = chunk else { break };
+
+        let chunk = chunk.map_err(|e| {
+            format!(
+                "Download of {} was interrupted after {} bytes: {}. It will resume from there next time.",
+                label, bytes_done, e
+            )
+        })?;
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| format!("Failed to write {} to disk: {}", label, e))?;
+        hasher.update(&chunk);
+        bytes_done += chunk.len() as u64;
+
+        if last_emit.elapsed() >= PROGRESS_INTERVAL {
+            let speed = bytes_per_second(bytes_done - resume_from, started);
+            emit_progress(window, label, DownloadPhase::Downloading, bytes_done, total_bytes, speed);
+            last_emit = Instant::now();
+        }
+    }
+
+    file.flush()
+        .await
+        .map_err(|e| format!("Failed to write {} to disk: {}", label, e))?;
+    drop(file);
+
+    emit_progress(window, label, DownloadPhase::Verifying, bytes_done, total_bytes, 0);
+
+    let sha256 = to_hex(&hasher.finalize());
+    if let Err(e) = verify(bytes_done, &sha256, expected, label) {
+        // A corrupt partial file must not be resumed again
+        discard_partial(&part_path, &validator_path);
+        return Err(e);
+    }
+    let _ = fs::remove_file(&validator_path);
+
+    fs::rename(&part_path, &archive_path)
+        .map_err(|e| format!("Failed to move {} into the download cache: {}", label, e))?;
+    cache::trim("archives", MAX_ARCHIVE_CACHE_SIZE);
+
+    Ok(DownloadedFile {
+        path: archive_path,
+        size: bytes_done,
+    })
+}
+
+fn bytes_per_second(bytes: u64, started: Instant) -> u64 {
+    let elapsed = started.elapsed().as_secs_f64();
+    if elapsed > 0.0 {
+        (bytes as f64 / elapsed) as u64
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_content_ranges() {
+        assert_eq!(parse_content_range("bytes 100-199/200"), Some((100, Some(200))));
+        assert_eq!(parse_content_range(" bytes 0-0/* "), Some((0, None)));
+        assert_eq!(parse_content_range("bytes 5-9/ 10"), Some((5, Some(10))));
+        assert_eq!(parse_content_range("bytes */200"), None);
+        assert_eq!(parse_content_range("items 0-9/10"), None);
+        assert_eq!(parse_content_range("bytes 100-199"), None);
+        assert_eq!(parse_content_range(""), None);
+    }
+
+    #[test]
+    fn resumes_only_from_the_requested_offset() {
+        let partial = StatusCode::PARTIAL_CONTENT;
+        assert_eq!(resume_offset(partial, Some("bytes 100-199/200"), 100), Some(100));
+        assert_eq!(resume_offset(partial, Some("bytes 50-199/200"), 100), None);
+        assert_eq!(resume_offset(partial, Some("bytes */200"), 100), None);
+        assert_eq!(resume_offset(partial, None, 100), None);
+    }
+
+    #[test]
+    fn full_responses_start_over() {
+        // A changed file (If-Range mismatch) or a server without range support sends it all
+        assert_eq!(resume_offset(StatusCode::OK, None, 100), Some(0));
+        assert_eq!(resume_offset(StatusCode::OK, Some("bytes 100-199/200"), 100), Some(0));
+        assert_eq!(resume_offset(StatusCode::PARTIAL_CONTENT, Some("bytes 0-199/200"), 0), Some(0));
+    }
+
+    #[test]
+    fn total_size_covers_the_partial_file() {
+        assert_eq!(total_size(0, None, Some(200)), Some(200));
+        assert_eq!(total_size(100, Some("bytes 100-199/200"), Some(100)), Some(200));
+        // An unknown total falls back to what is left plus what is already on disk
+        assert_eq!(total_size(100, Some("bytes 100-199/*"), Some(100)), Some(200));
+        assert_eq!(total_size(100, None, None), None);
+    }
+
+    const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    #[test]
+    fn normalizes_expected_checksums() {
+        let expected = ExpectedDownload::new(Some(5), Some(" sha256:2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824 "));
+        assert_eq!(expected.sha256.as_deref(), Some(HELLO_SHA256));
+        assert_eq!(ExpectedDownload::new(None, Some(HELLO_SHA256)).sha256.as_deref(), Some(HELLO_SHA256));
+        assert_eq!(ExpectedDownload::new(None, Some("")).sha256, None);
+        assert_eq!(ExpectedDownload::new(None, Some("sha256:")).sha256, None);
+        assert_eq!(ExpectedDownload::new(None, None).sha256, None);
+    }
+
+    #[test]
+    fn size_zero_means_unknown() {
+        assert_eq!(ExpectedDownload::new(Some(0), None).size, None);
+        assert_eq!(ExpectedDownload::new(Some(5), None).size, Some(5));
+        // Without a size, any length passes
+        assert!(verify(123, HELLO_SHA256, &ExpectedDownload::new(Some(0), None), "Mod").is_ok());
+    }
+
+    #[test]
+    fn verifies_size_and_checksum() {
+        let expected = ExpectedDownload::new(Some(5), Some(&format!("sha256:{}", HELLO_SHA256)));
+        assert!(verify(5, HELLO_SHA256, &expected, "Mod").is_ok());
+
+        let err = verify(4, HELLO_SHA256, &expected, "Mod").unwrap_err();
+        assert!(err.starts_with("Size mismatch for Mod: expected 5 bytes but received 4"), "{}", err);
+
+        let other = "0".repeat(64);
+        let err = verify(5, &other, &expected, "Mod").unwrap_err();
+        assert!(err.starts_with(&format!("Checksum mismatch for Mod: expected SHA-256 {} but got {}", HELLO_SHA256, other)), "{}", err);
+    }
+
+    #[test]
+    fn checks_local_files_like_downloads() {
+        let path = std::env::temp_dir().join(format!("entwine-download-test-local-{}", std::process::id()));
+        fs::write(&path, "hello").unwrap();
+
+        let file = local_file(&path, &ExpectedDownload::new(Some(5), Some(HELLO_SHA256)), "Mod").unwrap();
+        assert_eq!(file.size, 5);
+        assert!(local_file(&path, &ExpectedDownload::new(Some(6), None), "Mod").is_err());
+        assert!(local_file(&path, &ExpectedDownload::new(None, Some(&"0".repeat(64))), "Mod").is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
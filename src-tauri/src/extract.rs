@@ -0,0 +1,432 @@
+use crate::operations::Operation;
+use std::fs;
+use std::io::{self, Read, Seek};
+use std::path::{Component, Path, PathBuf};
+
+/// Largest total uncompressed size we will extract from a single archive
+pub const MAX_TOTAL_SIZE: u64 = 2 * 1024 * 1024 * 1024;
+/// Most entries we will extract from a single archive
+pub const MAX_ENTRIES: usize = 50_000;
+
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+
+const RESERVED_WINDOWS_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Limits applied while extracting an archive
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    pub max_total_size: u64,
+    pub max_entries: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_total_size: MAX_TOTAL_SIZE,
+            max_entries: MAX_ENTRIES,
+        }
+    }
+}
+
+/// Check that a name from the registry or an archive is a single, ordinary file name
+/// that can be joined onto a directory without escaping it
+pub fn safe_file_name(name: &str) -> Result<&str, String> {
+    let stem = name.split('.').next().unwrap_or("").trim_end();
+
+    if name.is_empty() || name.trim() != name {
+        Err(format!("Invalid file name '{}': empty or padded with whitespace", name))
+    } else if name == "." || name == ".." || name.starts_with('.') {
+        Err(format!("Invalid file name '{}': hidden or relative names are not allowed", name))
+    } else if name.contains(['/', '\\', ':']) || name.chars().any(|c| c.is_control()) {
+        Err(format!("Invalid file name '{}': contains a path separator or reserved character", name))
+    } else if RESERVED_WINDOWS_NAMES.iter().any(|r| stem.eq_ignore_ascii_case(r)) {
+        Err(format!("Invalid file name '{}': reserved by Windows", name))
+    } else {
+        Ok(name)
+    }
+}
+
+/// Turn an archive entry name into a relative path, rejecting anything that could
+/// point outside the extraction target
+pub fn sanitize_entry_name(raw: &str) -> Result<PathBuf, String> {
+    if raw.contains('\0') {
+        return Err(format!("Archive entry '{}' contains a NUL byte", raw.escape_default()));
+    }
+
+    // Archives built on Windows sometimes use backslashes as separators
+    let normalized = raw.replace('\\', "/");
+    let has_drive_letter = normalized.as_bytes().get(1) == Some(&b':');
+    if normalized.starts_with('/') || has_drive_letter {
+        return Err(format!("Archive entry '{}' has an absolute path", raw));
+    }
+
+    let mut relative = PathBuf::new();
+    for part in normalized.split('/') {
+        match part {
+            "" | "." => continue,
+            ".." => return Err(format!("Archive entry '{}' escapes the target directory", raw)),
+            _ if part.contains(':') || part.chars().any(|c| c.is_control()) => {
+                return Err(format!("Archive entry '{}' contains a reserved character", raw));
+            }
+            _ => relative.push(part),
+        }
+    }
+
+    // Belt and braces: the result must be purely relative
+    if relative.components().any(|c| !matches!(c, Component::Normal(_))) {
+        return Err(format!("Archive entry '{}' escapes the target directory", raw));
+    }
+
+    Ok(relative)
+}
+
+/// An entry that passed validation, ready to be written
+struct PlannedEntry {
+    index: usize,
+    relative: PathBuf,
+    is_dir: bool,
+}
+
+/// Extract a zip archive into `target` after validating every entry.
+///
+/// The whole archive is checked before anything is written: entries that escape the
+/// target, absolute paths, symlinks and archives over the size or entry limits are
+/// rejected. `keep` selects which (relative) entries are written, and `operation` makes
+/// the extraction cancellable between entries. Returns the relative paths of the files written.
+pub fn extract_zip<R: Read + Seek>(
+    reader: R,
+    target: &Path,
+    limits: &Limits,
+    keep: impl Fn(&Path) -> bool,
+    operation: Option<&Operation>,
+) -> Result<Vec<PathBuf>, String> {
+    let mut archive =
+        zip::ZipArchive::new(reader).map_err(|e| format!("Failed to read zip archive: {}", e))?;
+
+    if archive.len() > limits.max_entries {
+        return Err(format!(
+            "Archive has {} entries, more than the limit of {}",
+            archive.len(),
+            limits.max_entries
+        ));
+    }
+
+    let mut planned = Vec::new();
+    let mut declared_size: u64 = 0;
+
+    for i in 0..archive.len() {
+        let file = archive
+            .by_index_raw(i)
+            .map_err(|e| format!("Failed to read zip entry: {}", e))?;
+
+        let relative = sanitize_entry_name(file.name())?;
+
+        if file.unix_mode().is_some_and(|mode| mode & S_IFMT == S_IFLNK) {
+            return Err(format!("Archive entry '{}' is a symbolic link", file.name()));
+        }
+
+        declared_size = declared_size.saturating_add(file.size());
+        if declared_size > limits.max_total_size {
+            return Err(format!(
+                "Archive expands to more than the limit of {} bytes",
+                limits.max_total_size
+            ));
+        }
+
+        if relative.as_os_str().is_empty() || !keep(&relative) {
+            continue;
+        }
+
+        planned.push(PlannedEntry {
+            index: i,
+            is_dir: file.is_dir(),
+            relative,
+        });
+    }
+
+    fs::create_dir_all(target)
+        .map_err(|e| format!("Failed to create directory {}: {}", target.display(), e))?;
+    let canonical_target = fs::canonicalize(target)
+        .map_err(|e| format!("Failed to resolve {}: {}", target.display(), e))?;
+
+    let mut written = Vec::new();
+    let mut total_written: u64 = 0;
+
+    for entry in planned {
+        if let Some(operation) = operation {
+            operation.check()?;
+        }
+
+        let outpath = target.join(&entry.relative);
+        let dir = if entry.is_dir { outpath.as_path() } else { outpath.parent().unwrap_or(target) };
+
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create directory {}: {}", dir.display(), e))?;
+
+        // A directory already on disk could be a symlink pointing somewhere else
+        let canonical_dir = fs::canonicalize(dir)
+            .map_err(|e| format!("Failed to resolve {}: {}", dir.display(), e))?;
+        if !canonical_dir.starts_with(&canonical_target) {
+            return Err(format!(
+                "Archive entry '{}' would be written outside {}",
+                entry.relative.display(),
+                target.display()
+            ));
+        }
+
+        if entry.is_dir {
+            continue;
+        }
+
+        let mut file = archive
+            .by_index(entry.index)
+            .map_err(|e| format!("Failed to read zip entry: {}", e))?;
+
+        if fs::symlink_metadata(&outpath).is_ok_and(|m| m.file_type().is_symlink()) {
+            fs::remove_file(&outpath)
+                .map_err(|e| format!("Failed to replace {}: {}", outpath.display(), e))?;
+        }
+        let mut outfile = fs::File::create(&outpath)
+            .map_err(|e| format!("Failed to create file {}: {}", outpath.display(), e))?;
+
+        // Declared sizes can lie, so the limit is enforced on the bytes actually written
+        let remaining = limits.max_total_size - total_written;
+        let copied = io::copy(&mut (&mut file).take(remaining + 1), &mut outfile)
+            .map_err(|e| format!("Failed to write file {}: {}", outpath.display(), e))?;
+        total_written += copied;
+        if copied > remaining {
+            drop(outfile);
+            let _ = fs::remove_file(&outpath);
+            return Err(format!(
+                "Archive expands to more than the limit of {} bytes",
+                limits.max_total_size
+            ));
+        }
+
+        written.push(entry.relative);
+    }
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::write::SimpleFileOptions;
+
+    fn build_zip(entries: &[(&str, &[u8])]) -> Cursor<Vec<u8>> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in entries {
+            if name.ends_with('/') {
+                writer.add_directory(*name, SimpleFileOptions::default()).unwrap();
+            } else {
+                writer.start_file(*name, SimpleFileOptions::default()).unwrap();
+                writer.write_all(content).unwrap();
+            }
+        }
+        let mut cursor = writer.finish().unwrap();
+        cursor.set_position(0);
+        cursor
+    }
+
+    fn temp_target(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("entwine-extract-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn extract(zip: Cursor<Vec<u8>>, target: &Path) -> Result<Vec<PathBuf>, String> {
+        extract_zip(zip, target, &Limits::default(), |_| true, None)
+    }
+
+    #[test]
+    fn extracts_regular_archive() {
+        let target = temp_target("regular");
+        let zip = build_zip(&[("plugins/", b""), ("plugins/Mod.dll", b"dll"), ("README.md", b"hi")]);
+
+        let written = extract(zip, &target).unwrap();
+
+        assert_eq!(written.len(), 2);
+        assert_eq!(fs::read(target.join("plugins/Mod.dll")).unwrap(), b"dll");
+        assert_eq!(fs::read(target.join("README.md")).unwrap(), b"hi");
+        fs::remove_dir_all(&target).unwrap();
+    }
+
+    #[test]
+    fn keep_filter_skips_entries() {
+        let target = temp_target("filter");
+        let zip = build_zip(&[("BepInEx/core/BepInEx.dll", b"core"), ("winhttp.dll", b"proxy")]);
+
+        extract_zip(zip, &target, &Limits::default(), |p| p.starts_with("BepInEx"), None)
+            .unwrap();
+
+        assert!(target.join("BepInEx/core/BepInEx.dll").exists());
+        assert!(!target.join("winhttp.dll").exists());
+        fs::remove_dir_all(&target).unwrap();
+    }
+
+    #[test]
+    fn rejects_parent_traversal() {
+        let target = temp_target("traversal");
+        let zip = build_zip(&[("ok.txt", b"ok"), ("../../evil.txt", b"evil")]);
+
+        let err = extract(zip, &target).unwrap_err();
+
+        assert!(err.contains("escapes the target"), "{}", err);
+        // Nothing is written when any entry is rejected
+        assert!(!target.join("ok.txt").exists());
+        let _ = fs::remove_dir_all(&target);
+    }
+
+    #[test]
+    fn rejects_backslash_traversal() {
+        let target = temp_target("backslash");
+        let zip = build_zip(&[("mods\\..\\..\\evil.dll", b"evil")]);
+
+        assert!(extract(zip, &target).unwrap_err().contains("escapes the target"));
+        let _ = fs::remove_dir_all(&target);
+    }
+
+    #[test]
+    fn rejects_absolute_paths() {
+        let target = temp_target("absolute");
+
+        let unix = build_zip(&[("/tmp/evil.txt", b"evil")]);
+        assert!(extract(unix, &target).unwrap_err().contains("absolute path"));
+
+        let windows = build_zip(&[("C:/Windows/evil.dll", b"evil")]);
+        assert!(extract(windows, &target).unwrap_err().contains("absolute path"));
+        let _ = fs::remove_dir_all(&target);
+    }
+
+    #[test]
+    fn rejects_symlinks() {
+        let target = temp_target("symlink");
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        writer
+            .add_symlink("link", "/etc/passwd", SimpleFileOptions::default())
+            .unwrap();
+        let mut zip = writer.finish().unwrap();
+        zip.set_position(0);
+
+        assert!(extract(zip, &target).unwrap_err().contains("symbolic link"));
+        let _ = fs::remove_dir_all(&target);
+    }
+
+    #[test]
+    fn enforces_size_limit() {
+        let target = temp_target("size");
+        let zip = build_zip(&[("a.bin", &[0u8; 600]), ("b.bin", &[0u8; 600])]);
+        let limits = Limits {
+            max_total_size: 1000,
+            max_entries: MAX_ENTRIES,
+        };
+
+        let err = extract_zip(zip, &target, &limits, |_| true, None).unwrap_err();
+
+        assert!(err.contains("limit of 1000 bytes"), "{}", err);
+        let _ = fs::remove_dir_all(&target);
+    }
+
+    #[test]
+    fn enforces_entry_limit() {
+        let target = temp_target("entries");
+        let zip = build_zip(&[("a", b"1"), ("b", b"2"), ("c", b"3")]);
+        let limits = Limits {
+            max_total_size: MAX_TOTAL_SIZE,
+            max_entries: 2,
+        };
+
+        let err = extract_zip(zip, &target, &limits, |_| true, None).unwrap_err();
+
+        assert!(err.contains("3 entries"), "{}", err);
+        let _ = fs::remove_dir_all(&target);
+    }
+
+    #[test]
+    fn safe_file_name_rejects_unsafe_names() {
+        assert!(safe_file_name("CoolMod.dll").is_ok());
+        assert!(safe_file_name("Cool Mod").is_ok());
+
+        for name in ["", "..", ".hidden", "../Mods", "a/b.dll", "a\\b.dll", "C:evil", "NUL", "con.dll", " padded"] {
+            assert!(safe_file_name(name).is_err(), "{} should be rejected", name);
+        }
+    }
+
+    /// Cancels an operation from inside the archive reader once `trigger` exists, which
+    /// happens partway through extracting
+    struct CancelWhen<R> {
+        inner: R,
+        trigger: PathBuf,
+        operation_id: String,
+    }
+
+    impl<R: Read> Read for CancelWhen<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.trigger.exists() {
+                crate::operations::cancel(&self.operation_id);
+            }
+            self.inner.read(buf)
+        }
+    }
+
+    impl<R: Seek> Seek for CancelWhen<R> {
+        fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    /// Every file and directory under `root` with the contents of the files
+    fn snapshot(root: &Path) -> Vec<(PathBuf, Option<Vec<u8>>)> {
+        let mut entries: Vec<_> = walkdir::WalkDir::new(root)
+            .min_depth(1)
+            .into_iter()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let content = entry.file_type().is_file().then(|| fs::read(entry.path()).unwrap());
+                (entry.path().strip_prefix(root).unwrap().to_path_buf(), content)
+            })
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    #[test]
+    fn cancelled_extract_leaves_the_mods_folder_unchanged() {
+        let mods_dir = temp_target("cancel");
+        fs::create_dir_all(mods_dir.join("Existing")).unwrap();
+        fs::write(mods_dir.join("Existing/Existing.dll"), "existing").unwrap();
+        fs::write(mods_dir.join("Earlier.dll"), "old earlier").unwrap();
+        fs::write(mods_dir.join(".entwine_metadata.json"), "{}").unwrap();
+        let before = snapshot(&mods_dir);
+
+        let operation = Operation::start(None);
+        let mut transaction = crate::transaction::Transaction::begin(&mods_dir).unwrap();
+        let staging_dir = transaction.staging_dir();
+
+        // An earlier mod in the same install was already moved into place
+        fs::write(staging_dir.join("Earlier.dll"), "new earlier").unwrap();
+        transaction.place(&staging_dir.join("Earlier.dll"), Path::new("Earlier.dll")).unwrap();
+        transaction.back_up(Path::new(".entwine_metadata.json")).unwrap();
+        fs::write(mods_dir.join(".entwine_metadata.json"), "{\"Earlier\": {}}").unwrap();
+
+        let target = staging_dir.join("Archive");
+        let zip = CancelWhen {
+            inner: build_zip(&[("First.dll", b"first"), ("Second.dll", b"second"), ("Third.dll", b"third")]),
+            trigger: target.join("First.dll"),
+            operation_id: operation.id.clone(),
+        };
+        let err = extract_zip(zip, &target, &Limits::default(), |_| true, Some(&operation)).unwrap_err();
+        assert_eq!(err, crate::operations::CANCELLED_ERROR);
+        assert!(target.join("First.dll").exists() && !target.join("Second.dll").exists());
+
+        drop(transaction);
+        assert_eq!(snapshot(&mods_dir), before);
+        let _ = fs::remove_dir_all(&mods_dir);
+    }
+}
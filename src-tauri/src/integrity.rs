@@ -0,0 +1,585 @@
+use crate::manifest::{self, InstallManifest};
+use crate::operations::Operation;
+use crate::doorstop::{self, Loader};
+use crate::{bepinex, download, extract, registry, transaction, version};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use tauri::Emitter;
+use walkdir::WalkDir;
+
+/// How a single component compares with what was installed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentStatus {
+    /// `silk`, `bepinex`, or the mod's metadata key
+    pub component: String,
+    pub name: String,
+    pub version: String,
+    /// False when no install manifest was recorded, so individual files can't be checked
+    pub tracked: bool,
+    pub missing: Vec<String>,
+    pub modified: Vec<String>,
+    /// Files in the component's folders that it didn't install
+    pub extra: Vec<String>,
+}
+
+impl ComponentStatus {
+    pub fn is_broken(&self) -> bool {
+        !self.missing.is_empty() || !self.modified.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationReport {
+    pub silk: Option<ComponentStatus>,
+    pub bepinex: Option<ComponentStatus>,
+    pub mods: Vec<ComponentStatus>,
+}
+
+/// Where a component keeps files the user or the game adds, which are never reported as extra
+const SILK_USER_DIRS: &[&str] = &["Silk/Mods", "Silk/Config"];
+
+/// Compare the files on disk with a manifest. With `quick` set only presence and size are checked,
+/// and files shared with another component are only checked for presence.
+fn check_files(
+    root: &Path,
+    install_manifest: &InstallManifest,
+    renamed_top: Option<(&str, &str)>,
+    quick: bool,
+) -> (Vec<String>, Vec<String>) {
+    let mut missing = Vec::new();
+    let mut modified = Vec::new();
+
+    for file in &install_manifest.files {
+        let relative = manifest::relocate(&file.path, renamed_top);
+        let path = root.join(&relative);
+
+        if !path.is_file() {
+            missing.push(relative);
+        } else if file.shared {
+            continue;
+        } else if quick {
+            if fs::metadata(&path).map(|m| m.len()).unwrap_or(0) != file.size {
+                modified.push(relative);
+            }
+        } else if !manifest::is_unchanged(file, &path) {
+            modified.push(relative);
+        }
+    }
+
+    (missing, modified)
+}
+
+/// Files under `scan_dir` that the manifest doesn't list, skipping user folders and logs
+fn find_extra(
+    root: &Path,
+    install_manifest: &InstallManifest,
+    renamed_top: Option<(&str, &str)>,
+    scan_dir: &str,
+    user_dirs: &[&str],
+) -> Vec<String> {
+    let known: HashSet<String> = install_manifest
+        .files
+        .iter()
+        .map(|f| manifest::relocate(&f.path, renamed_top))
+        .collect();
+
+    WalkDir::new(root.join(scan_dir))
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let relative = entry.path().strip_prefix(root).ok()?;
+            Some(
+                relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/"),
+            )
+        })
+        .filter(|relative| !known.contains(relative))
+        .filter(|relative| !relative.ends_with(".log"))
+        .filter(|relative| !user_dirs.iter().any(|dir| relative.starts_with(&format!("{}/", dir))))
+        .collect()
+}
+
+fn tracked_status(
+    root: &Path,
+    install_manifest: &InstallManifest,
+    name: &str,
+    renamed_top: Option<(&str, &str)>,
+    extra_scan: Option<(&str, &[&str])>,
+    quick: bool,
+) -> ComponentStatus {
+    let (missing, modified) = check_files(root, install_manifest, renamed_top, quick);
+    let extra = match extra_scan {
+        Some((scan_dir, user_dirs)) if !quick => {
+            find_extra(root, install_manifest, renamed_top, scan_dir, user_dirs)
+        }
+        _ => Vec::new(),
+    };
+
+    ComponentStatus {
+        component: install_manifest.component.clone(),
+        name: name.to_string(),
+        version: install_manifest.version.clone(),
+        tracked: true,
+        missing,
+        modified,
+        extra,
+    }
+}
+
+/// Status of a component installed before manifests were recorded, judged by its key files
+fn untracked_status(component: &str, name: &str, version: &str, missing: Vec<String>) -> ComponentStatus {
+    ComponentStatus {
+        component: component.to_string(),
+        name: name.to_string(),
+        version: version.to_string(),
+        tracked: false,
+        missing,
+        modified: Vec::new(),
+        extra: Vec::new(),
+    }
+}
+
+/// Check Silk against its manifest; `None` when Silk isn't installed at all
+pub fn verify_silk(game_dir: &Path, quick: bool) -> Option<ComponentStatus> {
+    if let Some(install_manifest) = manifest::load(game_dir, manifest::SILK_COMPONENT) {
+        return Some(tracked_status(
+            game_dir,
+            &install_manifest,
+            "Silk",
+            None,
+            Some(("Silk", SILK_USER_DIRS)),
+            quick,
+        ));
+    }
+
+    let key_files = ["winhttp.dll", "Silk"];
+    let missing: Vec<String> = key_files
+        .iter()
+        .filter(|name| !game_dir.join(name).exists())
+        .map(|name| name.to_string())
+        .collect();
+
+    if missing.len() == key_files.len() {
+        return None;
+    }
+
+    let installed_version = version::get_installed_silk_version(&game_dir.to_string_lossy())
+        .unwrap_or_else(|_| "Unknown".to_string());
+    Some(untracked_status(manifest::SILK_COMPONENT, "Silk", &installed_version, missing))
+}
+
+/// Check BepInEx against its manifest; `None` when BepInEx isn't installed at all
+pub fn verify_bepinex(game_dir: &Path, quick: bool) -> Option<ComponentStatus> {
+    if let Some(install_manifest) = manifest::load(game_dir, manifest::BEPINEX_COMPONENT) {
+        return Some(tracked_status(
+            game_dir,
+            &install_manifest,
+            "BepInEx",
+            None,
+            Some(("BepInEx/core", &[])),
+            quick,
+        ));
+    }
+
+    if !game_dir.join("BepInEx").exists() {
+        return None;
+    }
+
+    let missing = if game_dir.join("BepInEx/core").is_dir() {
+        Vec::new()
+    } else {
+        vec!["BepInEx/core".to_string()]
+    };
+    let installed_version = bepinex::get_bepinex_version(&game_dir.to_string_lossy())
+        .unwrap_or_else(|_| "Unknown".to_string());
+    Some(untracked_status(manifest::BEPINEX_COMPONENT, "BepInEx", &installed_version, missing))
+}
+
+/// The name a mod's top-level file or folder was installed under, and its name now
+fn mod_location(mods_dir: &Path, install_manifest: &InstallManifest) -> Option<(String, String)> {
+    let top = install_manifest.files.first()?.path.split('/').next()?.to_string();
+    let disabled = crate::toggled_file_name(&top, false);
+
+    let current = if mods_dir.join(&top).exists() || !mods_dir.join(&disabled).exists() {
+        top.clone()
+    } else {
+        disabled
+    };
+    Some((top, current))
+}
+
+fn mod_manifests(mods_dir: &Path) -> Vec<InstallManifest> {
+    let Ok(entries) = fs::read_dir(mods_dir.join(manifest::MANIFESTS_DIR)) else {
+        return Vec::new();
+    };
+
+    let mut manifests: Vec<InstallManifest> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name.strip_suffix(".json").map(|component| component.to_string())
+        })
+        .filter_map(|component| manifest::load(mods_dir, &component))
+        .collect();
+    manifests.sort_by(|a, b| a.component.cmp(&b.component));
+    manifests
+}
+
+/// Check every mod that has an install manifest
+pub fn verify_mods(mods_dir: &Path, quick: bool) -> Vec<ComponentStatus> {
+    let metadata = crate::load_mod_metadata(mods_dir);
+
+    mod_manifests(mods_dir)
+        .iter()
+        .filter_map(|install_manifest| {
+            let (top, current) = mod_location(mods_dir, install_manifest)?;
+            let name = metadata
+                .get(&install_manifest.component)
+                .map(|m| m.name.clone())
+                .unwrap_or_else(|| install_manifest.component.clone());
+            let extra_scan = mods_dir.join(&current).is_dir().then_some(current.as_str());
+
+            Some(tracked_status(
+                mods_dir,
+                install_manifest,
+                &name,
+                Some((&top, &current)),
+                extra_scan.map(|dir| (dir, &[][..])),
+                quick,
+            ))
+        })
+        .collect()
+}
+
+/// Check Silk, BepInEx and every tracked mod against what was installed
+pub fn verify_installation(game_dir: &Path) -> VerificationReport {
+    let mods_dir = game_dir.join("Silk").join("Mods");
+
+    VerificationReport {
+        silk: verify_silk(game_dir, false),
+        bepinex: verify_bepinex(game_dir, false),
+        mods: verify_mods(&mods_dir, false),
+    }
+}
+
+/// Put back the files of a component that are missing or modified, taking them from a
+/// freshly downloaded copy that `fill_staging` extracts into the staging directory
+fn restore_files(
+    root: &Path,
+    install_manifest: &InstallManifest,
+    renamed_top: Option<(&str, &str)>,
+    fill_staging: impl FnOnce(&Path) -> Result<(), String>,
+) -> Result<Vec<String>, String> {
+    let broken: Vec<&manifest::ManifestFile> = install_manifest
+        .files
+        .iter()
+        .filter(|file| {
+            let path = root.join(manifest::relocate(&file.path, renamed_top));
+            if file.shared {
+                !path.is_file()
+            } else {
+                !manifest::is_unchanged(file, &path)
+            }
+        })
+        .collect();
+
+    if broken.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut transaction = transaction::Transaction::begin(root)?;
+    let staging_dir = transaction.staging_dir();
+    fill_staging(&staging_dir)?;
+
+    let mut restored = Vec::new();
+    for file in broken {
+        let staged = staging_dir.join(&file.path);
+        if file.shared {
+            // Its contents depend on the other component, so whatever was staged for it is used
+            if !staged.is_file() {
+                warn!("Cannot restore shared file {}: it wasn't staged", file.path);
+                continue;
+            }
+        } else if !manifest::is_unchanged(file, &staged) {
+            return Err(format!(
+                "Cannot repair {}: the downloaded copy doesn't match the installed version {}",
+                file.path, install_manifest.version
+            ));
+        }
+
+        let relative = manifest::relocate(&file.path, renamed_top);
+        transaction.place(&staged, Path::new(&relative))?;
+        restored.push(relative);
+    }
+
+    transaction.commit();
+    Ok(restored)
+}
+
+async fn repair_silk(game_dir: &Path, status: &ComponentStatus, window: &tauri::Window, operation: &Operation) -> Result<(), String> {
+    let Some(install_manifest) = manifest::load(game_dir, manifest::SILK_COMPONENT) else {
+        // Without a manifest there is no record of the files, so reinstall the whole version
+        warn!("Silk has no install manifest; reinstalling v{}", status.version);
+        let silk_version = version::parse_version_lenient(&status.version)
+            .map(|v| v.to_string())
+            .ok_or_else(|| "Cannot repair Silk: the installed version is unknown. Reinstall it instead.".to_string())?;
+        return version::download_silk_version(&silk_version, &game_dir.to_string_lossy(), window.clone(), operation).await;
+    };
+
+    let downloaded = version::fetch_silk_archive(&install_manifest.version, window, operation).await?;
+    let restored = restore_files(game_dir, &install_manifest, None, |staging_dir| {
+        extract::extract_zip(downloaded.open()?, staging_dir, &extract::Limits::default(), |_| true, Some(operation))?;
+        doorstop::prepare_staged(game_dir, staging_dir, Loader::Silk)?;
+        // version.txt is written by the installer rather than shipped in the archive
+        fs::write(staging_dir.join("Silk").join("version.txt"), &install_manifest.version)
+            .map_err(|e| format!("Failed to write version file: {}", e))
+    })?;
+
+    info!("Repaired Silk: restored {:?}", restored);
+    Ok(())
+}
+
+async fn repair_bepinex(game_dir: &Path, window: &tauri::Window, operation: &Operation) -> Result<(), String> {
+    let Some(install_manifest) = manifest::load(game_dir, manifest::BEPINEX_COMPONENT) else {
+        warn!("BepInEx has no install manifest; reinstalling it");
+        let installed_version = bepinex::get_bepinex_version(&game_dir.to_string_lossy())
+            .unwrap_or_else(|_| bepinex::DEFAULT_BEPINEX_VERSION.to_string());
+        return bepinex::install_bepinex(&game_dir.to_string_lossy(), &installed_version, window.clone(), operation).await;
+    };
+
+    let downloaded = bepinex::fetch_bepinex_archive(&install_manifest.version, window, operation).await?;
+    let restored = restore_files(game_dir, &install_manifest, None, |staging_dir| {
+        extract::extract_zip(
+            downloaded.open()?,
+            staging_dir,
+            &extract::Limits::default(),
+            |path| {
+                path.starts_with("BepInEx")
+                    || path.to_str().is_some_and(|p| doorstop::DOORSTOP_FILES.contains(&p))
+            },
+            Some(operation),
+        )?;
+        doorstop::prepare_staged(game_dir, staging_dir, Loader::BepInEx).map(|_| ())
+    })?;
+
+    info!("Repaired BepInEx: restored {:?}", restored);
+    Ok(())
+}
+
+async fn repair_mod(
+    mods_dir: &Path,
+    status: &ComponentStatus,
+    window: &tauri::Window,
+    operation: &Operation,
+) -> Result<(), String> {
+    let install_manifest = manifest::load(mods_dir, &status.component)
+        .ok_or_else(|| format!("No install record for {}", status.name))?;
+    let (top, current) = mod_location(mods_dir, &install_manifest)
+        .ok_or_else(|| format!("The install record for {} is empty", status.name))?;
+
+    let metadata = crate::load_mod_metadata(mods_dir);
+    let installed = metadata
+        .get(&status.component)
+        .ok_or_else(|| format!("Cannot repair {}: it has no metadata", status.name))?;
+    let source_url = installed
+        .source_url
+        .clone()
+        .ok_or_else(|| format!("Cannot repair {}: its download location is unknown", status.name))?;
+
+    // The file hashes in the manifest are what the repaired files are checked against
+    let source = registry::source_for(installed.source.as_deref());
+    let downloaded = registry::fetch_file(
+        &source,
+        &source_url,
+        &download::ExpectedDownload::default(),
+        &status.name,
+        window,
+        operation,
+    )
+    .await?;
+
+    let file_name = installed.file_name.to_lowercase();
+    let is_archive = file_name.ends_with(".zip") || file_name.ends_with(".silkmod");
+    let restored = restore_files(mods_dir, &install_manifest, Some((&top, &current)), |staging_dir| {
+        if is_archive {
+            extract::extract_zip(downloaded.open()?, &staging_dir.join(&top), &extract::Limits::default(), |_| true, Some(operation))
+                .map(|_| ())
+        } else {
+            downloaded.copy_to(&staging_dir.join(&top))
+        }
+    })?;
+
+    info!("Repaired {}: restored {:?}", status.name, restored);
+    Ok(())
+}
+
+/// Re-fetch and restore only the components with missing or modified files
+pub async fn repair(game_dir: &Path, window: &tauri::Window, operation: &Operation) -> Result<VerificationReport, String> {
+    let report = verify_installation(game_dir);
+    let mods_dir = game_dir.join("Silk").join("Mods");
+
+    if let Some(status) = report.silk.as_ref().filter(|s| s.is_broken()) {
+        let _ = window.emit("install-progress", "Repairing Silk...");
+        repair_silk(game_dir, status, window, operation).await?;
+    }
+
+    if report.bepinex.as_ref().is_some_and(|s| s.is_broken()) {
+        let _ = window.emit("install-progress", "Repairing BepInEx...");
+        repair_bepinex(game_dir, window, operation).await?;
+    }
+
+    for status in report.mods.iter().filter(|s| s.is_broken()) {
+        let _ = window.emit("install-progress", format!("Repairing {}...", status.name));
+        repair_mod(&mods_dir, status, window, operation).await?;
+    }
+
+    let _ = window.emit("install-progress", "Repair complete");
+    Ok(verify_installation(game_dir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    const SILK_CONFIG: &str = "[General]\r\nenabled = true\r\ntarget_assembly = Silk\\Silk.Preloader.dll\r\n";
+    const BEPINEX_CONFIG: &str = "[General]\r\nenabled = true\r\ntarget_assembly = BepInEx\\core\\BepInEx.Preloader.dll\r\n\r\n[UnityMono]\r\ndll_search_path_override = BepInEx\\core\r\n";
+
+    fn temp_game(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("entwine-integrity-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn stage(staging_dir: &Path, files: &[(&str, &str)]) {
+        for (path, content) in files {
+            let path = staging_dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+    }
+
+    fn silk_files() -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("winhttp.dll", "silk proxy"),
+            ("doorstop_config.ini", SILK_CONFIG),
+            ("Silk/Silk.Preloader.dll", "silk"),
+        ]
+    }
+
+    fn bepinex_files() -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("winhttp.dll", "bepinex proxy"),
+            ("doorstop_config.ini", BEPINEX_CONFIG),
+            (".doorstop_version", "4.3.0"),
+            ("BepInEx/core/BepInEx.Preloader.dll", "bepinex"),
+        ]
+    }
+
+    /// The same steps the Silk and BepInEx installers take, without the download
+    fn install(game_dir: &Path, component: &str, loader: Loader, files: &[(&str, &str)]) {
+        let mut transaction = transaction::Transaction::begin(game_dir).unwrap();
+        let staging_dir = transaction.staging_dir();
+        stage(&staging_dir, files);
+        let shared = doorstop::prepare_staged(game_dir, &staging_dir, loader).unwrap();
+
+        let mut install_manifest =
+            InstallManifest::from_staged(component, "1.0.0", &staging_dir, &[PathBuf::new()]).unwrap();
+        transaction.place_tree(&staging_dir).unwrap();
+        if shared {
+            doorstop::record_shared(game_dir, &mut install_manifest, &mut transaction).unwrap();
+        }
+        manifest::save(game_dir, &install_manifest).unwrap();
+        transaction.commit();
+    }
+
+    fn assert_intact(status: Option<ComponentStatus>) {
+        let status = status.unwrap();
+        assert!(status.tracked);
+        assert!(!status.is_broken(), "missing {:?}, modified {:?}", status.missing, status.modified);
+    }
+
+    #[test]
+    fn bepinex_over_silk_verifies() {
+        let game_dir = temp_game("bepinex-over-silk");
+        install(&game_dir, manifest::SILK_COMPONENT, Loader::Silk, &silk_files());
+        install(&game_dir, manifest::BEPINEX_COMPONENT, Loader::BepInEx, &bepinex_files());
+
+        // Silk keeps booting and its proxy stays in place
+        let config = fs::read_to_string(game_dir.join("doorstop_config.ini")).unwrap();
+        assert!(config.contains("target_assembly = Silk\\Silk.Preloader.dll"), "{}", config);
+        assert!(config.contains("dll_search_path_override = BepInEx\\core"), "{}", config);
+        assert_eq!(fs::read_to_string(game_dir.join("winhttp.dll")).unwrap(), "silk proxy");
+
+        assert_intact(verify_silk(&game_dir, false));
+        assert_intact(verify_bepinex(&game_dir, false));
+        assert_intact(verify_silk(&game_dir, true));
+        fs::remove_dir_all(&game_dir).unwrap();
+    }
+
+    #[test]
+    fn silk_over_bepinex_verifies() {
+        let game_dir = temp_game("silk-over-bepinex");
+        install(&game_dir, manifest::BEPINEX_COMPONENT, Loader::BepInEx, &bepinex_files());
+        install(&game_dir, manifest::SILK_COMPONENT, Loader::Silk, &silk_files());
+
+        let config = fs::read_to_string(game_dir.join("doorstop_config.ini")).unwrap();
+        assert!(config.contains("target_assembly = Silk\\Silk.Preloader.dll"), "{}", config);
+
+        assert_intact(verify_silk(&game_dir, false));
+        assert_intact(verify_bepinex(&game_dir, false));
+        fs::remove_dir_all(&game_dir).unwrap();
+    }
+
+    #[test]
+    fn unshared_files_are_still_hashed() {
+        let game_dir = temp_game("unshared");
+        install(&game_dir, manifest::SILK_COMPONENT, Loader::Silk, &silk_files());
+        fs::write(game_dir.join("winhttp.dll"), "tampered").unwrap();
+
+        let status = verify_silk(&game_dir, false).unwrap();
+        assert_eq!(status.modified, vec!["winhttp.dll".to_string()]);
+        fs::remove_dir_all(&game_dir).unwrap();
+    }
+
+    #[test]
+    fn restores_missing_shared_config() {
+        let game_dir = temp_game("restore-shared");
+        install(&game_dir, manifest::SILK_COMPONENT, Loader::Silk, &silk_files());
+        install(&game_dir, manifest::BEPINEX_COMPONENT, Loader::BepInEx, &bepinex_files());
+        fs::remove_file(game_dir.join("doorstop_config.ini")).unwrap();
+
+        // Repairing BepInEx alone can't bring back Silk's config
+        let bepinex_manifest = manifest::load(&game_dir, manifest::BEPINEX_COMPONENT).unwrap();
+        restore_files(&game_dir, &bepinex_manifest, None, |staging_dir| {
+            stage(staging_dir, &bepinex_files());
+            doorstop::prepare_staged(&game_dir, staging_dir, Loader::BepInEx).map(|_| ())
+        })
+        .unwrap();
+        assert!(!game_dir.join("doorstop_config.ini").exists());
+
+        let silk_manifest = manifest::load(&game_dir, manifest::SILK_COMPONENT).unwrap();
+        let restored = restore_files(&game_dir, &silk_manifest, None, |staging_dir| {
+            stage(staging_dir, &silk_files());
+            doorstop::prepare_staged(&game_dir, staging_dir, Loader::Silk).map(|_| ())
+        })
+        .unwrap();
+
+        assert_eq!(restored, vec!["doorstop_config.ini".to_string()]);
+        let config = fs::read_to_string(game_dir.join("doorstop_config.ini")).unwrap();
+        assert!(config.contains("target_assembly = Silk\\Silk.Preloader.dll"), "{}", config);
+        assert_intact(verify_silk(&game_dir, false));
+        assert_intact(verify_bepinex(&game_dir, false));
+        fs::remove_dir_all(&game_dir).unwrap();
+    }
+}
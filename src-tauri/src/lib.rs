@@ -1,17 +1,32 @@
 use log::{error, info, warn};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tauri::{Emitter, Manager};
 
 mod version;
+mod assembly;
+mod backup;
 mod bepinex;
+mod bepinex_config;
+mod cache;
 mod settings;
 mod config;
-
-const MODS_API_URL: &str = "https://silk.abstractmelon.net/api/mods";
-const MODS_BASE_URL: &str = "https://silk.abstractmelon.net";
+mod doorstop;
+mod download;
+mod extract;
+mod integrity;
+mod manifest;
+mod resolver;
+mod modpack;
+mod operations;
+mod profiles;
+mod registry;
+mod steam;
+mod transaction;
+mod vdf;
+mod yaml_edit;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -24,10 +39,19 @@ pub struct Mod {
     pub file_name: String,
     pub file_path: String,
     pub file_size: u64,
+    #[serde(default)]
+    pub sha256: Option<String>,
     pub icon_path: String,
     pub upload_date: String,
     pub downloads: u64,
     pub last_downloaded: Option<String>,
+    #[serde(default)]
+    pub dependencies: Vec<resolver::ModDependency>,
+    #[serde(default)]
+    pub conflicts: Vec<resolver::ModDependency>,
+    /// Id of the registry source that listed this mod
+    #[serde(default)]
+    pub source: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -41,100 +65,55 @@ pub struct InstalledMod {
     pub author: String,
     pub description: String,
     pub icon_path: String,
+    #[serde(default)]
+    pub source_url: Option<String>,
+    /// Id of the registry source the mod was installed from
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub origin: Option<ModOrigin>,
+    /// Read from the mod's DLL when listing installed mods
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assembly: Option<assembly::AssemblyInfo>,
+}
+
+/// How a mod was installed
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ModOrigin {
+    /// Listed by a registry source
+    Registry,
+    /// A `.dll`, `.zip` or `.silkmod` file on disk; `source_url` is its path
+    File,
+    /// A direct download link; `source_url` is the link
+    Url,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AppStatus {
     pub silk_installed: bool,
+    /// Silk is present but files are missing or changed
+    pub silk_needs_repair: bool,
     pub game_path: Option<String>,
     pub mods_path: Option<String>,
-}
-
-// Get common Steam library paths
-fn get_steam_library_paths() -> Vec<PathBuf> {
-    let mut paths = Vec::new();
-    
-    #[cfg(target_os = "linux")]
-    {
-        if let Some(home) = dirs::home_dir() {
-            // Default Steam path
-            paths.push(home.join(".steam/steam/steamapps/common"));
-            paths.push(home.join(".local/share/Steam/steamapps/common"));
-            // Flatpak Steam
-            paths.push(home.join(".var/app/com.valvesoftware.Steam/.steam/steam/steamapps/common"));
-            paths.push(home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam/steamapps/common"));
-        }
-        
-        // Check for additional library folders
-        // Common external drive mount points
-        for entry in fs::read_dir("/run/media").into_iter().flatten() {
-            if let Ok(user_entry) = entry {
-                for drive_entry in fs::read_dir(user_entry.path()).into_iter().flatten() {
-                    if let Ok(drive) = drive_entry {
-                        paths.push(drive.path().join("SteamLibrary/steamapps/common"));
-                        paths.push(drive.path().join("steamapps/common"));
-                    }
-                }
-            }
-        }
-        
-        // Check /media as well
-        for entry in fs::read_dir("/media").into_iter().flatten() {
-            if let Ok(user_entry) = entry {
-                for drive_entry in fs::read_dir(user_entry.path()).into_iter().flatten() {
-                    if let Ok(drive) = drive_entry {
-                        paths.push(drive.path().join("SteamLibrary/steamapps/common"));
-                        paths.push(drive.path().join("steamapps/common"));
-                    }
-                }
-            }
-        }
-
-        // Check mount points in /mnt as well
-        for entry in fs::read_dir("/mnt").into_iter().flatten() {
-            if let Ok(drive) = entry {
-                paths.push(drive.path().join("SteamLibrary/steamapps/common"));
-                paths.push(drive.path().join("steamapps/common"));
-            }
-        }
-
-    }
-    
-    #[cfg(target_os = "windows")]
-    {
-        paths.push(PathBuf::from("C:\\Program Files (x86)\\Steam\\steamapps\\common"));
-        paths.push(PathBuf::from("C:\\Program Files\\Steam\\steamapps\\common"));
-        
-        // Check other drives
-        for letter in 'D'..='Z' {
-            paths.push(PathBuf::from(format!("{}:\\SteamLibrary\\steamapps\\common", letter)));
-            paths.push(PathBuf::from(format!("{}:\\Steam\\steamapps\\common", letter)));
-        }
-    }
-    
-    #[cfg(target_os = "macos")]
-    {
-        if let Some(home) = dirs::home_dir() {
-            paths.push(home.join("Library/Application Support/Steam/steamapps/common"));
-        }
-    }
-    
-    paths
+    pub active_install: Option<settings::GameInstall>,
 }
 
 // Find SpiderHeck installation
 fn find_spiderheck_path() -> Option<PathBuf> {
-    for steam_path in get_steam_library_paths() {
-        let spiderheck_path = steam_path.join("SpiderHeck");
-        if spiderheck_path.exists() {
-            return Some(spiderheck_path);
-        }
+    if let Some(path) = steam::find_app_install_dir(steam::SPIDERHECK_APP_ID) {
+        return Some(path);
     }
-    None
+
+    // No app manifest found; fall back to the default folder name in each library
+    steam::get_steam_library_paths()
+        .into_iter()
+        .map(|library| library.join("steamapps/common/SpiderHeck"))
+        .find(|path| path.exists())
 }
 
-fn is_spiderheck_installation(game_path: &PathBuf) -> bool {
+fn is_spiderheck_installation(game_path: &Path) -> bool {
     let has_known_executable = [
         "SpiderHeckApp.exe",
         "SpiderHeckApp",
@@ -148,41 +127,27 @@ fn is_spiderheck_installation(game_path: &PathBuf) -> bool {
     has_known_executable || has_proton_layout
 }
 
-#[tauri::command]
-async fn get_app_status() -> Result<AppStatus, String> {
-    let game_path = find_spiderheck_path();
-
-    let silk_installed = game_path
-        .as_ref()
-        .map(|p| p.join("winhttp.dll").exists() || p.join("Silk").exists())
-        .unwrap_or(false);
+fn build_app_status(game_path: Option<PathBuf>, active_install: Option<settings::GameInstall>) -> AppStatus {
+    // A quick check (presence and size) so half-broken installs aren't reported as installed
+    let silk_status = game_path.as_ref().and_then(|p| integrity::verify_silk(p, true));
+    let silk_installed = silk_status.as_ref().is_some_and(|status| !status.is_broken());
+    let silk_needs_repair = silk_status.as_ref().is_some_and(|status| status.is_broken());
 
     let mods_path = game_path.as_ref().map(|p| {
         p.join("Silk/Mods").to_string_lossy().to_string()
     });
 
-    info!(
-        "App status: silk_installed={}, game_path={:?}",
-        silk_installed,
-        game_path.as_ref().map(|p| p.display().to_string())
-    );
-
-    Ok(AppStatus {
+    AppStatus {
         silk_installed,
+        silk_needs_repair,
         game_path: game_path.map(|p| p.to_string_lossy().to_string()),
         mods_path,
-    })
-}
-
-#[tauri::command]
-fn get_app_version() -> String {
-    env!("CARGO_PKG_VERSION").to_string()
+        active_install,
+    }
 }
 
-#[tauri::command]
-async fn set_game_path(path: String) -> Result<AppStatus, String> {
-    info!("Setting game path to: {}", path);
-    let game_path = PathBuf::from(&path);
+fn validate_game_path(path: &str) -> Result<(), String> {
+    let game_path = PathBuf::from(path);
 
     if !game_path.exists() {
         warn!("Game path does not exist: {}", path);
@@ -196,46 +161,139 @@ async fn set_game_path(path: String) -> Result<AppStatus, String> {
                 .to_string(),
         );
     }
-    
-    let silk_installed = game_path.join("winhttp.dll").exists() || game_path.join("Silk").exists();
-    
-    let mods_path = game_path.join("Silk/Mods").to_string_lossy().to_string();
-    
-    Ok(AppStatus {
-        silk_installed,
-        game_path: Some(path),
-        mods_path: Some(mods_path),
-    })
+
+    Ok(())
 }
 
 #[tauri::command]
-async fn fetch_mods() -> Result<Vec<Mod>, String> {
-    info!("Fetching mod list from {}", MODS_API_URL);
-    let client = reqwest::Client::new();
+async fn get_app_status() -> Result<AppStatus, String> {
+    let mut app_settings = settings::load_settings()?;
+
+    let active_install = match app_settings.active_game_install().cloned() {
+        Some(install) => Some(install),
+        None => {
+            // Nothing selected yet: auto-detect the Steam copy and remember it
+            match find_spiderheck_path() {
+                Some(detected) => {
+                    let install = app_settings.add_game_install("Steam", &detected.to_string_lossy());
+                    app_settings.active_install = Some(install.id.clone());
+                    settings::save_settings(&app_settings)?;
+                    Some(install)
+                }
+                None => None,
+            }
+        }
+    };
 
-    let response = client
-        .get(MODS_API_URL)
-        .send()
-        .await
-        .map_err(|e| {
-            error!("Failed to fetch mods: {}", e);
-            format!("Failed to fetch mods: {}", e)
-        })?;
+    let game_path = active_install.as_ref().map(|install| PathBuf::from(&install.path));
+    let status = build_app_status(game_path, active_install);
 
-    let mods: Vec<Mod> = response
-        .json()
-        .await
-        .map_err(|e| {
-            error!("Failed to parse mods JSON: {}", e);
-            format!("Failed to parse mods: {}", e)
-        })?;
+    info!(
+        "App status: silk_installed={}, game_path={:?}",
+        status.silk_installed,
+        status.game_path
+    );
+
+    Ok(status)
+}
 
-    info!("Fetched {} mods", mods.len());
-    Ok(mods)
+#[tauri::command]
+fn get_app_version() -> String {
+    env!("CARGO_PKG_VERSION").to_string()
 }
 
 #[tauri::command]
-async fn install_silk(game_path: String, window: tauri::Window) -> Result<(), String> {
+async fn set_game_path(path: String, name: Option<String>) -> Result<AppStatus, String> {
+    info!("Setting game path to: {}", path);
+    validate_game_path(&path)?;
+
+    let mut app_settings = settings::load_settings()?;
+    let install = app_settings.add_game_install(name.as_deref().unwrap_or("SpiderHeck"), &path);
+    app_settings.active_install = Some(install.id.clone());
+    settings::save_settings(&app_settings)?;
+
+    Ok(build_app_status(Some(PathBuf::from(&path)), Some(install)))
+}
+
+// Game Install Commands
+
+#[tauri::command]
+async fn list_game_installs() -> Result<Vec<settings::GameInstall>, String> {
+    Ok(settings::load_settings()?.game_installs)
+}
+
+#[tauri::command]
+async fn add_game_install(name: String, path: String) -> Result<settings::GameInstall, String> {
+    info!("Adding game install '{}' at {}", name, path);
+    validate_game_path(&path)?;
+
+    let mut app_settings = settings::load_settings()?;
+    let install = app_settings.add_game_install(&name, &path);
+    if app_settings.active_install.is_none() {
+        app_settings.active_install = Some(install.id.clone());
+    }
+    settings::save_settings(&app_settings)?;
+
+    Ok(install)
+}
+
+#[tauri::command]
+async fn remove_game_install(id: String) -> Result<(), String> {
+    info!("Removing game install: {}", id);
+    let mut app_settings = settings::load_settings()?;
+    app_settings.remove_game_install(&id)?;
+    settings::save_settings(&app_settings)
+}
+
+#[tauri::command]
+async fn set_active_game_install(id: String) -> Result<AppStatus, String> {
+    info!("Switching active game install to: {}", id);
+    let mut app_settings = settings::load_settings()?;
+    let install = app_settings.set_active_game_install(&id)?;
+    settings::save_settings(&app_settings)?;
+
+    Ok(build_app_status(Some(PathBuf::from(&install.path)), Some(install)))
+}
+
+#[tauri::command]
+async fn fetch_mods() -> Result<cache::ModRegistry, String> {
+    match registry::fetch_all().await {
+        Ok(mods) => {
+            info!("Fetched {} mods", mods.len());
+            cache::save_registry(&mods);
+            Ok(cache::ModRegistry { mods, stale_since: None })
+        }
+        Err(e) => match cache::load_registry() {
+            Some(registry) => {
+                warn!("{}; showing the cached mod list", e);
+                Ok(registry)
+            }
+            None => Err(e),
+        },
+    }
+}
+
+/// Get the configured mod registries, or the official one when none are configured
+#[tauri::command]
+fn list_registry_sources() -> Vec<settings::RegistrySource> {
+    registry::list_sources()
+}
+
+/// Get a mod's icon as a data URL, served from the cache once it has been downloaded
+#[tauri::command]
+async fn get_mod_icon(icon_path: String, source: Option<String>) -> Result<String, String> {
+    let source = registry::source_for(source.as_deref());
+    registry::icon(&source, &icon_path).await
+}
+
+#[tauri::command]
+async fn install_silk(
+    game_path: Option<String>,
+    operation_id: Option<String>,
+    window: tauri::Window,
+) -> Result<(), String> {
+    let game_path = settings::resolve_game_path(game_path)?;
+    let operation = operations::Operation::start(operation_id);
     info!("Installing Silk to: {}", game_path);
     let game_dir = PathBuf::from(&game_path);
 
@@ -244,22 +302,25 @@ async fn install_silk(game_path: String, window: tauri::Window) -> Result<(), St
         return Err("Game path does not exist".to_string());
     }
 
-    // Try to determine the latest Silk version and delegate to the download helper
-    let latest = match version::get_latest_silk_version().await {
-        Ok(v) => v,
-        Err(_) => {
-            // Fallback: attempt to read the raw version file or fall back to 0.6.1
-            let _ = window.emit("install-progress", "Failed to fetch latest Silk version; falling back to v0.6.1");
-            "0.6.1".to_string()
-        }
-    };
+    // Rate limits and network failures already fall back to the cached release list, so
+    // an error here means there is no known release to install
+    let app_settings = settings::load_settings()?;
+    let latest = version::get_latest_silk_version(app_settings.silk_channel)
+        .await
+        .map_err(|e| format!("Failed to determine the latest Silk version: {}", e))?;
+
+    // Updating Silk can break mods, so keep a way back to the current state
+    if game_dir.join("Silk").exists() {
+        backup::create_backup_before(&game_path, &format!("Installing Silk {}", latest), &app_settings.backup_retention)?;
+    }
 
     // Delegate to existing helper which downloads & extracts the specified version and writes version.txt
-    version::download_silk_version(&latest, &game_path, window).await
+    version::download_silk_version(&latest, &game_path, window, &operation).await
 }
 
 #[tauri::command]
-async fn get_installed_mods(mods_path: String) -> Result<Vec<InstalledMod>, String> {
+async fn get_installed_mods(mods_path: Option<String>) -> Result<Vec<InstalledMod>, String> {
+    let mods_path = settings::resolve_mods_path(mods_path)?;
     let mods_dir = PathBuf::from(&mods_path);
     
     if !mods_dir.exists() {
@@ -267,15 +328,7 @@ async fn get_installed_mods(mods_path: String) -> Result<Vec<InstalledMod>, Stri
     }
     
     let mut installed_mods = Vec::new();
-    let metadata_path = mods_dir.join(".entwine_metadata.json");
-    
-    // Load existing metadata
-    let metadata: std::collections::HashMap<String, InstalledMod> = if metadata_path.exists() {
-        let content = fs::read_to_string(&metadata_path).unwrap_or_default();
-        serde_json::from_str(&content).unwrap_or_default()
-    } else {
-        std::collections::HashMap::new()
-    };
+    let metadata = load_mod_metadata(&mods_dir);
     
     for entry in fs::read_dir(&mods_dir).map_err(|e| format!("Failed to read mods directory: {}", e))? {
         let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
@@ -293,6 +346,7 @@ async fn get_installed_mods(mods_path: String) -> Result<Vec<InstalledMod>, Stri
         let is_enabled = file_name.to_lowercase().ends_with(".dll");
         let is_disabled = file_name.to_lowercase().ends_with(".dll.disabled");
         let is_mod_folder = path.is_dir();
+        let folder_enabled = is_mod_folder && !file_name.ends_with(".disabled");
         
         if is_enabled || is_disabled || is_mod_folder {
             // Try to find metadata for this mod
@@ -301,130 +355,409 @@ async fn get_installed_mods(mods_path: String) -> Result<Vec<InstalledMod>, Stri
                 .trim_end_matches(".dll")
                 .to_string();
             
-            if let Some(mod_meta) = metadata.get(&base_name) {
-                installed_mods.push(InstalledMod {
-                    enabled: is_enabled || is_mod_folder,
+            let mod_meta = metadata.get(&base_name);
+            let mut installed = match mod_meta {
+                Some(mod_meta) => InstalledMod {
+                    enabled: is_enabled || folder_enabled,
                     file_name: file_name.clone(),
                     ..mod_meta.clone()
-                });
-            } else {
+                },
                 // Create basic metadata from filename
-                installed_mods.push(InstalledMod {
+                None => InstalledMod {
                     id: base_name.clone(),
                     name: base_name.clone(),
                     file_name,
-                    enabled: is_enabled || is_mod_folder,
+                    enabled: is_enabled || folder_enabled,
                     version: "Unknown".to_string(),
                     author: "Unknown".to_string(),
                     description: "Locally installed mod".to_string(),
                     icon_path: String::new(),
-                });
+                    source_url: None,
+                    source: None,
+                    origin: None,
+                    assembly: None,
+                },
+            };
+
+            // Registry metadata wins; for anything else the assembly knows more than the file name
+            let from_registry = mod_meta.is_some_and(|m| m.origin.unwrap_or(ModOrigin::Registry) == ModOrigin::Registry);
+            installed.assembly = read_mod_assembly(&path, &base_name);
+            if let Some(info) = installed.assembly.as_ref().filter(|_| !from_registry) {
+                installed.id = info.mod_id();
+                installed.name = info.mod_name();
+                installed.version = info.mod_version();
+                if let Some(author) = info.mod_author() {
+                    installed.author = author;
+                }
+                if let Some(description) = &info.description {
+                    installed.description = description.clone();
+                }
             }
+            installed_mods.push(installed);
         }
     }
     
     Ok(installed_mods)
 }
 
+/// Read the .NET metadata of a mod's DLL, or of the main DLL in a mod folder: the one
+/// named after the folder, or else the first with a Silk or BepInEx plugin attribute
+fn read_mod_assembly(path: &Path, base_name: &str) -> Option<assembly::AssemblyInfo> {
+    if !path.is_dir() {
+        return assembly::read_assembly_cached(path);
+    }
+
+    let mut dlls: Vec<PathBuf> = fs::read_dir(path)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|e| e.eq_ignore_ascii_case("dll")))
+        .collect();
+    dlls.sort();
+
+    let folder_name = base_name.trim_end_matches(".disabled");
+    if let Some(main) = dlls
+        .iter()
+        .find(|p| p.file_stem().is_some_and(|s| s.to_string_lossy().eq_ignore_ascii_case(folder_name)))
+    {
+        return assembly::read_assembly_cached(main);
+    }
+    dlls.iter()
+        .filter_map(|dll| assembly::read_assembly_cached(dll))
+        .find(|info| info.silk_mod.is_some() || info.plugin.is_some())
+}
+
+/// Work out which mods need to be installed for `mod_info`, dependencies first
+async fn plan_mod_install(mod_info: &Mod, mods_path: &str) -> Result<resolver::InstallPlan, String> {
+    let registry = match fetch_mods().await {
+        Ok(registry) => registry.mods,
+        Err(e) => {
+            // Dependencies can still be satisfied by mods that are already installed
+            warn!("Resolving '{}' without the mod registry: {}", mod_info.name, e);
+            Vec::new()
+        }
+    };
+    let installed = get_installed_mods(Some(mods_path.to_string())).await?;
+
+    resolver::resolve_install(mod_info, &registry, &installed)
+}
+
+#[tauri::command]
+async fn resolve_mod_install(
+    mod_info: Mod,
+    mods_path: Option<String>,
+) -> Result<resolver::InstallPlan, String> {
+    let mods_path = settings::resolve_mods_path(mods_path)?;
+    plan_mod_install(&mod_info, &mods_path).await
+}
+
 #[tauri::command]
 async fn install_mod(
     mod_info: Mod,
-    mods_path: String,
+    mods_path: Option<String>,
+    operation_id: Option<String>,
     window: tauri::Window,
 ) -> Result<(), String> {
-    info!("Installing mod '{}' (v{}) to {}", mod_info.name, mod_info.version, mods_path);
-    let mods_dir = PathBuf::from(&mods_path);
+    let mods_path = settings::resolve_mods_path(mods_path)?;
+    let operation = operations::Operation::start(operation_id);
+    install_mod_with_dependencies(&mod_info, &mods_path, &window, &operation).await
+}
 
-    // Create mods directory if it doesn't exist
-    fs::create_dir_all(&mods_dir)
+/// Install a mod from a `.dll`, `.zip` or `.silkmod` file
+#[tauri::command]
+async fn install_mod_from_path(
+    path: String,
+    mods_path: Option<String>,
+    operation_id: Option<String>,
+    window: tauri::Window,
+) -> Result<(), String> {
+    let mods_path = settings::resolve_mods_path(mods_path)?;
+    let operation = operations::Operation::start(operation_id);
+
+    let file = PathBuf::from(&path);
+    let file_name = file
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| format!("{} is not a file", path))?;
+    let file_size = fs::metadata(&file)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?
+        .len();
+
+    let mod_info = direct_mod(&file_name, path, file_size)?;
+    install_planned_mods(&[mod_info], ModOrigin::File, Path::new(&mods_path), &window, &operation).await
+}
+
+/// Install a mod from a direct link to a `.dll`, `.zip` or `.silkmod` file
+#[tauri::command]
+async fn install_mod_from_url(
+    url: String,
+    mods_path: Option<String>,
+    operation_id: Option<String>,
+    window: tauri::Window,
+) -> Result<(), String> {
+    let mods_path = settings::resolve_mods_path(mods_path)?;
+    let operation = operations::Operation::start(operation_id);
+
+    let parsed = reqwest::Url::parse(url.trim()).map_err(|e| format!("Invalid URL {}: {}", url, e))?;
+    if parsed.scheme() != "http" && parsed.scheme() != "https" {
+        return Err(format!("Only http and https links can be installed, not {}", parsed.scheme()));
+    }
+    let file_name = parsed
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_string())
+        .ok_or_else(|| format!("The link {} does not point to a file", url))?;
+
+    let mod_info = direct_mod(&file_name, parsed.to_string(), 0)?;
+    install_planned_mods(&[mod_info], ModOrigin::Url, Path::new(&mods_path), &window, &operation).await
+}
+
+/// Describe a mod that isn't listed by any registry, named after its file
+fn direct_mod(file_name: &str, location: String, file_size: u64) -> Result<Mod, String> {
+    let lower = file_name.to_lowercase();
+    let stem = [".dll", ".zip", ".silkmod"]
+        .iter()
+        .find(|ext| lower.ends_with(*ext))
+        .map(|ext| &file_name[..file_name.len() - ext.len()])
+        .ok_or_else(|| format!("{} is not a mod: expected a .dll, .zip or .silkmod file", file_name))?;
+    if stem.is_empty() {
+        return Err(format!("{} is not a valid mod file name", file_name));
+    }
+
+    Ok(Mod {
+        id: stem.to_string(),
+        name: stem.to_string(),
+        description: format!("Installed from {}", file_name),
+        version: "Unknown".to_string(),
+        author: "Unknown".to_string(),
+        file_name: file_name.to_string(),
+        file_path: location,
+        file_size,
+        sha256: None,
+        icon_path: String::new(),
+        upload_date: String::new(),
+        downloads: 0,
+        last_downloaded: None,
+        dependencies: Vec::new(),
+        conflicts: Vec::new(),
+        source: None,
+    })
+}
+
+/// Resolve and install a mod and everything it depends on
+async fn install_mod_with_dependencies(
+    mod_info: &Mod,
+    mods_path: &str,
+    window: &tauri::Window,
+    operation: &operations::Operation,
+) -> Result<(), String> {
+    // Resolve the full install set before downloading anything
+    let plan = plan_mod_install(mod_info, mods_path).await?;
+    if plan.to_install.len() > 1 {
+        info!(
+            "Installing '{}' with dependencies: {:?}",
+            mod_info.name,
+            plan.to_install.iter().map(|m| &m.id).collect::<Vec<_>>()
+        );
+    }
+
+    install_planned_mods(&plan.to_install, ModOrigin::Registry, Path::new(mods_path), window, operation).await
+}
+
+/// A downloaded mod extracted or copied into a transaction's staging directory
+struct StagedMod<'a> {
+    mod_info: &'a Mod,
+    staged_path: PathBuf,
+    file_name: String,
+}
+
+/// Download and stage every mod in the plan, then move them all into place as one
+/// transaction. The Mods folder is only touched once everything is staged and
+/// validated, and any failure restores the files that were there before.
+async fn install_planned_mods(
+    mods: &[Mod],
+    origin: ModOrigin,
+    mods_dir: &Path,
+    window: &tauri::Window,
+    operation: &operations::Operation,
+) -> Result<(), String> {
+    fs::create_dir_all(mods_dir)
         .map_err(|e| format!("Failed to create mods directory: {}", e))?;
 
+    let mut transaction = transaction::Transaction::begin(mods_dir)?;
+    if let Err(e) = install_mods_in(&mut transaction, mods, origin, mods_dir, window, operation).await {
+        transaction.rollback();
+        if e == operations::CANCELLED_ERROR {
+            info!("Install cancelled; the Mods folder was left unchanged");
+            let _ = window.emit("install-progress", "Install cancelled");
+        }
+        return Err(e);
+    }
+    transaction.commit();
+
+    for mod_info in mods {
+        download::emit_progress(window, &mod_info.name, download::DownloadPhase::Done, mod_info.file_size, Some(mod_info.file_size), 0);
+        info!("Mod '{}' installed successfully", mod_info.name);
+        let _ = window.emit("install-progress", format!("{} installed successfully!", mod_info.name));
+    }
+
+    Ok(())
+}
+
+/// Stage every mod, then move them into the Mods folder and record their metadata as part of `transaction`
+async fn install_mods_in(
+    transaction: &mut transaction::Transaction,
+    mods: &[Mod],
+    origin: ModOrigin,
+    mods_dir: &Path,
+    window: &tauri::Window,
+    operation: &operations::Operation,
+) -> Result<(), String> {
+    let staging_dir = transaction.staging_dir();
+    let mut staged = Vec::new();
+    for mod_info in mods {
+        staged.push(stage_mod(mod_info, origin, &staging_dir, window, operation).await?);
+    }
+
+    // Past this point the install is no longer cancellable
+    operation.check()?;
+
+    transaction.back_up(Path::new(".entwine_metadata.json"))?;
+    for staged_mod in &staged {
+        let mod_info = staged_mod.mod_info;
+        let install_manifest = manifest::InstallManifest::from_staged(
+            &metadata_key(mod_info),
+            &mod_info.version,
+            &staging_dir,
+            &[PathBuf::from(&staged_mod.file_name)],
+        )?;
+
+        transaction.place(&staged_mod.staged_path, Path::new(&staged_mod.file_name))?;
+        transaction.back_up(&manifest::manifest_path(&install_manifest.component))?;
+        manifest::save(mods_dir, &install_manifest)?;
+        save_mod_metadata(mods_dir, mod_info, origin)?;
+    }
+
+    Ok(())
+}
+
+/// Download a mod, extract or copy it into the staging directory and check the result
+async fn stage_mod<'a>(
+    mod_info: &'a Mod,
+    origin: ModOrigin,
+    staging_dir: &Path,
+    window: &tauri::Window,
+    operation: &operations::Operation,
+) -> Result<StagedMod<'a>, String> {
+    info!("Installing mod '{}' (v{})", mod_info.name, mod_info.version);
+
+    // Determine if it's a zip or dll based on filename
+    let is_zip = is_archive_mod(mod_info);
+
+    // Names come from the registry and are joined onto the Mods folder
+    let file_name = extract::safe_file_name(if is_zip { &mod_info.name } else { &mod_info.file_name })
+        .map_err(|e| format!("Refusing to install {}: {}", mod_info.name, e))?
+        .to_string();
+
     let _ = window.emit("install-progress", format!("Downloading {}...", mod_info.name));
     
-    // Download the mod
-    let download_url = format!("{}{}", MODS_BASE_URL, mod_info.file_path);
-    
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&download_url)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to download mod: {}", e))?;
-    
-    let bytes = response
-        .bytes()
-        .await
-        .map_err(|e| format!("Failed to read mod download: {}", e))?;
-    
-    // Determine if it's a zip or dll based on filename
-    let is_zip = mod_info.file_name.to_lowercase().ends_with(".zip") 
-        || mod_info.file_name.to_lowercase().ends_with(".silkmod");
+    // Download the mod from the registry that listed it, or from where it was given
+    let expected = download::ExpectedDownload::new(Some(mod_info.file_size), mod_info.sha256.as_deref());
+    let downloaded = match origin {
+        ModOrigin::Registry => {
+            let source = registry::source_for(mod_info.source.as_deref());
+            let location = registry::resolve(&source, &mod_info.file_path)?;
+            registry::fetch_file(&source, &location, &expected, &mod_info.name, window, operation).await?
+        }
+        ModOrigin::File => download::local_file(Path::new(&mod_info.file_path), &expected, &mod_info.name)?,
+        ModOrigin::Url => {
+            let client = reqwest::Client::new();
+            download::download_to_file(&client, &mod_info.file_path, &expected, &mod_info.name, window, operation).await?
+        }
+    };
     
+    let staged_path = staging_dir.join(&file_name);
+
     if is_zip {
         let _ = window.emit("install-progress", format!("Extracting {}...", mod_info.name));
-        
-        // Extract zip to a subfolder
-        let mod_folder = mods_dir.join(&mod_info.name);
-        fs::create_dir_all(&mod_folder)
-            .map_err(|e| format!("Failed to create mod folder: {}", e))?;
-        
-        let cursor = std::io::Cursor::new(&bytes);
-        let mut archive = zip::ZipArchive::new(cursor)
-            .map_err(|e| format!("Failed to read mod archive: {}", e))?;
-        
-        for i in 0..archive.len() {
-            let mut file = archive.by_index(i)
-                .map_err(|e| format!("Failed to read zip entry: {}", e))?;
-            
-            let outpath = mod_folder.join(file.mangled_name());
-            
-            if file.name().ends_with('/') {
-                fs::create_dir_all(&outpath)
-                    .map_err(|e| format!("Failed to create directory: {}", e))?;
-            } else {
-                if let Some(parent) = outpath.parent() {
-                    fs::create_dir_all(parent)
-                        .map_err(|e| format!("Failed to create parent directory: {}", e))?;
-                }
-                
-                let mut outfile = fs::File::create(&outpath)
-                    .map_err(|e| format!("Failed to create file: {}", e))?;
-                
-                io::copy(&mut file, &mut outfile)
-                    .map_err(|e| format!("Failed to write file: {}", e))?;
-            }
+        download::emit_progress(window, &mod_info.name, download::DownloadPhase::Extracting, downloaded.size, Some(downloaded.size), 0);
+
+        let written = extract::extract_zip(downloaded.open()?, &staged_path, &extract::Limits::default(), |_| true, Some(operation))
+            .map_err(|e| format!("Failed to extract {}: {}", mod_info.name, e))?;
+        if written.is_empty() {
+            return Err(format!("The archive for {} does not contain any files", mod_info.name));
         }
     } else {
         // It's a DLL, save directly
-        let dll_path = mods_dir.join(&mod_info.file_name);
-        fs::write(&dll_path, &bytes)
-            .map_err(|e| format!("Failed to write mod file: {}", e))?;
+        downloaded.copy_to(&staged_path)?;
+        let staged_size = fs::metadata(&staged_path).map(|m| m.len()).unwrap_or(0);
+        if staged_size != downloaded.size {
+            return Err(format!("Failed to stage {}: the copied file is incomplete", mod_info.name));
+        }
     }
-    
-    // Save metadata
-    save_mod_metadata(&mods_dir, &mod_info)?;
-    
-    info!("Mod '{}' installed successfully", mod_info.name);
-    let _ = window.emit("install-progress", format!("{} installed successfully!", mod_info.name));
 
-    Ok(())
+    Ok(StagedMod {
+        mod_info,
+        staged_path,
+        file_name,
+    })
 }
 
-fn save_mod_metadata(mods_dir: &PathBuf, mod_info: &Mod) -> Result<(), String> {
+/// Load the `.entwine_metadata.json` entries for a mods folder, keyed by base file name
+fn load_mod_metadata(mods_dir: &Path) -> HashMap<String, InstalledMod> {
     let metadata_path = mods_dir.join(".entwine_metadata.json");
-    
-    let mut metadata: std::collections::HashMap<String, InstalledMod> = if metadata_path.exists() {
+
+    if metadata_path.exists() {
         let content = fs::read_to_string(&metadata_path).unwrap_or_default();
         serde_json::from_str(&content).unwrap_or_default()
     } else {
-        std::collections::HashMap::new()
-    };
+        HashMap::new()
+    }
+}
+
+/// Get the name a mod file or folder should have in the enabled or disabled state
+fn toggled_file_name(file_name: &str, enable: bool) -> String {
+    let enabled_name = file_name.trim_end_matches(".disabled");
+    if enable {
+        enabled_name.to_string()
+    } else {
+        format!("{}.disabled", enabled_name)
+    }
+}
+
+fn is_archive_mod(mod_info: &Mod) -> bool {
+    let file_name = mod_info.file_name.to_lowercase();
+    file_name.ends_with(".zip") || file_name.ends_with(".silkmod")
+}
+
+/// Get the metadata key for a mod: the folder archives extract into, or the DLL name without extension
+fn metadata_key(mod_info: &Mod) -> String {
+    if is_archive_mod(mod_info) {
+        mod_info.name.clone()
+    } else {
+        mod_info.file_name.trim_end_matches(".dll").to_string()
+    }
+}
+
+fn save_mod_metadata(mods_dir: &Path, mod_info: &Mod, origin: ModOrigin) -> Result<(), String> {
+    let metadata_path = mods_dir.join(".entwine_metadata.json");
+    let mut metadata = load_mod_metadata(mods_dir);
     
-    let base_name = mod_info.file_name
-        .trim_end_matches(".dll")
-        .trim_end_matches(".zip")
-        .trim_end_matches(".silkmod")
-        .to_string();
+    let base_name = metadata_key(mod_info);
+    let (source, icon_path, source_url) = match origin {
+        ModOrigin::Registry => {
+            let source = registry::source_for(mod_info.source.as_deref());
+            let icon_path = if mod_info.icon_path.is_empty() {
+                String::new()
+            } else {
+                registry::resolve(&source, &mod_info.icon_path).unwrap_or_default()
+            };
+            let source_url = registry::resolve(&source, &mod_info.file_path).ok();
+            (Some(source.id), icon_path, source_url)
+        }
+        ModOrigin::File | ModOrigin::Url => (None, String::new(), Some(mod_info.file_path.clone())),
+    };
     
     metadata.insert(base_name, InstalledMod {
         id: mod_info.id.clone(),
@@ -434,7 +767,11 @@ fn save_mod_metadata(mods_dir: &PathBuf, mod_info: &Mod) -> Result<(), String> {
         version: mod_info.version.clone(),
         author: mod_info.author.clone(),
         description: mod_info.description.clone(),
-        icon_path: format!("{}{}", MODS_BASE_URL, mod_info.icon_path),
+        icon_path,
+        source_url,
+        source,
+        origin: Some(origin),
+        assembly: None,
     });
     
     let json = serde_json::to_string_pretty(&metadata)
@@ -447,7 +784,8 @@ fn save_mod_metadata(mods_dir: &PathBuf, mod_info: &Mod) -> Result<(), String> {
 }
 
 #[tauri::command]
-async fn toggle_mod(mods_path: String, file_name: String, enable: bool) -> Result<(), String> {
+async fn toggle_mod(mods_path: Option<String>, file_name: String, enable: bool) -> Result<(), String> {
+    let mods_path = settings::resolve_mods_path(mods_path)?;
     info!("Toggling mod '{}': enable={}", file_name, enable);
     let mods_dir = PathBuf::from(&mods_path);
     let current_path = mods_dir.join(&file_name);
@@ -457,31 +795,8 @@ async fn toggle_mod(mods_path: String, file_name: String, enable: bool) -> Resul
         return Err("Mod file not found".to_string());
     }
     
-    // Handle folders differently - we can't easily disable them, so we'd need to rename
-    if current_path.is_dir() {
-        if enable {
-            // Already enabled as a folder
-            return Ok(());
-        } else {
-            // Rename folder to add .disabled
-            let new_path = mods_dir.join(format!("{}.disabled", file_name));
-            fs::rename(&current_path, &new_path)
-                .map_err(|e| format!("Failed to disable mod: {}", e))?;
-        }
-        return Ok(());
-    }
-    
-    let new_name = if enable {
-        // Enable: remove .disabled suffix
-        file_name.trim_end_matches(".disabled").to_string()
-    } else {
-        // Disable: add .disabled suffix
-        if file_name.ends_with(".disabled") {
-            file_name.clone()
-        } else {
-            format!("{}.disabled", file_name)
-        }
-    };
+    // Folders and DLLs are both toggled by adding or removing the .disabled suffix
+    let new_name = toggled_file_name(&file_name, enable);
     
     let new_path = mods_dir.join(&new_name);
     
@@ -494,7 +809,8 @@ async fn toggle_mod(mods_path: String, file_name: String, enable: bool) -> Resul
 }
 
 #[tauri::command]
-async fn uninstall_mod(mods_path: String, file_name: String) -> Result<(), String> {
+async fn uninstall_mod(mods_path: Option<String>, file_name: String) -> Result<manifest::UninstallReport, String> {
+    let mods_path = settings::resolve_mods_path(mods_path)?;
     info!("Uninstalling mod: {}", file_name);
     let mods_dir = PathBuf::from(&mods_path);
     let mod_path = mods_dir.join(&file_name);
@@ -503,6 +819,13 @@ async fn uninstall_mod(mods_path: String, file_name: String) -> Result<(), Strin
         warn!("Mod file not found for uninstall: {}", file_name);
         return Err("Mod file not found".to_string());
     }
+
+    // Remove exactly what was installed when there is a manifest for the mod
+    let key = file_name.trim_end_matches(".disabled").trim_end_matches(".dll");
+    if let Some(install_manifest) = manifest::load(&mods_dir, key) {
+        let enabled_name = toggled_file_name(&file_name, true);
+        return manifest::uninstall(&mods_dir, &install_manifest, Some((&enabled_name, &file_name)));
+    }
     
     if mod_path.is_dir() {
         fs::remove_dir_all(&mod_path)
@@ -512,11 +835,136 @@ async fn uninstall_mod(mods_path: String, file_name: String) -> Result<(), Strin
             .map_err(|e| format!("Failed to remove mod file: {}", e))?;
     }
     
+    Ok(manifest::UninstallReport {
+        removed: vec![file_name],
+        ..Default::default()
+    })
+}
+
+/// Replace an installed mod with its latest version, keeping its enabled state and config
+async fn apply_mod_update(
+    update: &version::ModUpdate,
+    mods_dir: &Path,
+    window: &tauri::Window,
+    operation: &operations::Operation,
+) -> Result<(), String> {
+    info!(
+        "Updating mod '{}' from {} to {}",
+        update.name, update.installed_version, update.latest_version
+    );
+    let _ = window.emit("install-progress", format!("Updating {}...", update.name));
+
+    let was_enabled = !update.file_name.ends_with(".disabled");
+    let config_path = mods_dir
+        .parent()
+        .map(|silk_dir| silk_dir.join("Config/Mods").join(format!("{}.yaml", update.mod_id)));
+    let saved_config = config_path.as_ref().and_then(|p| fs::read_to_string(p).ok());
+
+    // The old copy stays in the transaction's backup until the new version is in place
+    let mods_path = mods_dir.to_string_lossy().to_string();
+    let mut transaction = transaction::Transaction::begin(mods_dir)?;
+    let install_result = async {
+        transaction.remove(Path::new(&update.file_name))?;
+        let plan = plan_mod_install(&update.latest, &mods_path).await?;
+        install_mods_in(&mut transaction, &plan.to_install, ModOrigin::Registry, mods_dir, window, operation).await?;
+
+        // Drop the metadata entry of the old file if the new version uses a different name
+        let old_key = update.file_name.trim_end_matches(".disabled").trim_end_matches(".dll");
+        let new_key = metadata_key(&update.latest);
+        if old_key != new_key {
+            transaction.remove(&manifest::manifest_path(old_key))?;
+            let mut metadata = load_mod_metadata(mods_dir);
+            if metadata.remove(old_key).is_some() {
+                let json = serde_json::to_string_pretty(&metadata)
+                    .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
+                fs::write(mods_dir.join(".entwine_metadata.json"), json)
+                    .map_err(|e| format!("Failed to write metadata: {}", e))?;
+            }
+        }
+        Ok::<(), String>(())
+    }
+    .await;
+
+    if let Err(e) = install_result {
+        transaction.rollback();
+        if e == operations::CANCELLED_ERROR {
+            return Err(e);
+        }
+        return Err(format!("Failed to update {}: {}", update.name, e));
+    }
+    transaction.commit();
+
+    if !was_enabled {
+        let installed = get_installed_mods(Some(mods_path.clone())).await?;
+        if let Some(new_mod) = installed.iter().find(|m| m.id == update.mod_id && m.enabled) {
+            toggle_mod(Some(mods_path.clone()), new_mod.file_name.clone(), false).await?;
+        }
+    }
+
+    if let (Some(path), Some(content)) = (config_path, saved_config) {
+        if fs::read_to_string(&path).ok().as_deref() != Some(content.as_str()) {
+            fs::write(&path, content)
+                .map_err(|e| format!("Failed to restore config for {}: {}", update.name, e))?;
+        }
+    }
+
+    let _ = window.emit("install-progress", format!("{} updated to {}", update.name, update.latest_version));
     Ok(())
 }
 
 #[tauri::command]
-async fn uninstall_silk(game_path: String, window: tauri::Window) -> Result<(), String> {
+async fn check_mod_updates(mods_path: Option<String>) -> Result<Vec<version::ModUpdate>, String> {
+    let mods_path = settings::resolve_mods_path(mods_path)?;
+    let installed = get_installed_mods(Some(mods_path)).await?;
+    let registry = fetch_mods().await?.mods;
+
+    let updates = version::find_mod_updates(&installed, &registry);
+    info!("Found {} mod updates", updates.len());
+    Ok(updates)
+}
+
+#[tauri::command]
+async fn update_mod(
+    mod_id: String,
+    mods_path: Option<String>,
+    operation_id: Option<String>,
+    window: tauri::Window,
+) -> Result<(), String> {
+    let mods_path = settings::resolve_mods_path(mods_path)?;
+    let updates = check_mod_updates(Some(mods_path.clone())).await?;
+
+    let update = updates
+        .iter()
+        .find(|u| u.mod_id == mod_id)
+        .ok_or_else(|| format!("No update available for mod: {}", mod_id))?;
+
+    let operation = operations::Operation::start(operation_id);
+    apply_mod_update(update, &PathBuf::from(&mods_path), &window, &operation).await
+}
+
+#[tauri::command]
+async fn update_all_mods(
+    mods_path: Option<String>,
+    operation_id: Option<String>,
+    window: tauri::Window,
+) -> Result<Vec<String>, String> {
+    let mods_path = settings::resolve_mods_path(mods_path)?;
+    let operation = operations::Operation::start(operation_id);
+    let mods_dir = PathBuf::from(&mods_path);
+    let updates = check_mod_updates(Some(mods_path.clone())).await?;
+
+    let mut updated = Vec::new();
+    for update in &updates {
+        apply_mod_update(update, &mods_dir, &window, &operation).await?;
+        updated.push(update.name.clone());
+    }
+
+    Ok(updated)
+}
+
+#[tauri::command]
+async fn uninstall_silk(game_path: Option<String>, window: tauri::Window) -> Result<manifest::UninstallReport, String> {
+    let game_path = settings::resolve_game_path(game_path)?;
     info!("Uninstalling Silk from: {}", game_path);
     let game_dir = PathBuf::from(&game_path);
 
@@ -525,32 +973,83 @@ async fn uninstall_silk(game_path: String, window: tauri::Window) -> Result<(),
         return Err("Game path does not exist".to_string());
     }
 
-    let mut found = false;
+    let report = match manifest::load(&game_dir, manifest::SILK_COMPONENT) {
+        Some(install_manifest) => manifest::uninstall(&game_dir, &install_manifest, None)?,
+        None => uninstall_silk_without_manifest(&game_dir)?,
+    };
+    doorstop::retarget_after_uninstall(&game_dir)?;
+
+    info!("Silk uninstalled successfully");
+    let _ = window.emit("install-progress", "Silk uninstalled successfully!");
+
+    Ok(report)
+}
+
+/// Uninstall a Silk install from before manifests were recorded, keeping the user's mods and configs
+fn uninstall_silk_without_manifest(game_dir: &Path) -> Result<manifest::UninstallReport, String> {
+    let mut report = manifest::UninstallReport::default();
 
-    // Remove injected DLL if present
+    // BepInEx loads through the same proxy DLL
     let winhttp = game_dir.join("winhttp.dll");
     if winhttp.exists() {
-        fs::remove_file(&winhttp)
-            .map_err(|e| format!("Failed to remove winhttp.dll: {}", e))?;
-        found = true;
+        if bepinex::is_bepinex_installed(&game_dir.to_string_lossy()) {
+            report.shared.push("winhttp.dll".to_string());
+        } else {
+            fs::remove_file(&winhttp)
+                .map_err(|e| format!("Failed to remove winhttp.dll: {}", e))?;
+            report.removed.push("winhttp.dll".to_string());
+        }
     }
 
-    // Remove Silk directory and everything under it
+    // Remove the Silk directory, apart from the user's mods and configs
     let silk_dir = game_dir.join("Silk");
     if silk_dir.exists() {
-        fs::remove_dir_all(&silk_dir)
-            .map_err(|e| format!("Failed to remove Silk directory: {}", e))?;
-        found = true;
+        for entry in fs::read_dir(&silk_dir).map_err(|e| format!("Failed to read Silk directory: {}", e))? {
+            let path = entry.map_err(|e| format!("Failed to read entry: {}", e))?.path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            if name == "Mods" || name == "Config" {
+                report.modified.push(format!("Silk/{}", name));
+                continue;
+            }
+
+            let result = if path.is_dir() { fs::remove_dir_all(&path) } else { fs::remove_file(&path) };
+            result.map_err(|e| format!("Failed to remove Silk/{}: {}", name, e))?;
+            report.removed.push(format!("Silk/{}", name));
+        }
+        let _ = fs::remove_dir(&silk_dir);
     }
 
-    if !found {
+    if report.removed.is_empty() && report.modified.is_empty() {
         return Err("Silk not found at this path".to_string());
     }
 
-    info!("Silk uninstalled successfully");
-    let _ = window.emit("install-progress", "Silk uninstalled successfully!");
+    Ok(report)
+}
 
-    Ok(())
+// Integrity Commands
+
+#[tauri::command]
+async fn verify_installation(game_path: Option<String>) -> Result<integrity::VerificationReport, String> {
+    let game_path = settings::resolve_game_path(game_path)?;
+    Ok(integrity::verify_installation(&PathBuf::from(game_path)))
+}
+
+#[tauri::command]
+async fn repair(
+    game_path: Option<String>,
+    operation_id: Option<String>,
+    window: tauri::Window,
+) -> Result<integrity::VerificationReport, String> {
+    let game_path = settings::resolve_game_path(game_path)?;
+    let operation = operations::Operation::start(operation_id);
+    integrity::repair(&PathBuf::from(game_path), &window, &operation).await
+}
+
+// Operation Commands
+
+#[tauri::command]
+async fn cancel_operation(operation_id: String) -> Result<bool, String> {
+    Ok(operations::cancel(&operation_id))
 }
 
 // Log Commands
@@ -580,64 +1079,143 @@ async fn read_log_file(app: tauri::AppHandle) -> Result<String, String> {
 // Version Management Commands
 
 #[tauri::command]
-async fn get_silk_version(game_path: String) -> Result<String, String> {
+async fn get_silk_version(game_path: Option<String>) -> Result<String, String> {
+    let game_path = settings::resolve_game_path(game_path)?;
     version::get_installed_silk_version(&game_path)
 }
 
 #[tauri::command]
-async fn get_latest_silk_version() -> Result<String, String> {
-    version::get_latest_silk_version().await
+async fn get_latest_silk_version(channel: Option<settings::ReleaseChannel>) -> Result<String, String> {
+    let channel = settings::resolve_release_channel(channel)?;
+    version::get_latest_silk_version(channel).await
+}
+
+#[tauri::command]
+async fn check_for_silk_updates(
+    game_path: Option<String>,
+    channel: Option<settings::ReleaseChannel>,
+) -> Result<Option<version::SilkRelease>, String> {
+    let game_path = settings::resolve_game_path(game_path)?;
+    let channel = settings::resolve_release_channel(channel)?;
+    version::check_for_updates(&game_path, channel).await
+}
+
+#[tauri::command]
+async fn list_available_silk_versions(channel: Option<settings::ReleaseChannel>) -> Result<Vec<String>, String> {
+    let channel = settings::resolve_release_channel(channel)?;
+    version::list_available_versions(channel).await
 }
 
 #[tauri::command]
-async fn check_for_silk_updates(game_path: String) -> Result<Option<version::SilkVersion>, String> {
-    version::check_for_updates(&game_path).await
+async fn list_silk_releases(channel: Option<settings::ReleaseChannel>) -> Result<Vec<version::SilkRelease>, String> {
+    let channel = settings::resolve_release_channel(channel)?;
+    version::list_silk_releases(channel).await
 }
 
 #[tauri::command]
-async fn list_available_silk_versions() -> Result<Vec<String>, String> {
-    version::list_available_versions().await
+async fn get_silk_release(version: String) -> Result<version::SilkRelease, String> {
+    version::get_silk_release(&version).await
 }
 
 #[tauri::command]
 async fn install_silk_version(
     version: String,
-    game_path: String,
+    game_path: Option<String>,
+    operation_id: Option<String>,
     window: tauri::Window,
 ) -> Result<(), String> {
-    version::download_silk_version(&version, &game_path, window).await
+    let game_path = settings::resolve_game_path(game_path)?;
+    let operation = operations::Operation::start(operation_id);
+
+    // Switching versions can break mods, so keep a way back to the current state
+    if PathBuf::from(&game_path).join("Silk").exists() {
+        let retention = settings::load_settings()?.backup_retention;
+        backup::create_backup_before(&game_path, &format!("Installing Silk {}", version), &retention)?;
+    }
+
+    version::download_silk_version(&version, &game_path, window, &operation).await
 }
 
 #[tauri::command]
 async fn check_mod_compatibility(
-    game_path: String,
-    mods_path: String,
+    game_path: Option<String>,
+    mods_path: Option<String>,
     mod_id: String,
 ) -> Result<bool, String> {
+    let game_path = settings::resolve_game_path(game_path)?;
+    let mods_path = settings::resolve_mods_path(mods_path)?;
     let installed_silk_version = version::get_installed_silk_version(&game_path)?;
-    let mod_version_info = version::get_mod_version_info(&mod_id, &mods_path)?;
+    let mod_version_info = match version::get_mod_version_info(&mod_id, &mods_path) {
+        Ok(info) => info,
+        Err(e) => side_loaded_version_info(&mod_id, &mods_path).await.ok_or(e)?,
+    };
     version::check_mod_compatibility(&installed_silk_version, &mod_version_info)
 }
 
+/// Version info for a mod without Entwine metadata, from its `[SilkMod]` attribute. A mod
+/// needs at least the Silk version it was built against.
+async fn side_loaded_version_info(mod_id: &str, mods_path: &str) -> Option<version::ModVersionInfo> {
+    let installed = get_installed_mods(Some(mods_path.to_string()))
+        .await
+        .ok()?
+        .into_iter()
+        .find(|m| m.id == mod_id)?;
+    let silk_version = installed.assembly?.silk_mod?.silk_version;
+
+    Some(version::ModVersionInfo {
+        mod_id: mod_id.to_string(),
+        version: installed.version,
+        silk_version: silk_version.clone().unwrap_or_else(|| "Unknown".to_string()),
+        min_silk_version: silk_version.filter(|v| semver::Version::parse(v).is_ok()),
+        max_silk_version: None,
+    })
+}
+
 // BepInEx Commands
 
 #[tauri::command]
-async fn is_bepinex_installed(game_path: String) -> Result<bool, String> {
+async fn is_bepinex_installed(game_path: Option<String>) -> Result<bool, String> {
+    let game_path = settings::resolve_game_path(game_path)?;
     Ok(bepinex::is_bepinex_installed(&game_path))
 }
 
 #[tauri::command]
-async fn get_bepinex_version(game_path: String) -> Result<String, String> {
+async fn get_bepinex_version(game_path: Option<String>) -> Result<String, String> {
+    let game_path = settings::resolve_game_path(game_path)?;
     bepinex::get_bepinex_version(&game_path)
 }
 
 #[tauri::command]
-async fn install_bepinex(game_path: String, window: tauri::Window) -> Result<(), String> {
-    bepinex::install_bepinex(&game_path, window).await
+async fn get_doorstop_info(game_path: Option<String>) -> Result<Option<doorstop::DoorstopInfo>, String> {
+    let game_path = settings::resolve_game_path(game_path)?;
+    Ok(doorstop::detect(Path::new(&game_path)))
+}
+
+#[tauri::command]
+async fn list_bepinex_releases(channel: Option<settings::ReleaseChannel>) -> Result<Vec<bepinex::BepInExRelease>, String> {
+    bepinex::list_bepinex_releases(settings::resolve_release_channel(channel)?).await
+}
+
+/// Install a BepInEx version, or switch the installed one to it (the default version when not given)
+#[tauri::command]
+async fn install_bepinex(
+    game_path: Option<String>,
+    version: Option<String>,
+    operation_id: Option<String>,
+    window: tauri::Window,
+) -> Result<(), String> {
+    let game_path = settings::resolve_game_path(game_path)?;
+    let operation = operations::Operation::start(operation_id);
+    let version = version.unwrap_or_else(|| bepinex::DEFAULT_BEPINEX_VERSION.to_string());
+    bepinex::install_bepinex(&game_path, &version, window, &operation).await
 }
 
 #[tauri::command]
-async fn uninstall_bepinex(game_path: String, window: tauri::Window) -> Result<(), String> {
+async fn uninstall_bepinex(
+    game_path: Option<String>,
+    window: tauri::Window,
+) -> Result<manifest::UninstallReport, String> {
+    let game_path = settings::resolve_game_path(game_path)?;
     bepinex::uninstall_bepinex(&game_path, window).await
 }
 
@@ -654,7 +1232,8 @@ async fn save_settings(settings: settings::AppSettings) -> Result<(), String> {
 }
 
 #[tauri::command]
-async fn launch_game(game_path: String) -> Result<(), String> {
+async fn launch_game(game_path: Option<String>) -> Result<(), String> {
+    let game_path = settings::resolve_game_path(game_path)?;
     let settings = settings::load_settings()?;
     settings::launch_game(&game_path, &settings.launch_method)
 }
@@ -662,28 +1241,166 @@ async fn launch_game(game_path: String) -> Result<(), String> {
 // Config Commands
 
 #[tauri::command]
-async fn list_mod_configs(game_path: String) -> Result<Vec<config::ModConfigFile>, String> {
+async fn list_mod_configs(game_path: Option<String>) -> Result<Vec<config::ModConfigFile>, String> {
+    let game_path = settings::resolve_game_path(game_path)?;
     config::list_mod_configs(&game_path)
 }
 
 #[tauri::command]
-async fn get_mod_config(game_path: String, mod_id: String) -> Result<std::collections::HashMap<String, serde_json::Value>, String> {
+async fn get_mod_config(game_path: Option<String>, mod_id: String) -> Result<std::collections::HashMap<String, serde_json::Value>, String> {
+    let game_path = settings::resolve_game_path(game_path)?;
     config::load_mod_config(&game_path, &mod_id)
 }
 
 #[tauri::command]
 async fn set_mod_config_value(
-    game_path: String,
+    game_path: Option<String>,
     mod_id: String,
     key: String,
     value: serde_json::Value,
 ) -> Result<(), String> {
+    let game_path = settings::resolve_game_path(game_path)?;
     config::set_mod_config_value(&game_path, &mod_id, &key, value)
 }
 
 #[tauri::command]
-async fn reset_mod_config(game_path: String, mod_id: String) -> Result<(), String> {
-    config::reset_mod_config(&game_path, &mod_id)
+async fn reset_mod_config(game_path: Option<String>, mod_id: String, key: Option<String>) -> Result<(), String> {
+    let game_path = settings::resolve_game_path(game_path)?;
+    config::reset_mod_config(&game_path, &mod_id, key.as_deref())
+}
+
+#[tauri::command]
+async fn get_mod_config_schema(game_path: Option<String>, mod_id: String) -> Result<Option<config::ConfigSchema>, String> {
+    let game_path = settings::resolve_game_path(game_path)?;
+    config::load_config_schema(&game_path, &mod_id)
+}
+
+#[tauri::command]
+async fn list_bepinex_configs(game_path: Option<String>) -> Result<Vec<bepinex_config::BepInExConfigFile>, String> {
+    let game_path = settings::resolve_game_path(game_path)?;
+    bepinex_config::list_bepinex_configs(&game_path)
+}
+
+#[tauri::command]
+async fn get_bepinex_config(game_path: Option<String>, file_name: String) -> Result<bepinex_config::BepInExConfigFile, String> {
+    let game_path = settings::resolve_game_path(game_path)?;
+    bepinex_config::load_bepinex_config(&game_path, &file_name)
+}
+
+#[tauri::command]
+async fn set_bepinex_config_value(
+    game_path: Option<String>,
+    file_name: String,
+    section: String,
+    key: String,
+    value: serde_json::Value,
+) -> Result<(), String> {
+    let game_path = settings::resolve_game_path(game_path)?;
+    bepinex_config::set_bepinex_config_value(&game_path, &file_name, &section, &key, value)
+}
+
+#[tauri::command]
+async fn reset_bepinex_config(
+    game_path: Option<String>,
+    file_name: String,
+    section: Option<String>,
+    key: Option<String>,
+) -> Result<(), String> {
+    let game_path = settings::resolve_game_path(game_path)?;
+    bepinex_config::reset_bepinex_config(&game_path, &file_name, section.as_deref(), key.as_deref())
+}
+
+// Profile Commands
+
+#[tauri::command]
+async fn list_profiles(game_path: Option<String>) -> Result<profiles::ProfileStore, String> {
+    let game_path = settings::resolve_game_path(game_path)?;
+    profiles::load_profiles(&game_path)
+}
+
+#[tauri::command]
+async fn save_profile(game_path: Option<String>, name: String) -> Result<profiles::Profile, String> {
+    let game_path = settings::resolve_game_path(game_path)?;
+    profiles::save_profile(&game_path, &name)
+}
+
+#[tauri::command]
+async fn delete_profile(game_path: Option<String>, name: String) -> Result<(), String> {
+    let game_path = settings::resolve_game_path(game_path)?;
+    profiles::delete_profile(&game_path, &name)
+}
+
+#[tauri::command]
+async fn apply_profile(
+    game_path: Option<String>,
+    name: String,
+) -> Result<profiles::ProfileSwitchResult, String> {
+    let game_path = settings::resolve_game_path(game_path)?;
+    info!("Switching to profile: {}", name);
+    let retention = settings::load_settings()?.backup_retention;
+    backup::create_backup_before(&game_path, &format!("Switching to profile {}", name), &retention)?;
+    profiles::apply_profile(&game_path, &name)
+}
+
+// Backup Commands
+
+#[tauri::command]
+async fn list_backups(game_path: Option<String>) -> Result<Vec<backup::BackupInfo>, String> {
+    let game_path = settings::resolve_game_path(game_path)?;
+    backup::list_backups(&game_path)
+}
+
+#[tauri::command]
+async fn create_backup(game_path: Option<String>, reason: Option<String>) -> Result<backup::BackupInfo, String> {
+    let game_path = settings::resolve_game_path(game_path)?;
+    let reason = reason.filter(|r| !r.trim().is_empty()).unwrap_or_else(|| "Manual backup".to_string());
+    backup::create_backup(&game_path, &reason)
+}
+
+#[tauri::command]
+async fn restore_backup(game_path: Option<String>, backup_id: String) -> Result<backup::BackupInfo, String> {
+    let game_path = settings::resolve_game_path(game_path)?;
+    let retention = settings::load_settings()?.backup_retention;
+    info!("Restoring backup: {}", backup_id);
+    backup::restore_backup(&game_path, &backup_id, &retention)
+}
+
+#[tauri::command]
+async fn prune_backups(game_path: Option<String>) -> Result<Vec<String>, String> {
+    let game_path = settings::resolve_game_path(game_path)?;
+    let retention = settings::load_settings()?.backup_retention;
+    backup::prune_backups(&game_path, &retention)
+}
+
+#[tauri::command]
+async fn delete_backup(game_path: Option<String>, backup_id: String) -> Result<(), String> {
+    let game_path = settings::resolve_game_path(game_path)?;
+    backup::delete_backup(&game_path, &backup_id)
+}
+
+// Modpack Commands
+
+#[tauri::command]
+async fn export_modpack(
+    game_path: Option<String>,
+    output_path: String,
+    name: String,
+    include_configs: bool,
+) -> Result<(), String> {
+    let game_path = settings::resolve_game_path(game_path)?;
+    modpack::export_modpack(&game_path, &output_path, &name, include_configs).await
+}
+
+#[tauri::command]
+async fn import_modpack(
+    game_path: Option<String>,
+    path: String,
+    operation_id: Option<String>,
+    window: tauri::Window,
+) -> Result<modpack::ImportReport, String> {
+    let game_path = settings::resolve_game_path(game_path)?;
+    let operation = operations::Operation::start(operation_id);
+    modpack::import_modpack(&game_path, &path, window, &operation).await
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -702,23 +1419,40 @@ pub fn run() {
             get_app_status,
             get_app_version,
             set_game_path,
+            // Game installs
+            list_game_installs,
+            add_game_install,
+            remove_game_install,
+            set_active_game_install,
             fetch_mods,
+            get_mod_icon,
+            list_registry_sources,
             install_silk,
             uninstall_silk,
             get_installed_mods,
             install_mod,
+            install_mod_from_path,
+            install_mod_from_url,
+            resolve_mod_install,
             toggle_mod,
             uninstall_mod,
+            check_mod_updates,
+            update_mod,
+            update_all_mods,
             // Version management
             get_silk_version,
             get_latest_silk_version,
             check_for_silk_updates,
             list_available_silk_versions,
+            list_silk_releases,
+            get_silk_release,
             install_silk_version,
             check_mod_compatibility,
             // BepInEx
             is_bepinex_installed,
             get_bepinex_version,
+            get_doorstop_info,
+            list_bepinex_releases,
             install_bepinex,
             uninstall_bepinex,
             // Settings
@@ -730,6 +1464,30 @@ pub fn run() {
             get_mod_config,
             set_mod_config_value,
             reset_mod_config,
+            get_mod_config_schema,
+            list_bepinex_configs,
+            get_bepinex_config,
+            set_bepinex_config_value,
+            reset_bepinex_config,
+            // Profiles
+            list_profiles,
+            save_profile,
+            delete_profile,
+            apply_profile,
+            // Backups
+            list_backups,
+            create_backup,
+            restore_backup,
+            prune_backups,
+            delete_backup,
+            // Modpacks
+            export_modpack,
+            import_modpack,
+            // Integrity
+            verify_installation,
+            repair,
+            // Operations
+            cancel_operation,
             // Logs
             get_log_path,
             read_log_file,
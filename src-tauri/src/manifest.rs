@@ -0,0 +1,396 @@
+use crate::download::to_hex;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+/// Folder, relative to the install root, that holds one manifest per installed component
+pub const MANIFESTS_DIR: &str = ".entwine_manifests";
+
+pub const SILK_COMPONENT: &str = "silk";
+pub const BEPINEX_COMPONENT: &str = "bepinex";
+
+/// A file written by an install, relative to the install root
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestFile {
+    /// Relative path with `/` separators
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+    /// Also written by another component's install, so only its presence is checked
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub shared: bool,
+}
+
+/// Every file an install of a component (Silk, BepInEx or a mod) put on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallManifest {
+    pub component: String,
+    pub version: String,
+    /// Seconds since the Unix epoch
+    pub installed_at: u64,
+    pub files: Vec<ManifestFile>,
+}
+
+/// What an uninstall did with each file in the manifest
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct UninstallReport {
+    pub removed: Vec<String>,
+    /// Files changed since they were installed; these are left in place
+    pub modified: Vec<String>,
+    /// Files that were already gone
+    pub missing: Vec<String>,
+    /// Files another installed component also owns; these are left in place
+    pub shared: Vec<String>,
+}
+
+/// Relative path of a component's manifest under the install root
+pub fn manifest_path(component: &str) -> PathBuf {
+    Path::new(MANIFESTS_DIR).join(format!("{}.json", component))
+}
+
+fn to_manifest_path(relative: &Path) -> String {
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Size and hex SHA-256 of a file on disk
+pub fn hash_file(path: &Path) -> Result<(u64, String), String> {
+    let mut file = fs::File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    let size = io::copy(&mut file, &mut hasher).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok((size, to_hex(&hasher.finalize())))
+}
+
+impl InstallManifest {
+    /// Build a manifest from staged files. `entries` are paths relative to `staging_dir`;
+    /// directories are walked and every file under them is recorded.
+    pub fn from_staged(
+        component: &str,
+        version: &str,
+        staging_dir: &Path,
+        entries: &[PathBuf],
+    ) -> Result<InstallManifest, String> {
+        let mut files = Vec::new();
+
+        for entry in entries {
+            for item in WalkDir::new(staging_dir.join(entry)).sort_by_file_name() {
+                let item = item.map_err(|e| format!("Failed to read staged files: {}", e))?;
+                if item.file_type().is_dir() {
+                    continue;
+                }
+
+                let relative = item
+                    .path()
+                    .strip_prefix(staging_dir)
+                    .map_err(|e| format!("Invalid staged path {}: {}", item.path().display(), e))?;
+                let (size, sha256) = hash_file(item.path())?;
+                files.push(ManifestFile {
+                    path: to_manifest_path(relative),
+                    size,
+                    sha256,
+                    shared: false,
+                });
+            }
+        }
+
+        Ok(InstallManifest {
+            component: component.to_string(),
+            version: version.to_string(),
+            installed_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            files,
+        })
+    }
+
+    /// Mark the files at `paths` as also written by another component
+    pub fn mark_shared(&mut self, paths: &[&str]) {
+        for file in self.files.iter_mut().filter(|f| paths.contains(&f.path.as_str())) {
+            file.shared = true;
+        }
+    }
+}
+
+/// Whether the file on disk still matches what was installed
+pub fn is_unchanged(file: &ManifestFile, path: &Path) -> bool {
+    match hash_file(path) {
+        Ok((size, sha256)) => size == file.size && sha256 == file.sha256,
+        Err(_) => false,
+    }
+}
+
+/// Load the manifest of a component, if one was recorded
+pub fn load(root: &Path, component: &str) -> Option<InstallManifest> {
+    let content = fs::read_to_string(root.join(manifest_path(component))).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(manifest) => Some(manifest),
+        Err(e) => {
+            warn!("Ignoring unreadable manifest for {}: {}", component, e);
+            None
+        }
+    }
+}
+
+/// Write a component's manifest under the install root
+pub fn save(root: &Path, manifest: &InstallManifest) -> Result<(), String> {
+    let path = root.join(manifest_path(&manifest.component));
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create manifest directory: {}", e))?;
+    }
+
+    let json = serde_json::to_string_pretty(manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write manifest for {}: {}", manifest.component, e))
+}
+
+/// Paths owned by every other component installed under the same root
+fn paths_owned_by_others(root: &Path, component: &str) -> HashSet<String> {
+    let Ok(entries) = fs::read_dir(root.join(MANIFESTS_DIR)) else {
+        return HashSet::new();
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let other = name.strip_suffix(".json")?.to_string();
+            (other != component).then_some(other)
+        })
+        .filter_map(|other| load(root, &other))
+        .flat_map(|manifest| manifest.files.into_iter().map(|f| f.path))
+        .collect()
+}
+
+/// Files from `old` that `new` no longer installs and the user hasn't changed
+pub fn stale_files(root: &Path, old: &InstallManifest, new: &InstallManifest) -> Vec<PathBuf> {
+    let kept: HashSet<&str> = new.files.iter().map(|f| f.path.as_str()).collect();
+    let shared = paths_owned_by_others(root, &old.component);
+
+    old.files
+        .iter()
+        .filter(|f| !kept.contains(f.path.as_str()) && !shared.contains(&f.path))
+        .filter(|f| is_unchanged(f, &root.join(&f.path)))
+        .map(|f| PathBuf::from(&f.path))
+        .collect()
+}
+
+/// Where a manifest path is now, given the current name of its first path component
+pub fn relocate(path: &str, renamed_top: Option<(&str, &str)>) -> String {
+    match renamed_top {
+        Some((from, to)) if path == from || path.starts_with(&format!("{}/", from)) => {
+            format!("{}{}", to, &path[from.len()..])
+        }
+        _ => path.to_string(),
+    }
+}
+
+/// Remove the now-empty parent directories of `path`, stopping at `root`
+fn remove_empty_parents(root: &Path, path: &Path) {
+    let mut current = path.parent();
+    while let Some(dir) = current {
+        if dir == root || !dir.starts_with(root) || fs::remove_dir(dir).is_err() {
+            break;
+        }
+        current = dir.parent();
+    }
+}
+
+/// Remove exactly the files a component installed. Files the user changed and files
+/// another component also installed are left in place and reported.
+///
+/// `renamed_top` maps the first path component recorded in the manifest to its current
+/// name, for mods that were disabled after they were installed.
+pub fn uninstall(
+    root: &Path,
+    manifest: &InstallManifest,
+    renamed_top: Option<(&str, &str)>,
+) -> Result<UninstallReport, String> {
+    let shared = paths_owned_by_others(root, &manifest.component);
+    let mut report = UninstallReport::default();
+
+    for file in &manifest.files {
+        let relative = relocate(&file.path, renamed_top);
+        let path = root.join(&relative);
+
+        if shared.contains(&file.path) {
+            report.shared.push(relative);
+        } else if !path.exists() {
+            report.missing.push(relative);
+        } else if !is_unchanged(file, &path) {
+            report.modified.push(relative);
+        } else {
+            fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {}", relative, e))?;
+            remove_empty_parents(root, &path);
+            report.removed.push(relative);
+        }
+    }
+
+    let manifest_file = root.join(manifest_path(&manifest.component));
+    if let Err(e) = fs::remove_file(&manifest_file) {
+        warn!("Failed to remove manifest for {}: {}", manifest.component, e);
+    }
+
+    if !report.modified.is_empty() {
+        warn!(
+            "Kept {} modified files while uninstalling {}: {:?}",
+            report.modified.len(),
+            manifest.component,
+            report.modified
+        );
+    }
+    info!(
+        "Uninstalled {}: {} files removed, {} kept",
+        manifest.component,
+        report.removed.len(),
+        report.modified.len() + report.shared.len()
+    );
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("entwine-manifest-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    /// Write `files` under `root` and record them as `component`'s install
+    fn install(root: &Path, component: &str, files: &[(&str, &str)]) -> InstallManifest {
+        for (path, content) in files {
+            write(&root.join(path), content);
+        }
+        let entries: Vec<PathBuf> = files.iter().map(|(path, _)| PathBuf::from(path)).collect();
+        let manifest = InstallManifest::from_staged(component, "1.0.0", root, &entries).unwrap();
+        save(root, &manifest).unwrap();
+        manifest
+    }
+
+    #[test]
+    fn uninstall_removes_files_and_empty_folders() {
+        let root = temp_root("uninstall");
+        let manifest = install(&root, "Mod", &[("Mod/Mod.dll", "mod"), ("Mod/lib/Dep.dll", "dep")]);
+        write(&root.join("Other.dll"), "other");
+
+        let report = uninstall(&root, &manifest, None).unwrap();
+        assert_eq!(report.removed, vec!["Mod/Mod.dll", "Mod/lib/Dep.dll"]);
+        assert!(report.modified.is_empty() && report.missing.is_empty() && report.shared.is_empty());
+        assert!(!root.join("Mod").exists());
+        assert!(!root.join(manifest_path("Mod")).exists());
+        assert!(root.join("Other.dll").is_file());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn uninstall_keeps_modified_files_and_their_folders() {
+        let root = temp_root("modified");
+        let manifest = install(&root, "Mod", &[("Mod/Mod.dll", "mod"), ("Mod/settings.json", "{}")]);
+        write(&root.join("Mod/settings.json"), "{\"edited\": true}");
+
+        let report = uninstall(&root, &manifest, None).unwrap();
+        assert_eq!(report.removed, vec!["Mod/Mod.dll"]);
+        assert_eq!(report.modified, vec!["Mod/settings.json"]);
+        assert_eq!(fs::read_to_string(root.join("Mod/settings.json")).unwrap(), "{\"edited\": true}");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn uninstall_reports_missing_files() {
+        let root = temp_root("missing");
+        let manifest = install(&root, "Mod", &[("Mod/Mod.dll", "mod"), ("Mod/Gone.dll", "gone")]);
+        fs::remove_file(root.join("Mod/Gone.dll")).unwrap();
+
+        let report = uninstall(&root, &manifest, None).unwrap();
+        assert_eq!(report.removed, vec!["Mod/Mod.dll"]);
+        assert_eq!(report.missing, vec!["Mod/Gone.dll"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn uninstall_keeps_files_another_component_owns() {
+        let root = temp_root("shared");
+        let bepinex = install(&root, BEPINEX_COMPONENT, &[("winhttp.dll", "proxy"), ("BepInEx/core/BepInEx.dll", "core")]);
+        install(&root, SILK_COMPONENT, &[("winhttp.dll", "proxy"), ("Silk/Silk.dll", "silk")]);
+
+        let report = uninstall(&root, &bepinex, None).unwrap();
+        assert_eq!(report.removed, vec!["BepInEx/core/BepInEx.dll"]);
+        assert_eq!(report.shared, vec!["winhttp.dll"]);
+        assert!(root.join("winhttp.dll").is_file());
+        assert!(load(&root, SILK_COMPONENT).is_some());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn uninstall_follows_a_disabled_folder() {
+        let root = temp_root("disabled");
+        let manifest = install(&root, "Mod", &[("Mod/Mod.dll", "mod"), ("Mod/lib/Dep.dll", "dep")]);
+        fs::rename(root.join("Mod"), root.join("Mod.disabled")).unwrap();
+
+        let report = uninstall(&root, &manifest, Some(("Mod", "Mod.disabled"))).unwrap();
+        assert_eq!(report.removed, vec!["Mod.disabled/Mod.dll", "Mod.disabled/lib/Dep.dll"]);
+        assert!(!root.join("Mod.disabled").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn relocates_only_the_renamed_top_component() {
+        let renamed = Some(("Mod", "Mod.disabled"));
+        assert_eq!(relocate("Mod/Mod.dll", renamed), "Mod.disabled/Mod.dll");
+        assert_eq!(relocate("Mod", renamed), "Mod.disabled");
+        assert_eq!(relocate("Modded/Mod.dll", renamed), "Modded/Mod.dll");
+        assert_eq!(relocate("Other/Mod/Mod.dll", renamed), "Other/Mod/Mod.dll");
+        assert_eq!(relocate("Mod/Mod.dll", None), "Mod/Mod.dll");
+    }
+
+    #[test]
+    fn stale_files_are_unchanged_unowned_and_dropped() {
+        let root = temp_root("stale");
+        let old = install(
+            &root,
+            BEPINEX_COMPONENT,
+            &[
+                ("BepInEx/core/Kept.dll", "kept"),
+                ("BepInEx/core/Dropped.dll", "dropped"),
+                ("BepInEx/core/Edited.dll", "edited"),
+                ("BepInEx/core/Gone.dll", "gone"),
+                ("winhttp.dll", "proxy"),
+            ],
+        );
+        install(&root, SILK_COMPONENT, &[("winhttp.dll", "proxy")]);
+        write(&root.join("BepInEx/core/Edited.dll"), "changed by the user");
+        fs::remove_file(root.join("BepInEx/core/Gone.dll")).unwrap();
+
+        let mut new = old.clone();
+        new.files.retain(|f| f.path == "BepInEx/core/Kept.dll");
+
+        assert_eq!(stale_files(&root, &old, &new), vec![PathBuf::from("BepInEx/core/Dropped.dll")]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,336 @@
+use crate::config;
+use crate::extract;
+use crate::operations::{Operation, CANCELLED_ERROR};
+use crate::InstalledMod;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::Emitter;
+
+const MODPACK_FORMAT_VERSION: u32 = 1;
+const MANIFEST_NAME: &str = "modpack.json";
+const CONFIG_DIR_NAME: &str = "config";
+/// A modpack only holds a manifest and config text, so it is read into memory under a
+/// much tighter size limit than mod archives
+const MODPACK_LIMITS: extract::Limits = extract::Limits {
+    max_total_size: 64 * 1024 * 1024,
+    max_entries: extract::MAX_ENTRIES,
+};
+
+/// A single mod in a shared modpack
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModpackEntry {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub file_name: String,
+    pub enabled: bool,
+    #[serde(default)]
+    pub source_url: Option<String>,
+}
+
+/// The portable manifest written by `export_modpack`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Modpack {
+    pub format_version: u32,
+    pub name: String,
+    pub exported_with: String,
+    pub mods: Vec<ModpackEntry>,
+    /// Raw YAML of `Silk/Config/Mods/<id>.yaml`, keyed by config id
+    #[serde(default)]
+    pub configs: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnmatchedEntry {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub reason: String,
+}
+
+/// What `import_modpack` did with each entry
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub installed: Vec<String>,
+    pub already_installed: Vec<String>,
+    /// Mods installed from the registry at a different version than the pack asked for
+    pub version_changed: Vec<String>,
+    pub unmatched: Vec<UnmatchedEntry>,
+    pub configs_imported: Vec<String>,
+}
+
+/// Read a zip entry as text, failing once more than `remaining` bytes come out of it
+fn read_entry(entry: impl Read, remaining: &mut u64) -> Result<String, String> {
+    let mut bytes = Vec::new();
+    let read = entry
+        .take(*remaining + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| e.to_string())? as u64;
+    if read > *remaining {
+        return Err(format!(
+            "modpack expands to more than the limit of {} bytes",
+            MODPACK_LIMITS.max_total_size
+        ));
+    }
+    *remaining -= read;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+fn is_zip_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .map(|ext| ext.eq_ignore_ascii_case("zip"))
+        .unwrap_or(false)
+}
+
+/// Export the installed mods (and optionally their configs) to a `.json` or `.zip` modpack
+pub async fn export_modpack(
+    game_path: &str,
+    output_path: &str,
+    name: &str,
+    include_configs: bool,
+) -> Result<(), String> {
+    let mods_path = PathBuf::from(game_path).join("Silk/Mods");
+    let installed: Vec<InstalledMod> =
+        crate::get_installed_mods(Some(mods_path.to_string_lossy().to_string())).await?;
+
+    let mods = installed
+        .into_iter()
+        .map(|m| ModpackEntry {
+            file_name: crate::toggled_file_name(&m.file_name, true),
+            id: m.id,
+            name: m.name,
+            version: m.version,
+            enabled: m.enabled,
+            source_url: m.source_url,
+        })
+        .collect();
+
+    let configs = if include_configs {
+        config::read_config_files(game_path)?
+    } else {
+        HashMap::new()
+    };
+
+    let mut pack = Modpack {
+        format_version: MODPACK_FORMAT_VERSION,
+        name: name.to_string(),
+        exported_with: format!("Entwine {}", env!("CARGO_PKG_VERSION")),
+        mods,
+        configs,
+    };
+
+    let output = PathBuf::from(output_path);
+
+    if is_zip_path(&output) {
+        // Configs travel as real files next to the manifest
+        let configs = std::mem::take(&mut pack.configs);
+        let file = fs::File::create(&output)
+            .map_err(|e| format!("Failed to create modpack file: {}", e))?;
+        let mut zip = zip::ZipWriter::new(file);
+        let options = zip::write::SimpleFileOptions::default();
+
+        let manifest = serde_json::to_string_pretty(&pack)
+            .map_err(|e| format!("Failed to serialize modpack: {}", e))?;
+        zip.start_file(MANIFEST_NAME, options)
+            .map_err(|e| format!("Failed to write modpack: {}", e))?;
+        zip.write_all(manifest.as_bytes())
+            .map_err(|e| format!("Failed to write modpack: {}", e))?;
+
+        for (config_id, content) in &configs {
+            zip.start_file(format!("{}/{}.yaml", CONFIG_DIR_NAME, config_id), options)
+                .map_err(|e| format!("Failed to write modpack: {}", e))?;
+            zip.write_all(content.as_bytes())
+                .map_err(|e| format!("Failed to write modpack: {}", e))?;
+        }
+
+        zip.finish()
+            .map_err(|e| format!("Failed to finish modpack archive: {}", e))?;
+    } else {
+        let json = serde_json::to_string_pretty(&pack)
+            .map_err(|e| format!("Failed to serialize modpack: {}", e))?;
+        fs::write(&output, json).map_err(|e| format!("Failed to write modpack file: {}", e))?;
+    }
+
+    info!("Exported modpack '{}' with {} mods to {}", pack.name, pack.mods.len(), output_path);
+    Ok(())
+}
+
+/// Read a modpack from a `.json` manifest or a `.zip` archive
+pub fn read_modpack(path: &str) -> Result<Modpack, String> {
+    let path = PathBuf::from(path);
+
+    if !is_zip_path(&path) {
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read modpack file: {}", e))?;
+        return serde_json::from_str(&content).map_err(|e| format!("Invalid modpack file: {}", e));
+    }
+
+    let file = fs::File::open(&path).map_err(|e| format!("Failed to open modpack: {}", e))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| format!("Failed to read modpack archive: {}", e))?;
+
+    if archive.len() > MODPACK_LIMITS.max_entries {
+        return Err(format!(
+            "Modpack archive has {} entries, more than the limit of {}",
+            archive.len(),
+            MODPACK_LIMITS.max_entries
+        ));
+    }
+
+    // Entry sizes in the archive can lie, so the limit is applied to the bytes read
+    let mut remaining = MODPACK_LIMITS.max_total_size;
+    let mut pack: Modpack = {
+        let manifest = archive
+            .by_name(MANIFEST_NAME)
+            .map_err(|_| format!("Modpack archive is missing {}", MANIFEST_NAME))?;
+        let content = read_entry(manifest, &mut remaining)
+            .map_err(|e| format!("Failed to read modpack manifest: {}", e))?;
+        serde_json::from_str(&content).map_err(|e| format!("Invalid modpack manifest: {}", e))?
+    };
+
+    for i in 0..archive.len() {
+        let entry = archive
+            .by_index(i)
+            .map_err(|e| format!("Failed to read modpack entry: {}", e))?;
+
+        let Some(config_id) = entry
+            .name()
+            .strip_prefix(&format!("{}/", CONFIG_DIR_NAME))
+            .and_then(|name| name.strip_suffix(".yaml"))
+            .map(|id| id.to_string())
+        else {
+            continue;
+        };
+
+        // Config ids become file names, so anything that isn't a plain name is refused
+        if let Err(e) = extract::safe_file_name(&config_id) {
+            warn!("Skipping unexpected config entry in modpack: {}", e);
+            continue;
+        }
+
+        let content = read_entry(entry, &mut remaining)
+            .map_err(|e| format!("Failed to read config {}: {}", config_id, e))?;
+        pack.configs.insert(config_id, content);
+    }
+
+    Ok(pack)
+}
+
+/// Install everything a modpack lists, matching entries against the mod registry
+pub async fn import_modpack(
+    game_path: &str,
+    path: &str,
+    window: tauri::Window,
+    operation: &Operation,
+) -> Result<ImportReport, String> {
+    let pack = read_modpack(path)?;
+
+    if pack.format_version > MODPACK_FORMAT_VERSION {
+        return Err(format!(
+            "This modpack was made with a newer version of Entwine (format {})",
+            pack.format_version
+        ));
+    }
+
+    info!("Importing modpack '{}' with {} mods", pack.name, pack.mods.len());
+    let mods_path = PathBuf::from(game_path).join("Silk/Mods").to_string_lossy().to_string();
+    let mut report = ImportReport::default();
+
+    let installed = crate::get_installed_mods(Some(mods_path.clone())).await?;
+    let registry = match crate::fetch_mods().await {
+        Ok(registry) => Some(registry.mods),
+        Err(e) => {
+            warn!("Mod registry unavailable during import: {}", e);
+            None
+        }
+    };
+
+    for entry in &pack.mods {
+        operation.check()?;
+
+        if installed.iter().any(|m| m.id == entry.id && m.version == entry.version) {
+            report.already_installed.push(entry.name.clone());
+            continue;
+        }
+
+        let Some(registry) = &registry else {
+            report.unmatched.push(UnmatchedEntry {
+                id: entry.id.clone(),
+                name: entry.name.clone(),
+                version: entry.version.clone(),
+                reason: "The mod registry could not be reached".to_string(),
+            });
+            continue;
+        };
+
+        let Some(mod_info) = registry.iter().find(|m| m.id == entry.id) else {
+            report.unmatched.push(UnmatchedEntry {
+                id: entry.id.clone(),
+                name: entry.name.clone(),
+                version: entry.version.clone(),
+                reason: "Not found in the mod registry".to_string(),
+            });
+            continue;
+        };
+
+        let _ = window.emit("install-progress", format!("Installing {} from modpack...", mod_info.name));
+        if let Err(e) = crate::install_mod_with_dependencies(mod_info, &mods_path, &window, operation).await {
+            if e == CANCELLED_ERROR {
+                return Err(e);
+            }
+            report.unmatched.push(UnmatchedEntry {
+                id: entry.id.clone(),
+                name: entry.name.clone(),
+                version: entry.version.clone(),
+                reason: format!("Install failed: {}", e),
+            });
+            continue;
+        }
+
+        if mod_info.version != entry.version {
+            report.version_changed.push(format!(
+                "{} (pack {}, installed {})",
+                entry.name, entry.version, mod_info.version
+            ));
+        }
+        report.installed.push(entry.name.clone());
+    }
+
+    // Match the enabled flags from the pack now that everything is on disk
+    let installed = crate::get_installed_mods(Some(mods_path.clone())).await?;
+    for entry in &pack.mods {
+        if let Some(m) = installed.iter().find(|m| m.id == entry.id) {
+            if m.enabled != entry.enabled {
+                crate::toggle_mod(Some(mods_path.clone()), m.file_name.clone(), entry.enabled).await?;
+            }
+        }
+    }
+
+    if !pack.configs.is_empty() {
+        let config_dir = config::get_mods_config_path(game_path);
+        fs::create_dir_all(&config_dir)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+
+        for (config_id, content) in &pack.configs {
+            if let Err(e) = extract::safe_file_name(config_id) {
+                warn!("Skipping unsafe config id in modpack: {}", e);
+                continue;
+            }
+            fs::write(config_dir.join(format!("{}.yaml", config_id)), content)
+                .map_err(|e| format!("Failed to write config {}: {}", config_id, e))?;
+            report.configs_imported.push(config_id.clone());
+        }
+    }
+
+    let _ = window.emit("install-progress", format!("Modpack '{}' imported", pack.name));
+    Ok(report)
+}
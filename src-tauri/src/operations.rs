@@ -0,0 +1,94 @@
+use log::info;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::Notify;
+
+pub const CANCELLED_ERROR: &str = "Operation cancelled";
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+fn registry() -> &'static Mutex<HashMap<String, Arc<CancelState>>> {
+    static OPERATIONS: OnceLock<Mutex<HashMap<String, Arc<CancelState>>>> = OnceLock::new();
+    OPERATIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// A running install or download that can be cancelled by id, unregistered when dropped
+pub struct Operation {
+    pub id: String,
+    state: Arc<CancelState>,
+}
+
+impl Operation {
+    /// Register a new operation, using the id chosen by the frontend if there is one
+    pub fn start(id: Option<String>) -> Operation {
+        let id = id
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| format!("op-{}", NEXT_ID.fetch_add(1, Ordering::Relaxed)));
+        let state = Arc::new(CancelState::default());
+
+        registry()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id.clone(), state.clone());
+
+        Operation { id, state }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Return `Err(CANCELLED_ERROR)` if the operation was cancelled
+    pub fn check(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            Err(CANCELLED_ERROR.to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Resolve once the operation is cancelled
+    pub async fn cancelled(&self) {
+        loop {
+            // Register for the notification before checking the flag so a cancel can't slip between them
+            let notified = self.state.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+impl Drop for Operation {
+    fn drop(&mut self) {
+        let mut operations = registry().lock().unwrap_or_else(|e| e.into_inner());
+        if operations
+            .get(&self.id)
+            .is_some_and(|state| Arc::ptr_eq(state, &self.state))
+        {
+            operations.remove(&self.id);
+        }
+    }
+}
+
+/// Request cancellation of a running operation; returns false if no such operation is running
+pub fn cancel(id: &str) -> bool {
+    let operations = registry().lock().unwrap_or_else(|e| e.into_inner());
+    match operations.get(id) {
+        Some(state) => {
+            info!("Cancelling operation {}", id);
+            state.cancelled.store(true, Ordering::SeqCst);
+            state.notify.notify_waiters();
+            true
+        }
+        None => false,
+    }
+}
@@ -0,0 +1,472 @@
+use crate::config;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const PROFILES_FILE: &str = ".entwine_profiles.json";
+
+/// A mod entry recorded in a profile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileMod {
+    pub id: String,
+    pub name: String,
+    /// File or folder name in its enabled form (without `.disabled`)
+    pub file_name: String,
+    pub version: String,
+    pub enabled: bool,
+}
+
+/// A named set of mods, their enabled state and their configs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    pub name: String,
+    pub mods: Vec<ProfileMod>,
+    /// Raw YAML of `Silk/Config/Mods/<id>.yaml`, keyed by config id
+    #[serde(default)]
+    pub configs: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ProfileStore {
+    pub active_profile: Option<String>,
+    pub profiles: Vec<Profile>,
+}
+
+/// Outcome of switching to a profile
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileSwitchResult {
+    pub enabled: Vec<String>,
+    pub disabled: Vec<String>,
+    /// Mods the profile enables that are not present in the Mods folder
+    pub missing: Vec<String>,
+    /// Mods whose installed version differs from the one recorded in the profile
+    pub version_mismatches: Vec<String>,
+    pub configs_restored: Vec<String>,
+}
+
+/// A mod file or folder currently in the Mods folder
+struct ModEntry {
+    current_name: String,
+    enabled_name: String,
+    enabled: bool,
+}
+
+fn get_mods_path(game_path: &str) -> PathBuf {
+    PathBuf::from(game_path).join("Silk").join("Mods")
+}
+
+/// Load the profile store for a game install
+pub fn load_profiles(game_path: &str) -> Result<ProfileStore, String> {
+    let profiles_path = get_mods_path(game_path).join(PROFILES_FILE);
+
+    if !profiles_path.exists() {
+        return Ok(ProfileStore::default());
+    }
+
+    let content = fs::read_to_string(&profiles_path)
+        .map_err(|e| format!("Failed to read profiles file: {}", e))?;
+
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse profiles file: {}", e))
+}
+
+fn save_profiles(game_path: &str, store: &ProfileStore) -> Result<(), String> {
+    let mods_dir = get_mods_path(game_path);
+    fs::create_dir_all(&mods_dir)
+        .map_err(|e| format!("Failed to create mods directory: {}", e))?;
+
+    let json = serde_json::to_string_pretty(store)
+        .map_err(|e| format!("Failed to serialize profiles: {}", e))?;
+
+    fs::write(mods_dir.join(PROFILES_FILE), json)
+        .map_err(|e| format!("Failed to write profiles file: {}", e))
+}
+
+/// List the mod files and folders in the Mods folder, skipping hidden entries
+fn scan_mod_entries(mods_dir: &Path) -> Result<Vec<ModEntry>, String> {
+    if !mods_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut entries = Vec::new();
+
+    for entry in fs::read_dir(mods_dir).map_err(|e| format!("Failed to read mods directory: {}", e))? {
+        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
+        let path = entry.path();
+        let file_name = entry.file_name().to_string_lossy().to_string();
+
+        if file_name.starts_with('.') {
+            continue;
+        }
+
+        let lower = file_name.to_lowercase();
+        let is_mod = path.is_dir() || lower.ends_with(".dll") || lower.ends_with(".dll.disabled");
+        if !is_mod {
+            continue;
+        }
+
+        entries.push(ModEntry {
+            enabled_name: crate::toggled_file_name(&file_name, true),
+            enabled: !file_name.ends_with(".disabled"),
+            current_name: file_name,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Capture the current Mods folder and configs as a profile (replacing one with the same name)
+pub fn save_profile(game_path: &str, name: &str) -> Result<Profile, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Profile name cannot be empty".to_string());
+    }
+
+    let mods_dir = get_mods_path(game_path);
+    let metadata = crate::load_mod_metadata(&mods_dir);
+
+    let mods = scan_mod_entries(&mods_dir)?
+        .into_iter()
+        .map(|entry| {
+            let base_name = entry.enabled_name.trim_end_matches(".dll");
+            let meta = metadata.get(base_name);
+            ProfileMod {
+                id: meta.map(|m| m.id.clone()).unwrap_or_else(|| base_name.to_string()),
+                name: meta.map(|m| m.name.clone()).unwrap_or_else(|| base_name.to_string()),
+                version: meta.map(|m| m.version.clone()).unwrap_or_else(|| "Unknown".to_string()),
+                file_name: entry.enabled_name,
+                enabled: entry.enabled,
+            }
+        })
+        .collect();
+
+    let configs = config::read_config_files(game_path)?;
+
+    let profile = Profile {
+        name: name.to_string(),
+        mods,
+        configs,
+    };
+
+    let mut store = load_profiles(game_path)?;
+    store.profiles.retain(|p| p.name != profile.name);
+    store.profiles.push(profile.clone());
+    store.active_profile = Some(profile.name.clone());
+    save_profiles(game_path, &store)?;
+
+    info!("Saved profile '{}' with {} mods", profile.name, profile.mods.len());
+    Ok(profile)
+}
+
+/// Delete a profile by name
+pub fn delete_profile(game_path: &str, name: &str) -> Result<(), String> {
+    let mut store = load_profiles(game_path)?;
+    let before = store.profiles.len();
+    store.profiles.retain(|p| p.name != name);
+
+    if store.profiles.len() == before {
+        return Err(format!("Profile not found: {}", name));
+    }
+
+    if store.active_profile.as_deref() == Some(name) {
+        store.active_profile = None;
+    }
+
+    save_profiles(game_path, &store)
+}
+
+/// A change made while applying a profile, kept so it can be undone
+enum AppliedChange {
+    Renamed { from: PathBuf, to: PathBuf },
+    ConfigWritten { path: PathBuf, previous: Option<String> },
+}
+
+fn rollback(changes: Vec<AppliedChange>) {
+    for change in changes.into_iter().rev() {
+        let result = match &change {
+            AppliedChange::Renamed { from, to } => fs::rename(to, from),
+            AppliedChange::ConfigWritten { path, previous: Some(content) } => fs::write(path, content),
+            AppliedChange::ConfigWritten { path, previous: None } => fs::remove_file(path),
+        };
+
+        if let Err(e) = result {
+            let path = match &change {
+                AppliedChange::Renamed { to, .. } => to,
+                AppliedChange::ConfigWritten { path, .. } => path,
+            };
+            error!("Failed to roll back change to {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Switch the Mods folder and configs to match a profile, rolling back on any failure
+pub fn apply_profile(game_path: &str, name: &str) -> Result<ProfileSwitchResult, String> {
+    let mut store = load_profiles(game_path)?;
+    let profile = store
+        .profiles
+        .iter()
+        .find(|p| p.name == name)
+        .cloned()
+        .ok_or_else(|| format!("Profile not found: {}", name))?;
+
+    let mods_dir = get_mods_path(game_path);
+    let entries = scan_mod_entries(&mods_dir)?;
+    let metadata = crate::load_mod_metadata(&mods_dir);
+    let mut result = ProfileSwitchResult::default();
+
+    let wanted: HashMap<&str, &ProfileMod> = profile
+        .mods
+        .iter()
+        .map(|m| (m.file_name.as_str(), m))
+        .collect();
+
+    // Plan every rename up front so conflicts are caught before anything moves
+    let mut renames = Vec::new();
+    for entry in &entries {
+        let target = wanted.get(entry.enabled_name.as_str());
+        let enable = target.map(|m| m.enabled).unwrap_or(false);
+
+        if let Some(profile_mod) = target {
+            let base_name = entry.enabled_name.trim_end_matches(".dll");
+            if let Some(meta) = metadata.get(base_name) {
+                if meta.version != profile_mod.version {
+                    result.version_mismatches.push(format!(
+                        "{} (installed {}, profile {})",
+                        profile_mod.name, meta.version, profile_mod.version
+                    ));
+                }
+            }
+        }
+
+        if enable == entry.enabled {
+            continue;
+        }
+
+        let new_name = crate::toggled_file_name(&entry.current_name, enable);
+        let new_path = mods_dir.join(&new_name);
+        if new_path.exists() {
+            return Err(format!(
+                "Cannot switch profile: both '{}' and '{}' exist in the Mods folder",
+                entry.current_name, new_name
+            ));
+        }
+
+        renames.push((mods_dir.join(&entry.current_name), new_path, enable, entry.enabled_name.clone()));
+    }
+
+    for profile_mod in profile.mods.iter().filter(|m| m.enabled) {
+        if !entries.iter().any(|e| e.enabled_name == profile_mod.file_name) {
+            result.missing.push(profile_mod.name.clone());
+        }
+    }
+
+    let mut changes = Vec::new();
+
+    for (from, to, enable, enabled_name) in renames {
+        if let Err(e) = fs::rename(&from, &to) {
+            error!("Failed to rename {} while switching profile: {}", from.display(), e);
+            rollback(changes);
+            return Err(format!(
+                "Failed to switch to profile '{}': could not rename {}: {}. All changes were rolled back.",
+                name, enabled_name, e
+            ));
+        }
+
+        changes.push(AppliedChange::Renamed { from, to });
+        if enable {
+            result.enabled.push(enabled_name);
+        } else {
+            result.disabled.push(enabled_name);
+        }
+    }
+
+    let config_dir = config::get_mods_config_path(game_path);
+    if !profile.configs.is_empty() {
+        if let Err(e) = fs::create_dir_all(&config_dir) {
+            rollback(changes);
+            return Err(format!("Failed to create config directory: {}. All changes were rolled back.", e));
+        }
+    }
+
+    for (config_id, content) in &profile.configs {
+        let path = config_dir.join(format!("{}.yaml", config_id));
+        let previous = fs::read_to_string(&path).ok();
+
+        if previous.as_deref() == Some(content.as_str()) {
+            continue;
+        }
+
+        if let Err(e) = fs::write(&path, content) {
+            rollback(changes);
+            return Err(format!(
+                "Failed to switch to profile '{}': could not write config {}: {}. All changes were rolled back.",
+                name, config_id, e
+            ));
+        }
+
+        changes.push(AppliedChange::ConfigWritten { path, previous });
+        result.configs_restored.push(config_id.clone());
+    }
+
+    store.active_profile = Some(profile.name.clone());
+    if let Err(e) = save_profiles(game_path, &store) {
+        rollback(changes);
+        return Err(e);
+    }
+
+    if !result.missing.is_empty() {
+        warn!("Profile '{}' references missing mods: {:?}", name, result.missing);
+    }
+    info!(
+        "Switched to profile '{}': {} enabled, {} disabled",
+        name,
+        result.enabled.len(),
+        result.disabled.len()
+    );
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use walkdir::WalkDir;
+
+    fn temp_game(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("entwine-profiles-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("Silk/Mods")).unwrap();
+        fs::create_dir_all(dir.join("Silk/Config/Mods")).unwrap();
+        dir
+    }
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    /// Every file and directory under `root` with the contents of the files
+    fn snapshot(root: &Path) -> BTreeMap<PathBuf, Option<Vec<u8>>> {
+        WalkDir::new(root)
+            .min_depth(1)
+            .into_iter()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let content = entry.file_type().is_file().then(|| fs::read(entry.path()).unwrap());
+                (entry.path().strip_prefix(root).unwrap().to_path_buf(), content)
+            })
+            .collect()
+    }
+
+    fn profile_mod(file_name: &str, enabled: bool) -> ProfileMod {
+        ProfileMod {
+            id: file_name.to_string(),
+            name: file_name.to_string(),
+            file_name: file_name.to_string(),
+            version: "1.0.0".to_string(),
+            enabled,
+        }
+    }
+
+    fn store_profile(game_path: &str, mods: Vec<ProfileMod>, configs: &[(&str, &str)]) {
+        let profile = Profile {
+            name: "Test".to_string(),
+            mods,
+            configs: configs.iter().map(|(id, c)| (id.to_string(), c.to_string())).collect(),
+        };
+        let store = ProfileStore {
+            active_profile: None,
+            profiles: vec![profile],
+        };
+        save_profiles(game_path, &store).unwrap();
+    }
+
+    #[test]
+    fn switches_mods_and_configs() {
+        let game = temp_game("switch");
+        let game_path = game.to_string_lossy().to_string();
+        write(&game.join("Silk/Mods/Alpha.dll"), "alpha");
+        write(&game.join("Silk/Mods/Beta.dll.disabled"), "beta");
+        write(&game.join("Silk/Config/Mods/Alpha.yaml"), "speed: 1\n");
+        store_profile(
+            &game_path,
+            vec![profile_mod("Alpha.dll", false), profile_mod("Beta.dll", true)],
+            &[("Alpha", "speed: 2\n")],
+        );
+
+        let result = apply_profile(&game_path, "Test").unwrap();
+        assert_eq!(result.enabled, vec!["Beta.dll"]);
+        assert_eq!(result.disabled, vec!["Alpha.dll"]);
+        assert!(game.join("Silk/Mods/Alpha.dll.disabled").is_file());
+        assert!(game.join("Silk/Mods/Beta.dll").is_file());
+        assert_eq!(fs::read_to_string(game.join("Silk/Config/Mods/Alpha.yaml")).unwrap(), "speed: 2\n");
+        assert_eq!(load_profiles(&game_path).unwrap().active_profile.as_deref(), Some("Test"));
+
+        fs::remove_dir_all(&game).unwrap();
+    }
+
+    #[test]
+    fn refuses_to_switch_when_a_target_name_exists() {
+        let game = temp_game("conflict");
+        let game_path = game.to_string_lossy().to_string();
+        write(&game.join("Silk/Mods/Alpha.dll"), "alpha");
+        write(&game.join("Silk/Mods/Alpha.dll.disabled"), "old alpha");
+        store_profile(&game_path, vec![profile_mod("Alpha.dll", false)], &[]);
+        let before = snapshot(&game);
+
+        assert!(apply_profile(&game_path, "Test").is_err());
+        assert_eq!(snapshot(&game), before);
+        fs::remove_dir_all(&game).unwrap();
+    }
+
+    #[test]
+    fn rolls_back_when_a_rename_fails_partway() {
+        let game = temp_game("rename-fails");
+        let game_path = game.to_string_lossy().to_string();
+        write(&game.join("Silk/Mods/Alpha.dll"), "alpha");
+        // Both folders enable to `Gamma`, which doesn't exist yet, so the plan passes and
+        // whichever is renamed second fails because the first is already there
+        write(&game.join("Silk/Mods/Gamma.disabled/Gamma.dll"), "gamma");
+        write(&game.join("Silk/Mods/Gamma.disabled.disabled/Gamma.dll"), "old gamma");
+        write(&game.join("Silk/Config/Mods/Alpha.yaml"), "speed: 1\n");
+        store_profile(
+            &game_path,
+            vec![profile_mod("Alpha.dll", false), profile_mod("Gamma", true)],
+            &[("Alpha", "speed: 2\n")],
+        );
+        let before = snapshot(&game);
+
+        let error = apply_profile(&game_path, "Test").unwrap_err();
+        assert!(error.contains("rolled back"), "{}", error);
+        assert_eq!(snapshot(&game), before);
+        fs::remove_dir_all(&game).unwrap();
+    }
+
+    #[test]
+    fn rolls_back_renames_when_a_config_cannot_be_written() {
+        let game = temp_game("config-fails");
+        let game_path = game.to_string_lossy().to_string();
+        write(&game.join("Silk/Mods/Alpha.dll"), "alpha");
+        write(&game.join("Silk/Mods/Beta.dll.disabled"), "beta");
+        write(&game.join("Silk/Config/Mods/Alpha.yaml"), "speed: 1\n");
+        // A folder where the config file should go can't be written over
+        fs::create_dir_all(game.join("Silk/Config/Mods/Beta.yaml")).unwrap();
+        store_profile(
+            &game_path,
+            vec![profile_mod("Alpha.dll", false), profile_mod("Beta.dll", true)],
+            &[("Alpha", "speed: 2\n"), ("Beta", "enabled: true\n"), ("Delta", "new: true\n")],
+        );
+        let before = snapshot(&game);
+
+        let error = apply_profile(&game_path, "Test").unwrap_err();
+        assert!(error.contains("rolled back"), "{}", error);
+        assert_eq!(snapshot(&game), before);
+        fs::remove_dir_all(&game).unwrap();
+    }
+}
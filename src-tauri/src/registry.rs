@@ -0,0 +1,206 @@
+use crate::operations::Operation;
+use crate::settings::{self, RegistryKind, RegistrySource};
+use crate::{download, extract, Mod};
+use log::{error, info, warn};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::Url;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+pub const OFFICIAL_SOURCE_ID: &str = "official";
+const OFFICIAL_REGISTRY_URL: &str = "https://silk.abstractmelon.net";
+
+/// Index file of a local registry folder
+const LOCAL_INDEX_NAME: &str = "mods.json";
+
+/// The silk.abstractmelon.net registry, used when no sources are configured
+pub fn official_source() -> RegistrySource {
+    RegistrySource {
+        id: OFFICIAL_SOURCE_ID.to_string(),
+        name: "Silk Mods".to_string(),
+        kind: RegistryKind::Http,
+        url: OFFICIAL_REGISTRY_URL.to_string(),
+        priority: 0,
+        auth_token: None,
+        enabled: true,
+    }
+}
+
+/// The sources from the settings, or the official registry when none are configured
+pub fn list_sources() -> Vec<RegistrySource> {
+    let configured = settings::load_settings()
+        .map(|s| s.registry_sources)
+        .unwrap_or_default();
+    if configured.is_empty() {
+        vec![official_source()]
+    } else {
+        configured
+    }
+}
+
+/// The enabled sources, highest priority first
+pub fn configured_sources() -> Vec<RegistrySource> {
+    let mut sources: Vec<RegistrySource> = list_sources().into_iter().filter(|s| s.enabled).collect();
+    sources.sort_by_key(|s| std::cmp::Reverse(s.priority));
+    sources
+}
+
+/// The source a mod was listed by, falling back to the official registry for mods
+/// recorded before sources existed
+pub fn source_for(source_id: Option<&str>) -> RegistrySource {
+    let source_id = source_id.unwrap_or(OFFICIAL_SOURCE_ID);
+    list_sources()
+        .into_iter()
+        .find(|s| s.id == source_id)
+        .unwrap_or_else(official_source)
+}
+
+/// Whether `url` is on the same scheme, host and port as the source
+fn same_origin(source: &RegistrySource, url: &str) -> bool {
+    match (Url::parse(&source.url), Url::parse(url)) {
+        (Ok(source_url), Ok(url)) => source_url.origin() == url.origin(),
+        _ => false,
+    }
+}
+
+/// An HTTP client for requesting `url` from a source. The source's token is only sent
+/// to the source's own origin, never to files it links elsewhere.
+pub fn client_for(source: &RegistrySource, url: &str) -> Result<reqwest::Client, String> {
+    let mut headers = HeaderMap::new();
+    let token = source.auth_token.as_deref().map(str::trim).filter(|t| !t.is_empty());
+    if let Some(token) = token.filter(|_| same_origin(source, url)) {
+        let mut value = HeaderValue::from_str(&format!("Bearer {}", token))
+            .map_err(|_| format!("The token for {} contains invalid characters", source.name))?;
+        value.set_sensitive(true);
+        headers.insert(AUTHORIZATION, value);
+    }
+
+    reqwest::Client::builder()
+        .default_headers(headers)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client for {}: {}", source.name, e))
+}
+
+/// Where a path from a source's listing points: a URL for HTTP registries, a file
+/// inside the folder for local ones
+pub fn resolve(source: &RegistrySource, path: &str) -> Result<String, String> {
+    if is_url(path) {
+        return Ok(path.to_string());
+    }
+
+    match source.kind {
+        RegistryKind::Http => Ok(format!("{}{}", source.url.trim_end_matches('/'), path)),
+        RegistryKind::Local => {
+            // Installed mods record the already-resolved location
+            let resolved = Path::new(path);
+            if resolved.starts_with(&source.url) && !resolved.components().any(|c| c == Component::ParentDir) {
+                return Ok(path.to_string());
+            }
+            // Listings written for HTTP registries start paths with a slash
+            let relative = extract::sanitize_entry_name(path.trim_start_matches('/'))
+                .map_err(|e| format!("Invalid path in {}: {}", source.name, e))?;
+            Ok(PathBuf::from(&source.url).join(relative).to_string_lossy().to_string())
+        }
+    }
+}
+
+fn is_url(location: &str) -> bool {
+    location.starts_with("http://") || location.starts_with("https://")
+}
+
+async fn fetch_source(source: &RegistrySource) -> Result<Vec<Mod>, String> {
+    match source.kind {
+        RegistryKind::Http => {
+            let url = format!("{}/api/mods", source.url.trim_end_matches('/'));
+            info!("Fetching mod list from {}", url);
+
+            let response = client_for(source, &url)?
+                .get(&url)
+                .send()
+                .await
+                .map_err(|e| format!("Failed to fetch mods from {}: {}", source.name, e))?;
+            if !response.status().is_success() {
+                return Err(format!("Failed to fetch mods from {}: server responded with {}", source.name, response.status()));
+            }
+
+            response
+                .json()
+                .await
+                .map_err(|e| format!("Failed to parse mods from {}: {}", source.name, e))
+        }
+        RegistryKind::Local => {
+            let index = PathBuf::from(&source.url).join(LOCAL_INDEX_NAME);
+            info!("Reading mod list from {}", index.display());
+
+            let content = fs::read_to_string(&index)
+                .map_err(|e| format!("Failed to read {}: {}", index.display(), e))?;
+            serde_json::from_str(&content).map_err(|e| format!("Failed to parse mods from {}: {}", source.name, e))
+        }
+    }
+}
+
+/// Fetch every enabled source and merge the results. A mod listed by several sources is
+/// taken from the one with the highest priority. Fails only if no source could be read.
+pub async fn fetch_all() -> Result<Vec<Mod>, String> {
+    let sources = configured_sources();
+    if sources.is_empty() {
+        return Err("No mod registries are enabled".to_string());
+    }
+
+    let mut mods = Vec::new();
+    let mut seen = HashSet::new();
+    let mut errors = Vec::new();
+
+    for source in &sources {
+        match fetch_source(source).await {
+            Ok(listed) => {
+                info!("Fetched {} mods from {}", listed.len(), source.name);
+                for mut mod_info in listed {
+                    if seen.insert(mod_info.id.clone()) {
+                        mod_info.source = Some(source.id.clone());
+                        mods.push(mod_info);
+                    }
+                }
+            }
+            Err(e) => {
+                warn!("Skipping unavailable registry: {}", e);
+                errors.push(e);
+            }
+        }
+    }
+
+    if errors.len() == sources.len() {
+        error!("No mod registry could be reached");
+        return Err(errors.join("; "));
+    }
+    Ok(mods)
+}
+
+/// Get a file listed by a source: downloaded for HTTP registries, read in place for local ones
+pub async fn fetch_file(
+    source: &RegistrySource,
+    location: &str,
+    expected: &download::ExpectedDownload,
+    label: &str,
+    window: &tauri::Window,
+    operation: &Operation,
+) -> Result<download::DownloadedFile, String> {
+    if is_url(location) {
+        let client = client_for(source, location)?;
+        download::download_to_file(&client, location, expected, label, window, operation).await
+    } else {
+        download::local_file(&PathBuf::from(location), expected, label)
+    }
+}
+
+/// A mod icon from a source as a `data:` URL
+pub async fn icon(source: &RegistrySource, icon_path: &str) -> Result<String, String> {
+    let location = resolve(source, icon_path)?;
+    if is_url(&location) {
+        crate::cache::mod_icon(&client_for(source, &location)?, &location).await
+    } else {
+        let bytes = fs::read(&location).map_err(|e| format!("Failed to read icon: {}", e))?;
+        Ok(crate::cache::data_url(&location, None, &bytes))
+    }
+}
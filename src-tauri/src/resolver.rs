@@ -0,0 +1,374 @@
+use crate::version::parse_version_lenient;
+use crate::{InstalledMod, Mod};
+use semver::VersionReq;
+use serde::{Deserialize, Serialize};
+
+/// A dependency or conflict declared by a mod in the registry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModDependency {
+    pub id: String,
+    /// Semver range such as ">=1.2.0, <2.0.0"; empty or "*" matches any version
+    #[serde(default)]
+    pub version_range: String,
+}
+
+/// The ordered set of mods to install, dependencies before the mods that need them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallPlan {
+    pub to_install: Vec<Mod>,
+    /// Dependencies that are already installed at a suitable version
+    pub already_satisfied: Vec<String>,
+}
+
+fn parse_range(dependency: &ModDependency, required_by: &str) -> Result<VersionReq, String> {
+    let range = dependency.version_range.trim();
+    if range.is_empty() || range == "*" {
+        return Ok(VersionReq::STAR);
+    }
+
+    VersionReq::parse(range).map_err(|e| {
+        format!(
+            "'{}' declares an invalid version range '{}' for '{}': {}",
+            required_by, range, dependency.id, e
+        )
+    })
+}
+
+fn satisfies(version: &str, range: &VersionReq) -> bool {
+    if *range == VersionReq::STAR {
+        return true;
+    }
+    parse_version_lenient(version)
+        .map(|v| range.matches(&v))
+        .unwrap_or(false)
+}
+
+struct Resolver<'a> {
+    registry: &'a [Mod],
+    installed: &'a [InstalledMod],
+    plan: Vec<Mod>,
+    already_satisfied: Vec<String>,
+    stack: Vec<String>,
+}
+
+impl<'a> Resolver<'a> {
+    fn visit(&mut self, mod_info: &Mod) -> Result<(), String> {
+        self.stack.push(mod_info.id.clone());
+
+        for dependency in &mod_info.dependencies {
+            let range = parse_range(dependency, &mod_info.name)?;
+            self.visit_dependency(dependency, &range, mod_info)?;
+        }
+
+        self.stack.pop();
+        self.plan.push(mod_info.clone());
+        Ok(())
+    }
+
+    fn visit_dependency(
+        &mut self,
+        dependency: &ModDependency,
+        range: &VersionReq,
+        required_by: &Mod,
+    ) -> Result<(), String> {
+        if let Some(start) = self.stack.iter().position(|id| id == &dependency.id) {
+            let mut cycle = self.stack[start..].to_vec();
+            cycle.push(dependency.id.clone());
+            return Err(format!("Dependency cycle detected: {}", cycle.join(" -> ")));
+        }
+
+        if let Some(planned) = self.plan.iter().find(|m| m.id == dependency.id) {
+            if satisfies(&planned.version, range) {
+                return Ok(());
+            }
+            return Err(format!(
+                "Unsatisfiable version range: '{}' requires '{}' {}, but another mod needs version {}",
+                required_by.name, dependency.id, range, planned.version
+            ));
+        }
+
+        let installed = self.installed.iter().find(|m| m.id == dependency.id);
+        if let Some(installed) = installed {
+            if satisfies(&installed.version, range) {
+                if !self.already_satisfied.contains(&installed.name) {
+                    self.already_satisfied.push(installed.name.clone());
+                }
+                return Ok(());
+            }
+        }
+
+        let Some(available) = self.registry.iter().find(|m| m.id == dependency.id) else {
+            return Err(format!(
+                "Missing dependency: '{}' requires '{}', which is not installed and not in the mod registry",
+                required_by.name, dependency.id
+            ));
+        };
+
+        if !satisfies(&available.version, range) {
+            let installed_note = installed
+                .map(|m| format!(" (installed: {})", m.version))
+                .unwrap_or_default();
+            return Err(format!(
+                "Unsatisfiable version range: '{}' requires '{}' {}, but the registry only has {}{}",
+                required_by.name, dependency.id, range, available.version, installed_note
+            ));
+        }
+
+        self.visit(available)
+    }
+
+    /// Reject the plan if any mod in it conflicts with another planned or installed mod
+    fn check_conflicts(&self) -> Result<(), String> {
+        let planned_versions = self.plan.iter().map(|m| (m.id.as_str(), m.name.as_str(), m.version.as_str()));
+        let installed_versions = self
+            .installed
+            .iter()
+            .filter(|m| !self.plan.iter().any(|p| p.id == m.id))
+            .map(|m| (m.id.as_str(), m.name.as_str(), m.version.as_str()));
+        let present: Vec<(&str, &str, &str)> = planned_versions.chain(installed_versions).collect();
+
+        // Conflicts declared by the mods being installed
+        for mod_info in &self.plan {
+            for conflict in &mod_info.conflicts {
+                let range = parse_range(conflict, &mod_info.name)?;
+                if let Some((_, name, version)) = present
+                    .iter()
+                    .find(|(id, _, version)| *id == conflict.id && satisfies(version, &range))
+                {
+                    return Err(format!(
+                        "'{}' conflicts with '{}' {}",
+                        mod_info.name, name, version
+                    ));
+                }
+            }
+        }
+
+        // Conflicts declared by installed mods against what is being installed
+        for installed in self.installed {
+            let Some(entry) = self.registry.iter().find(|m| m.id == installed.id) else {
+                continue;
+            };
+            for conflict in &entry.conflicts {
+                let range = parse_range(conflict, &entry.name)?;
+                if let Some(planned) = self
+                    .plan
+                    .iter()
+                    .find(|m| m.id == conflict.id && satisfies(&m.version, &range))
+                {
+                    return Err(format!(
+                        "'{}' {} cannot be installed because the installed mod '{}' conflicts with it",
+                        planned.name, planned.version, installed.name
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Compute everything that must be installed for `root`, without downloading anything
+pub fn resolve_install(
+    root: &Mod,
+    registry: &[Mod],
+    installed: &[InstalledMod],
+) -> Result<InstallPlan, String> {
+    let mut resolver = Resolver {
+        registry,
+        installed,
+        plan: Vec::new(),
+        already_satisfied: Vec::new(),
+        stack: Vec::new(),
+    };
+
+    resolver.visit(root)?;
+    resolver.check_conflicts()?;
+
+    Ok(InstallPlan {
+        to_install: resolver.plan,
+        already_satisfied: resolver.already_satisfied,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dependency(id: &str, version_range: &str) -> ModDependency {
+        ModDependency {
+            id: id.to_string(),
+            version_range: version_range.to_string(),
+        }
+    }
+
+    fn registry_mod(id: &str, version: &str, dependencies: &[(&str, &str)]) -> Mod {
+        Mod {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: String::new(),
+            version: version.to_string(),
+            author: String::new(),
+            file_name: format!("{}.dll", id),
+            file_path: format!("/mods/{}.dll", id),
+            file_size: 0,
+            sha256: None,
+            icon_path: String::new(),
+            upload_date: String::new(),
+            downloads: 0,
+            last_downloaded: None,
+            dependencies: dependencies.iter().map(|(id, range)| dependency(id, range)).collect(),
+            conflicts: Vec::new(),
+            source: None,
+        }
+    }
+
+    fn conflicting(mut mod_info: Mod, conflicts: &[(&str, &str)]) -> Mod {
+        mod_info.conflicts = conflicts.iter().map(|(id, range)| dependency(id, range)).collect();
+        mod_info
+    }
+
+    fn installed_mod(id: &str, version: &str) -> InstalledMod {
+        InstalledMod {
+            id: id.to_string(),
+            name: id.to_string(),
+            file_name: format!("{}.dll", id),
+            enabled: true,
+            version: version.to_string(),
+            author: String::new(),
+            description: String::new(),
+            icon_path: String::new(),
+            source_url: None,
+            source: None,
+            origin: None,
+            assembly: None,
+        }
+    }
+
+    fn plan_ids(plan: &InstallPlan) -> Vec<&str> {
+        plan.to_install.iter().map(|m| m.id.as_str()).collect()
+    }
+
+    #[test]
+    fn orders_dependencies_first() {
+        let registry = vec![
+            registry_mod("app", "1.0.0", &[("ui", "^1"), ("core", ">=2.0.0")]),
+            registry_mod("ui", "1.4.0", &[("core", "*")]),
+            registry_mod("core", "2.1.0", &[]),
+        ];
+
+        let plan = resolve_install(&registry[0], &registry, &[]).unwrap();
+
+        assert_eq!(plan_ids(&plan), vec!["core", "ui", "app"]);
+        assert!(plan.already_satisfied.is_empty());
+    }
+
+    #[test]
+    fn skips_installed_dependencies_that_match() {
+        let registry = vec![
+            registry_mod("app", "1.0.0", &[("core", ">=2.0.0"), ("lib", "")]),
+            registry_mod("core", "2.1.0", &[]),
+            registry_mod("lib", "1.0.0", &[]),
+        ];
+        let installed = vec![installed_mod("core", "2.0.3"), installed_mod("lib", "Unknown")];
+
+        let plan = resolve_install(&registry[0], &registry, &installed).unwrap();
+
+        assert_eq!(plan_ids(&plan), vec!["app"]);
+        assert_eq!(plan.already_satisfied, vec!["core", "lib"]);
+    }
+
+    #[test]
+    fn upgrades_installed_dependencies_that_are_too_old() {
+        let registry = vec![registry_mod("app", "1.0.0", &[("core", ">=2.0.0")]), registry_mod("core", "2.1.0", &[])];
+        let installed = vec![installed_mod("core", "1.9.0")];
+
+        let plan = resolve_install(&registry[0], &registry, &installed).unwrap();
+
+        assert_eq!(plan_ids(&plan), vec!["core", "app"]);
+    }
+
+    #[test]
+    fn reports_missing_and_unsatisfiable_dependencies() {
+        let registry = vec![registry_mod("app", "1.0.0", &[("ghost", "")])];
+        let error = resolve_install(&registry[0], &registry, &[]).unwrap_err();
+        assert!(error.starts_with("Missing dependency: 'app' requires 'ghost'"), "{}", error);
+
+        let registry = vec![registry_mod("app", "1.0.0", &[("core", "^3")]), registry_mod("core", "2.1.0", &[])];
+        let installed = vec![installed_mod("core", "2.0.0")];
+        let error = resolve_install(&registry[0], &registry, &installed).unwrap_err();
+        assert!(error.contains("the registry only has 2.1.0 (installed: 2.0.0)"), "{}", error);
+    }
+
+    #[test]
+    fn reports_diamonds_that_need_two_versions() {
+        let registry = vec![
+            registry_mod("app", "1.0.0", &[("a", ""), ("b", "")]),
+            registry_mod("a", "1.0.0", &[("core", "^2")]),
+            registry_mod("b", "1.0.0", &[("core", "^1")]),
+            registry_mod("core", "2.0.0", &[]),
+        ];
+
+        let error = resolve_install(&registry[0], &registry, &[]).unwrap_err();
+        assert!(error.contains("'b' requires 'core' ^1, but another mod needs version 2.0.0"), "{}", error);
+    }
+
+    #[test]
+    fn detects_cycles() {
+        let registry = vec![
+            registry_mod("a", "1.0.0", &[("b", "")]),
+            registry_mod("b", "1.0.0", &[("c", "")]),
+            registry_mod("c", "1.0.0", &[("a", "")]),
+        ];
+
+        let error = resolve_install(&registry[0], &registry, &[]).unwrap_err();
+        assert_eq!(error, "Dependency cycle detected: a -> b -> c -> a");
+
+        let registry = vec![registry_mod("self", "1.0.0", &[("self", "")])];
+        assert!(resolve_install(&registry[0], &registry, &[]).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_ranges() {
+        let registry = vec![registry_mod("app", "1.0.0", &[("core", "not a range")]), registry_mod("core", "1.0.0", &[])];
+        let error = resolve_install(&registry[0], &registry, &[]).unwrap_err();
+        assert!(error.starts_with("'app' declares an invalid version range 'not a range' for 'core'"), "{}", error);
+    }
+
+    #[test]
+    fn reports_conflicts_declared_by_the_new_mod() {
+        let registry = vec![
+            conflicting(registry_mod("app", "1.0.0", &[]), &[("old", "<2.0.0")]),
+            registry_mod("old", "2.0.0", &[]),
+        ];
+
+        let error = resolve_install(&registry[0], &registry, &[installed_mod("old", "1.5.0")]).unwrap_err();
+        assert_eq!(error, "'app' conflicts with 'old' 1.5.0");
+        // Outside the conflicting range the install goes ahead
+        assert!(resolve_install(&registry[0], &registry, &[installed_mod("old", "2.0.0")]).is_ok());
+    }
+
+    #[test]
+    fn reports_conflicts_between_planned_mods() {
+        let registry = vec![
+            registry_mod("app", "1.0.0", &[("a", ""), ("b", "")]),
+            conflicting(registry_mod("a", "1.0.0", &[]), &[("b", "")]),
+            registry_mod("b", "1.0.0", &[]),
+        ];
+
+        let error = resolve_install(&registry[0], &registry, &[]).unwrap_err();
+        assert_eq!(error, "'a' conflicts with 'b' 1.0.0");
+    }
+
+    #[test]
+    fn reports_conflicts_declared_by_installed_mods() {
+        let registry = vec![
+            registry_mod("app", "1.0.0", &[]),
+            conflicting(registry_mod("guard", "1.0.0", &[]), &[("app", "")]),
+        ];
+
+        let error = resolve_install(&registry[0], &registry, &[installed_mod("guard", "1.0.0")]).unwrap_err();
+        assert_eq!(error, "'app' 1.0.0 cannot be installed because the installed mod 'guard' conflicts with it");
+    }
+}
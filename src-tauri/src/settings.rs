@@ -2,23 +2,180 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum LaunchMethod {
+    #[default]
     Steam,
     Executable,
 }
 
-impl Default for LaunchMethod {
+/// Which Silk releases are offered for install and update
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReleaseChannel {
+    /// Full releases only
+    #[default]
+    Stable,
+    /// Full releases and prereleases
+    Prerelease,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RegistryKind {
+    /// A registry server exposing `/api/mods`
+    #[default]
+    Http,
+    /// A folder with a `mods.json` index next to the mod files
+    Local,
+}
+
+/// A place mods are listed and downloaded from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistrySource {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub kind: RegistryKind,
+    /// Base URL of an HTTP registry, or the folder of a local one
+    pub url: String,
+    /// When several sources list the same mod, the one with the highest priority is used
+    #[serde(default)]
+    pub priority: i32,
+    /// Sent as a bearer token with every request to an HTTP registry
+    #[serde(default)]
+    pub auth_token: Option<String>,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// A SpiderHeck installation the user has registered
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GameInstall {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+}
+
+/// Which backups are kept when old ones are pruned
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BackupRetention {
+    /// Most backups kept per game install; 0 keeps every backup
+    pub max_backups: u32,
+    /// Backups older than this many days are removed; 0 keeps them regardless of age
+    pub max_age_days: u32,
+}
+
+impl Default for BackupRetention {
     fn default() -> Self {
-        LaunchMethod::Steam
+        BackupRetention {
+            max_backups: 10,
+            max_age_days: 0,
+        }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct AppSettings {
     pub launch_method: LaunchMethod,
+    pub game_installs: Vec<GameInstall>,
+    pub active_install: Option<String>,
+    pub backup_retention: BackupRetention,
+    pub silk_channel: ReleaseChannel,
+    /// Personal access token sent to the GitHub API to raise its rate limit
+    pub github_token: Option<String>,
+    /// Mod registries to list and install from; the official registry when empty
+    pub registry_sources: Vec<RegistrySource>,
+}
+
+impl AppSettings {
+    /// Get the install currently marked active
+    pub fn active_game_install(&self) -> Option<&GameInstall> {
+        let active_id = self.active_install.as_ref()?;
+        self.game_installs.iter().find(|install| &install.id == active_id)
+    }
+
+    /// Register a game install, reusing the existing entry if the path is already known
+    pub fn add_game_install(&mut self, name: &str, path: &str) -> GameInstall {
+        if let Some(existing) = self.game_installs.iter_mut().find(|i| same_path(&i.path, path)) {
+            existing.name = name.to_string();
+            return existing.clone();
+        }
+
+        let install = GameInstall {
+            id: self.unique_install_id(name),
+            name: name.to_string(),
+            path: path.to_string(),
+        };
+        self.game_installs.push(install.clone());
+        install
+    }
+
+    /// Remove a game install, clearing the active selection if it pointed at it
+    pub fn remove_game_install(&mut self, id: &str) -> Result<(), String> {
+        let before = self.game_installs.len();
+        self.game_installs.retain(|install| install.id != id);
+
+        if self.game_installs.len() == before {
+            return Err(format!("Game install not found: {}", id));
+        }
+
+        if self.active_install.as_deref() == Some(id) {
+            self.active_install = self.game_installs.first().map(|i| i.id.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Mark a game install as the active one
+    pub fn set_active_game_install(&mut self, id: &str) -> Result<GameInstall, String> {
+        let install = self
+            .game_installs
+            .iter()
+            .find(|install| install.id == id)
+            .cloned()
+            .ok_or_else(|| format!("Game install not found: {}", id))?;
+
+        self.active_install = Some(install.id.clone());
+        Ok(install)
+    }
+
+    fn unique_install_id(&self, name: &str) -> String {
+        let slug: String = name
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect::<String>()
+            .split('-')
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("-");
+        let base = if slug.is_empty() { "install".to_string() } else { slug };
+
+        let mut id = base.clone();
+        let mut suffix = 2;
+        while self.game_installs.iter().any(|install| install.id == id) {
+            id = format!("{}-{}", base, suffix);
+            suffix += 1;
+        }
+        id
+    }
+}
+
+fn same_path(a: &str, b: &str) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
 }
 
 /// Get the path to the settings file
@@ -63,6 +220,40 @@ pub fn save_settings(settings: &AppSettings) -> Result<(), String> {
     Ok(())
 }
 
+/// Resolve a game path argument, falling back to the active game install
+pub fn resolve_game_path(game_path: Option<String>) -> Result<String, String> {
+    if let Some(path) = game_path.filter(|p| !p.trim().is_empty()) {
+        return Ok(path);
+    }
+
+    let settings = load_settings()?;
+    settings
+        .active_game_install()
+        .map(|install| install.path.clone())
+        .ok_or_else(|| "No game installation selected".to_string())
+}
+
+/// Resolve a mods path argument, falling back to the active game install's Silk/Mods
+pub fn resolve_mods_path(mods_path: Option<String>) -> Result<String, String> {
+    if let Some(path) = mods_path.filter(|p| !p.trim().is_empty()) {
+        return Ok(path);
+    }
+
+    let game_path = resolve_game_path(None)?;
+    Ok(PathBuf::from(game_path)
+        .join("Silk/Mods")
+        .to_string_lossy()
+        .to_string())
+}
+
+/// Resolve a release channel argument, falling back to the channel in the settings
+pub fn resolve_release_channel(channel: Option<ReleaseChannel>) -> Result<ReleaseChannel, String> {
+    match channel {
+        Some(channel) => Ok(channel),
+        None => Ok(load_settings()?.silk_channel),
+    }
+}
+
 /// Launch the game
 pub fn launch_game(game_path: &str, launch_method: &LaunchMethod) -> Result<(), String> {
     match launch_method {
@@ -0,0 +1,149 @@
+use crate::vdf::{self, VdfValue};
+use log::warn;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const SPIDERHECK_APP_ID: &str = "1329500";
+
+/// Get every Steam installation root that exists on this machine
+fn get_steam_roots() -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+
+    #[cfg(target_os = "linux")]
+    {
+        if let Some(home) = dirs::home_dir() {
+            roots.push(home.join(".steam/steam"));
+            roots.push(home.join(".steam/root"));
+            roots.push(home.join(".local/share/Steam"));
+            // Flatpak Steam
+            roots.push(home.join(".var/app/com.valvesoftware.Steam/.steam/steam"));
+            roots.push(home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam"));
+            // Snap Steam
+            roots.push(home.join("snap/steam/common/.local/share/Steam"));
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        if let Some(registry_path) = read_steam_path_from_registry() {
+            roots.push(registry_path);
+        }
+        roots.push(PathBuf::from("C:\\Program Files (x86)\\Steam"));
+        roots.push(PathBuf::from("C:\\Program Files\\Steam"));
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        if let Some(home) = dirs::home_dir() {
+            roots.push(home.join("Library/Application Support/Steam"));
+        }
+    }
+
+    dedupe_existing(roots)
+}
+
+/// Read the Steam install path that the Steam client records in the registry
+#[cfg(target_os = "windows")]
+fn read_steam_path_from_registry() -> Option<PathBuf> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let output = std::process::Command::new("reg")
+        .args(["query", "HKCU\\Software\\Valve\\Steam", "/v", "SteamPath"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()?;
+
+    // Output line looks like: "    SteamPath    REG_SZ    c:/program files (x86)/steam"
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.split_once("REG_SZ"))
+        .map(|(_, value)| PathBuf::from(value.trim()))
+}
+
+/// Drop paths that don't exist and collapse symlinked duplicates (e.g. ~/.steam/steam)
+fn dedupe_existing(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut seen = Vec::new();
+    let mut result = Vec::new();
+
+    for path in paths {
+        if !path.is_dir() {
+            continue;
+        }
+        let canonical = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+        if !seen.contains(&canonical) {
+            seen.push(canonical);
+            result.push(path);
+        }
+    }
+
+    result
+}
+
+fn read_vdf(path: &Path) -> Option<VdfValue> {
+    let content = fs::read_to_string(path).ok()?;
+    match vdf::parse(&content) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!("Failed to parse {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Read the library paths listed in a Steam root's libraryfolders.vdf
+fn read_library_folders(steam_root: &Path) -> Vec<PathBuf> {
+    let vdf_path = steam_root.join("steamapps").join("libraryfolders.vdf");
+    let Some(document) = read_vdf(&vdf_path) else {
+        return Vec::new();
+    };
+
+    let Some(folders) = document.get("libraryfolders") else {
+        return Vec::new();
+    };
+
+    folders
+        .entries()
+        .filter(|(key, _)| key.chars().all(|c| c.is_ascii_digit()))
+        .filter_map(|(_, value)| match value {
+            // Current format: "0" { "path" "..." "apps" { ... } }
+            VdfValue::Object(_) => value.get("path").and_then(VdfValue::as_str),
+            // Legacy format: "1" "D:\\SteamLibrary"
+            VdfValue::String(path) => Some(path.as_str()),
+        })
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Get every Steam library folder (the directories containing `steamapps`)
+pub fn get_steam_library_paths() -> Vec<PathBuf> {
+    let mut libraries = Vec::new();
+
+    for root in get_steam_roots() {
+        libraries.push(root.clone());
+        libraries.extend(read_library_folders(&root));
+    }
+
+    dedupe_existing(libraries)
+}
+
+/// Read the `installdir` from a library's app manifest
+fn read_install_dir(library: &Path, app_id: &str) -> Option<String> {
+    let manifest_path = library
+        .join("steamapps")
+        .join(format!("appmanifest_{}.acf", app_id));
+
+    read_vdf(&manifest_path)?
+        .get_path(&["AppState", "installdir"])
+        .and_then(VdfValue::as_str)
+        .map(|s| s.to_string())
+}
+
+/// Resolve the install directory of a Steam app from its app manifest
+pub fn find_app_install_dir(app_id: &str) -> Option<PathBuf> {
+    get_steam_library_paths().into_iter().find_map(|library| {
+        let install_dir = read_install_dir(&library, app_id)?;
+        let path = library.join("steamapps").join("common").join(install_dir);
+        path.is_dir().then_some(path)
+    })
+}
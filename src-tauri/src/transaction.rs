@@ -0,0 +1,561 @@
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use walkdir::WalkDir;
+
+pub const TRANSACTION_DIR_PREFIX: &str = ".entwine_txn_";
+const JOURNAL_NAME: &str = "journal";
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+/// Held while a transaction starts so no thread mistakes another's new folder for a stale one
+static BEGIN_LOCK: Mutex<()> = Mutex::new(());
+
+/// A change made to the target directory, kept so it can be undone. Paths are relative
+/// to the root so the journal can be replayed after a crash.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum Change {
+    /// A file or directory that did not exist before
+    Created(PathBuf),
+    /// A directory created to hold new files
+    CreatedDir(PathBuf),
+    /// An existing file or directory moved or copied into the backup folder
+    Saved(PathBuf),
+}
+
+/// A set of changes to a directory that is either committed as a whole or rolled back.
+///
+/// New files are staged inside the target directory so moving them into place is a
+/// rename on the same filesystem. Anything they replace is kept in a backup folder
+/// until the transaction commits, and restored if it is dropped without committing.
+/// Each change is also written to a journal, locked while the transaction runs, so a
+/// transaction cut short by a crash is rolled back the next time one begins in the
+/// same directory.
+pub struct Transaction {
+    root: PathBuf,
+    work_dir: PathBuf,
+    journal: Option<File>,
+    changes: Vec<Change>,
+    committed: bool,
+}
+
+fn remove_any(path: &Path) -> std::io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(meta) if meta.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+fn ignore_missing(result: std::io::Result<()>) -> std::io::Result<()> {
+    match result {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// Undo `changes` in reverse order. Changes are journaled before they happen, so one
+/// that never took place (a missing backup, a path that wasn't created) is skipped.
+fn undo_changes(root: &Path, work_dir: &Path, changes: Vec<Change>) {
+    for change in changes.into_iter().rev() {
+        let result = match &change {
+            Change::Created(relative) => remove_any(&root.join(relative)),
+            Change::CreatedDir(relative) => ignore_missing(fs::remove_dir(root.join(relative))),
+            Change::Saved(relative) => {
+                let path = root.join(relative);
+                let backup = work_dir.join("backup").join(relative);
+                if fs::symlink_metadata(&backup).is_ok() {
+                    remove_any(&path).and_then(|_| fs::rename(&backup, &path))
+                } else {
+                    Ok(())
+                }
+            }
+        };
+
+        if let Err(e) = result {
+            let (Change::Created(relative) | Change::CreatedDir(relative) | Change::Saved(relative)) = &change;
+            error!("Failed to roll back {}: {}", root.join(relative).display(), e);
+        }
+    }
+}
+
+/// Roll back and remove the work folders of transactions that ended without committing
+/// or cleaning up, e.g. because the app was killed mid-install. Folders whose journal is
+/// still locked belong to a running transaction and are left alone.
+fn recover_stale(root: &Path) {
+    let Ok(entries) = fs::read_dir(root) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let work_dir = entry.path();
+        if !entry.file_name().to_string_lossy().starts_with(TRANSACTION_DIR_PREFIX) || !work_dir.is_dir() {
+            continue;
+        }
+
+        let journal_path = work_dir.join(JOURNAL_NAME);
+        let changes = match OpenOptions::new().read(true).write(true).open(&journal_path) {
+            Ok(mut journal) => {
+                if journal.try_lock().is_err() {
+                    continue;
+                }
+                // A line cut off by the crash is the change that was about to happen
+                let mut changes: Vec<Change> = Vec::new();
+                for line in BufReader::new(&mut journal).lines() {
+                    match line.ok().and_then(|line| serde_json::from_str(&line).ok()) {
+                        Some(change) => changes.push(change),
+                        None => break,
+                    }
+                }
+                changes
+            }
+            // Interrupted before its journal was created, so nothing was changed, unless the
+            // folder holds backups whose original paths aren't known and can't be thrown away
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let has_backups = fs::read_dir(work_dir.join("backup")).is_ok_and(|mut d| d.next().is_some());
+                if has_backups {
+                    warn!("Leaving {}: it holds backups but no journal", work_dir.display());
+                    continue;
+                }
+                Vec::new()
+            }
+            Err(e) => {
+                warn!("Failed to open {}: {}", journal_path.display(), e);
+                continue;
+            }
+        };
+
+        if !changes.is_empty() {
+            warn!("Rolling back {} changes left by an interrupted transaction in {}", changes.len(), root.display());
+            undo_changes(root, &work_dir, changes);
+        }
+        if let Err(e) = remove_any(&work_dir) {
+            warn!("Failed to clean up {}: {}", work_dir.display(), e);
+        }
+    }
+}
+
+impl Transaction {
+    pub fn begin(root: &Path) -> Result<Transaction, String> {
+        let _guard = BEGIN_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        recover_stale(root);
+
+        let work_dir = root.join(format!(
+            "{}{}_{}",
+            TRANSACTION_DIR_PREFIX,
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let journal = fs::create_dir_all(&work_dir)
+            .and_then(|_| File::create(work_dir.join(JOURNAL_NAME)))
+            .and_then(|journal| journal.lock().map(|_| journal))
+            .and_then(|journal| {
+                fs::create_dir_all(work_dir.join("staging"))?;
+                fs::create_dir_all(work_dir.join("backup"))?;
+                Ok(journal)
+            })
+            .map_err(|e| format!("Failed to create staging directory in {}: {}", root.display(), e))?;
+
+        Ok(Transaction {
+            root: root.to_path_buf(),
+            work_dir,
+            journal: Some(journal),
+            changes: Vec::new(),
+            committed: false,
+        })
+    }
+
+    /// Directory to build new files in before they are moved into place
+    pub fn staging_dir(&self) -> PathBuf {
+        self.work_dir.join("staging")
+    }
+
+    fn backup_path(&self, relative: &Path) -> PathBuf {
+        self.work_dir.join("backup").join(relative)
+    }
+
+    /// Write `change` to the journal and remember it for rollback
+    fn record(&mut self, change: Change) -> Result<(), String> {
+        let mut line = serde_json::to_string(&change).map_err(|e| format!("Failed to record change: {}", e))?;
+        line.push('\n');
+        if let Some(journal) = self.journal.as_mut() {
+            journal
+                .write_all(line.as_bytes())
+                .map_err(|e| format!("Failed to write transaction journal: {}", e))?;
+        }
+        self.changes.push(change);
+        Ok(())
+    }
+
+    /// Refuse to save a path twice, which would replace its original copy in the backup
+    fn check_not_saved(&self, relative: &Path) -> Result<(), String> {
+        if self.changes.iter().any(|c| matches!(c, Change::Saved(saved) if saved == relative)) {
+            return Err(format!("{} was already backed up in this transaction", relative.display()));
+        }
+        Ok(())
+    }
+
+    /// Create the parent directories of `path` under the root, recording the ones that are new
+    fn create_parents(&mut self, path: &Path) -> Result<(), String> {
+        let Some(parent) = path.parent() else {
+            return Ok(());
+        };
+
+        let mut missing = Vec::new();
+        let mut current = parent;
+        while current.starts_with(&self.root) && current != self.root && !current.exists() {
+            missing.push(current.to_path_buf());
+            match current.parent() {
+                Some(p) => current = p,
+                None => break,
+            }
+        }
+
+        for dir in missing.into_iter().rev() {
+            let relative = dir.strip_prefix(&self.root).unwrap_or(&dir).to_path_buf();
+            self.record(Change::CreatedDir(relative))?;
+            fs::create_dir(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+
+        Ok(())
+    }
+
+    /// Move whatever is at `relative` into the backup folder so it can be restored later
+    pub fn remove(&mut self, relative: &Path) -> Result<(), String> {
+        let path = self.root.join(relative);
+        if fs::symlink_metadata(&path).is_err() {
+            return Ok(());
+        }
+        self.check_not_saved(relative)?;
+
+        let backup = self.backup_path(relative);
+        if let Some(parent) = backup.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create backup directory: {}", e))?;
+        }
+        self.record(Change::Saved(relative.to_path_buf()))?;
+        fs::rename(&path, &backup)
+            .map_err(|e| format!("Failed to move {} aside: {}", path.display(), e))?;
+
+        Ok(())
+    }
+
+    /// Keep a copy of the file at `relative` before it is modified in place
+    pub fn back_up(&mut self, relative: &Path) -> Result<(), String> {
+        let path = self.root.join(relative);
+        if !path.is_file() {
+            self.create_parents(&path)?;
+            return self.record(Change::Created(relative.to_path_buf()));
+        }
+        self.check_not_saved(relative)?;
+
+        let backup = self.backup_path(relative);
+        if let Some(parent) = backup.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create backup directory: {}", e))?;
+        }
+        // Recorded only once the copy is complete, so a partial copy is never restored
+        fs::copy(&path, &backup).map_err(|e| format!("Failed to back up {}: {}", path.display(), e))?;
+
+        self.record(Change::Saved(relative.to_path_buf()))
+    }
+
+    /// Move a staged file or directory to `relative`, keeping any existing copy until commit
+    pub fn place(&mut self, staged: &Path, relative: &Path) -> Result<(), String> {
+        let path = self.root.join(relative);
+        let replacing = fs::symlink_metadata(&path).is_ok();
+
+        // A replaced path is restored from its backup, so only new paths need recording
+        if replacing {
+            self.remove(relative)?;
+        } else {
+            self.create_parents(&path)?;
+            self.record(Change::Created(relative.to_path_buf()))?;
+        }
+
+        fs::rename(staged, &path)
+            .map_err(|e| format!("Failed to move {} into place: {}", relative.display(), e))?;
+
+        Ok(())
+    }
+
+    /// Move every file under a staged directory into the same place under the root,
+    /// merging with what is already there
+    pub fn place_tree(&mut self, staged_root: &Path) -> Result<(), String> {
+        let files: Vec<PathBuf> = WalkDir::new(staged_root)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| !entry.file_type().is_dir())
+            .map(|entry| entry.into_path())
+            .collect();
+
+        for staged in files {
+            let relative = staged
+                .strip_prefix(staged_root)
+                .map_err(|e| format!("Invalid staged path {}: {}", staged.display(), e))?
+                .to_path_buf();
+
+            if self.root.join(&relative).is_dir() {
+                return Err(format!(
+                    "Cannot install {}: a directory with that name already exists",
+                    relative.display()
+                ));
+            }
+            self.place(&staged, &relative)?;
+        }
+
+        Ok(())
+    }
+
+    /// Keep the new files and discard the backups
+    pub fn commit(mut self) {
+        self.committed = true;
+        info!("Committed {} changes to {}", self.changes.len(), self.root.display());
+    }
+
+    /// Undo every change in reverse order
+    pub fn rollback(mut self) {
+        self.undo();
+    }
+
+    fn undo(&mut self) {
+        if self.changes.is_empty() {
+            return;
+        }
+        warn!("Rolling back {} changes to {}", self.changes.len(), self.root.display());
+        undo_changes(&self.root, &self.work_dir, self.changes.drain(..).collect());
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if !self.committed {
+            self.undo();
+        }
+
+        // Empty the journal while it is still locked so the changes are never undone twice
+        if let Some(journal) = self.journal.take() {
+            if let Err(e) = journal.set_len(0) {
+                error!("Failed to clear the journal in {}: {}", self.work_dir.display(), e);
+            }
+        }
+        if let Err(e) = remove_any(&self.work_dir) {
+            warn!("Failed to clean up {}: {}", self.work_dir.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("entwine-transaction-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    /// Every file and directory under `root` with the contents of the files
+    fn snapshot(root: &Path) -> BTreeMap<PathBuf, Option<Vec<u8>>> {
+        WalkDir::new(root)
+            .min_depth(1)
+            .into_iter()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let content = entry.file_type().is_file().then(|| fs::read(entry.path()).unwrap());
+                (entry.path().strip_prefix(root).unwrap().to_path_buf(), content)
+            })
+            .collect()
+    }
+
+    fn populate(root: &Path) {
+        write(&root.join("Mod.dll"), "old mod");
+        write(&root.join("Removed/Removed.dll"), "removed");
+        write(&root.join("metadata.json"), "{}");
+    }
+
+    #[test]
+    fn failures_after_each_step_leave_the_tree_unchanged() {
+        for steps in 0..=4 {
+            let root = temp_root(&format!("rollback-{}", steps));
+            populate(&root);
+            let before = snapshot(&root);
+
+            let mut transaction = Transaction::begin(&root).unwrap();
+            let staging_dir = transaction.staging_dir();
+            write(&staging_dir.join("Mod.dll"), "new mod");
+            write(&staging_dir.join("Extra.dll"), "extra");
+
+            let result = (|| {
+                if steps >= 1 {
+                    transaction.place(&staging_dir.join("Mod.dll"), Path::new("Mod.dll"))?;
+                    transaction.place(&staging_dir.join("Extra.dll"), Path::new("New/Nested/Extra.dll"))?;
+                }
+                if steps >= 2 {
+                    transaction.remove(Path::new("Removed"))?;
+                }
+                if steps >= 3 {
+                    transaction.back_up(Path::new("metadata.json"))?;
+                    fs::write(root.join("metadata.json"), "{\"changed\": true}").map_err(|e| e.to_string())?;
+                }
+                if steps >= 4 {
+                    transaction.back_up(Path::new("Added.json"))?;
+                    fs::write(root.join("Added.json"), "{}").map_err(|e| e.to_string())?;
+                }
+                Err::<(), String>("failed".to_string())
+            })();
+            assert!(result.is_err());
+            drop(transaction);
+
+            assert_eq!(snapshot(&root), before, "after {} steps", steps);
+            fs::remove_dir_all(&root).unwrap();
+        }
+    }
+
+    #[test]
+    fn commit_keeps_the_new_files() {
+        let root = temp_root("commit");
+        populate(&root);
+
+        let mut transaction = Transaction::begin(&root).unwrap();
+        let staging_dir = transaction.staging_dir();
+        write(&staging_dir.join("Mod.dll"), "new mod");
+        write(&staging_dir.join("Extra.dll"), "extra");
+        transaction.place(&staging_dir.join("Mod.dll"), Path::new("Mod.dll")).unwrap();
+        transaction.place(&staging_dir.join("Extra.dll"), Path::new("New/Extra.dll")).unwrap();
+        transaction.remove(Path::new("Removed")).unwrap();
+        transaction.back_up(Path::new("metadata.json")).unwrap();
+        fs::write(root.join("metadata.json"), "{\"changed\": true}").unwrap();
+        transaction.commit();
+
+        let mut expected = BTreeMap::new();
+        expected.insert(PathBuf::from("Mod.dll"), Some(b"new mod".to_vec()));
+        expected.insert(PathBuf::from("New"), None);
+        expected.insert(PathBuf::from("New/Extra.dll"), Some(b"extra".to_vec()));
+        expected.insert(PathBuf::from("metadata.json"), Some(b"{\"changed\": true}".to_vec()));
+        assert_eq!(snapshot(&root), expected);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn place_tree_merges_into_an_existing_directory() {
+        let root = temp_root("place-tree");
+        write(&root.join("BepInEx/core/BepInEx.dll"), "old core");
+        write(&root.join("BepInEx/config/BepInEx.cfg"), "user config");
+        let before = snapshot(&root);
+
+        let mut transaction = Transaction::begin(&root).unwrap();
+        let staged = transaction.staging_dir().join("tree");
+        write(&staged.join("BepInEx/core/BepInEx.dll"), "new core");
+        write(&staged.join("BepInEx/plugins/Plugin.dll"), "plugin");
+        transaction.place_tree(&staged).unwrap();
+
+        assert_eq!(fs::read_to_string(root.join("BepInEx/core/BepInEx.dll")).unwrap(), "new core");
+        assert_eq!(fs::read_to_string(root.join("BepInEx/plugins/Plugin.dll")).unwrap(), "plugin");
+        assert_eq!(fs::read_to_string(root.join("BepInEx/config/BepInEx.cfg")).unwrap(), "user config");
+
+        drop(transaction);
+        assert_eq!(snapshot(&root), before);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn place_tree_refuses_to_replace_a_directory_with_a_file() {
+        let root = temp_root("place-tree-dir");
+        write(&root.join("BepInEx/core/BepInEx.dll"), "old core");
+        let before = snapshot(&root);
+
+        let mut transaction = Transaction::begin(&root).unwrap();
+        let staged = transaction.staging_dir().join("tree");
+        write(&staged.join("BepInEx/core"), "not a directory");
+        assert!(transaction.place_tree(&staged).is_err());
+
+        drop(transaction);
+        assert_eq!(snapshot(&root), before);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn refuses_to_save_a_path_twice() {
+        let root = temp_root("save-twice");
+        populate(&root);
+        let before = snapshot(&root);
+
+        let mut transaction = Transaction::begin(&root).unwrap();
+        transaction.back_up(Path::new("metadata.json")).unwrap();
+        fs::write(root.join("metadata.json"), "{\"changed\": true}").unwrap();
+        assert!(transaction.back_up(Path::new("metadata.json")).is_err());
+        assert!(transaction.remove(Path::new("metadata.json")).is_err());
+
+        drop(transaction);
+        assert_eq!(snapshot(&root), before);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn rolls_back_a_transaction_interrupted_by_a_crash() {
+        let root = temp_root("crash");
+        populate(&root);
+        let before = snapshot(&root);
+
+        let mut transaction = Transaction::begin(&root).unwrap();
+        let staging_dir = transaction.staging_dir();
+        write(&staging_dir.join("Mod.dll"), "new mod");
+        transaction.place(&staging_dir.join("Mod.dll"), Path::new("Mod.dll")).unwrap();
+        transaction.remove(Path::new("Removed")).unwrap();
+        transaction.back_up(Path::new("Added.json")).unwrap();
+        fs::write(root.join("Added.json"), "{}").unwrap();
+        // Dying releases the journal's lock without running Drop
+        drop(transaction.journal.take());
+        std::mem::forget(transaction);
+
+        drop(Transaction::begin(&root).unwrap());
+        assert_eq!(snapshot(&root), before);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn skips_journaled_changes_that_never_happened() {
+        let root = temp_root("crash-partial");
+        populate(&root);
+        let before = snapshot(&root);
+
+        // The app died after journaling a removal but before moving the file, partway
+        // through writing the next line
+        let work_dir = root.join(format!("{}1_1", TRANSACTION_DIR_PREFIX));
+        fs::create_dir_all(work_dir.join("backup")).unwrap();
+        fs::write(work_dir.join(JOURNAL_NAME), "{\"saved\":\"Mod.dll\"}\n{\"created\":\"metad").unwrap();
+
+        drop(Transaction::begin(&root).unwrap());
+        assert_eq!(snapshot(&root), before);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn leaves_running_transactions_alone() {
+        let root = temp_root("concurrent");
+        populate(&root);
+
+        let mut first = Transaction::begin(&root).unwrap();
+        first.remove(Path::new("Removed")).unwrap();
+        let second = Transaction::begin(&root).unwrap();
+        assert!(first.work_dir.join("backup/Removed/Removed.dll").is_file());
+        drop(second);
+
+        first.commit();
+        assert!(!root.join("Removed").exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,308 @@
+/// A node in a Valve KeyValues (VDF) document
+#[derive(Debug, Clone, PartialEq)]
+pub enum VdfValue {
+    String(String),
+    Object(Vec<(String, VdfValue)>),
+}
+
+impl VdfValue {
+    /// Look up a child by key (keys are case-insensitive in KeyValues)
+    pub fn get(&self, key: &str) -> Option<&VdfValue> {
+        match self {
+            VdfValue::Object(entries) => entries
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v),
+            VdfValue::String(_) => None,
+        }
+    }
+
+    /// Follow a path of keys through nested objects
+    pub fn get_path(&self, path: &[&str]) -> Option<&VdfValue> {
+        path.iter().try_fold(self, |node, key| node.get(key))
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            VdfValue::String(s) => Some(s),
+            VdfValue::Object(_) => None,
+        }
+    }
+
+    /// Iterate over the entries of an object (empty for strings)
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &VdfValue)> {
+        let entries: &[(String, VdfValue)] = match self {
+            VdfValue::Object(entries) => entries,
+            VdfValue::String(_) => &[],
+        };
+        entries.iter().map(|(k, v)| (k.as_str(), v))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Str(String),
+    Open,
+    Close,
+}
+
+struct Lexer<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    line: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(input: &'a str) -> Self {
+        Lexer {
+            chars: input.char_indices().peekable(),
+            line: 1,
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, String> {
+        loop {
+            let Some(&(_, c)) = self.chars.peek() else {
+                return Ok(None);
+            };
+
+            match c {
+                '\n' => {
+                    self.line += 1;
+                    self.chars.next();
+                }
+                c if c.is_whitespace() || c == '\u{feff}' => {
+                    self.chars.next();
+                }
+                '/' => {
+                    self.chars.next();
+                    if !matches!(self.chars.peek(), Some((_, '/'))) {
+                        return Err(format!("Unexpected '/' on line {}", self.line));
+                    }
+                    // Line comment
+                    while let Some(&(_, c)) = self.chars.peek() {
+                        if c == '\n' {
+                            break;
+                        }
+                        self.chars.next();
+                    }
+                }
+                '[' => {
+                    // Platform conditionals like [$WIN32] are ignored
+                    self.chars.next();
+                    for (_, c) in self.chars.by_ref() {
+                        if c == ']' {
+                            break;
+                        }
+                    }
+                }
+                '{' => {
+                    self.chars.next();
+                    return Ok(Some(Token::Open));
+                }
+                '}' => {
+                    self.chars.next();
+                    return Ok(Some(Token::Close));
+                }
+                '"' => {
+                    self.chars.next();
+                    return self.quoted().map(|s| Some(Token::Str(s)));
+                }
+                _ => return Ok(Some(Token::Str(self.unquoted()))),
+            }
+        }
+    }
+
+    fn quoted(&mut self) -> Result<String, String> {
+        let start_line = self.line;
+        let mut value = String::new();
+
+        while let Some((_, c)) = self.chars.next() {
+            match c {
+                '"' => return Ok(value),
+                '\\' => match self.chars.next() {
+                    Some((_, 'n')) => value.push('\n'),
+                    Some((_, 't')) => value.push('\t'),
+                    Some((_, '\\')) => value.push('\\'),
+                    Some((_, '"')) => value.push('"'),
+                    Some((_, other)) => {
+                        value.push('\\');
+                        value.push(other);
+                    }
+                    None => break,
+                },
+                '\n' => {
+                    self.line += 1;
+                    value.push(c);
+                }
+                _ => value.push(c),
+            }
+        }
+
+        Err(format!("Unterminated string starting on line {}", start_line))
+    }
+
+    fn unquoted(&mut self) -> String {
+        let mut value = String::new();
+        while let Some(&(_, c)) = self.chars.peek() {
+            if c.is_whitespace() || c == '{' || c == '}' || c == '"' {
+                break;
+            }
+            value.push(c);
+            self.chars.next();
+        }
+        value
+    }
+}
+
+/// Parse a text VDF document into a root object
+pub fn parse(input: &str) -> Result<VdfValue, String> {
+    let mut lexer = Lexer::new(input);
+    let entries = parse_entries(&mut lexer, false)?;
+    Ok(VdfValue::Object(entries))
+}
+
+fn parse_entries(lexer: &mut Lexer, nested: bool) -> Result<Vec<(String, VdfValue)>, String> {
+    let mut entries = Vec::new();
+
+    loop {
+        let key = match lexer.next_token()? {
+            Some(Token::Str(key)) => key,
+            Some(Token::Close) if nested => return Ok(entries),
+            Some(Token::Close) => return Err(format!("Unexpected '}}' on line {}", lexer.line)),
+            Some(Token::Open) => return Err(format!("Expected a key on line {}", lexer.line)),
+            None if nested => return Err("Unexpected end of file inside an object".to_string()),
+            None => return Ok(entries),
+        };
+
+        let value = match lexer.next_token()? {
+            Some(Token::Str(value)) => VdfValue::String(value),
+            Some(Token::Open) => VdfValue::Object(parse_entries(lexer, true)?),
+            Some(Token::Close) | None => {
+                return Err(format!("Missing value for key '{}' on line {}", key, lexer.line))
+            }
+        };
+
+        entries.push((key, value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIBRARY_FOLDERS: &str = r#""libraryfolders"
+{
+	"0"
+	{
+		"path"		"C:\\Program Files (x86)\\Steam"
+		"label"		""
+		"contentid"		"4382737221392872136"
+		"totalsize"		"0"
+		"apps"
+		{
+			"228980"		"353214571"
+		}
+	}
+	"1"
+	{
+		"path"		"D:\\SteamLibrary"
+		"apps"
+		{
+			"1329500"		"523108834"
+		}
+	}
+}
+"#;
+
+    const APP_MANIFEST: &str = r#""AppState"
+{
+	"appid"		"1329500"
+	"Universe"		"1"
+	"name"		"SpiderHeck"
+	"StateFlags"		"4"
+	"installdir"		"SpiderHeck"
+	"UserConfig"
+	{
+		"language"		"english"
+	}
+}
+"#;
+
+    fn text(value: &str) -> VdfValue {
+        VdfValue::String(value.to_string())
+    }
+
+    #[test]
+    fn parses_library_folders() {
+        let document = parse(LIBRARY_FOLDERS).unwrap();
+        let folders = document.get("libraryfolders").unwrap();
+
+        let paths: Vec<&str> = folders
+            .entries()
+            .filter_map(|(_, folder)| folder.get("path").and_then(VdfValue::as_str))
+            .collect();
+        assert_eq!(paths, vec!["C:\\Program Files (x86)\\Steam", "D:\\SteamLibrary"]);
+        assert_eq!(
+            folders.get_path(&["1", "apps", "1329500"]).and_then(VdfValue::as_str),
+            Some("523108834")
+        );
+        assert_eq!(folders.get_path(&["0", "label"]).and_then(VdfValue::as_str), Some(""));
+    }
+
+    #[test]
+    fn parses_legacy_library_folders() {
+        let document = parse("\"LibraryFolders\"\n{\n\t\"TimeNextStatsReport\"\t\"1\"\n\t\"1\"\t\"E:\\\\Games\"\n}\n").unwrap();
+        assert_eq!(document.get_path(&["libraryfolders", "1"]), Some(&text("E:\\Games")));
+    }
+
+    #[test]
+    fn parses_app_manifest() {
+        let document = parse(APP_MANIFEST).unwrap();
+        assert_eq!(document.get_path(&["AppState", "installdir"]).and_then(VdfValue::as_str), Some("SpiderHeck"));
+        // Keys are looked up case-insensitively
+        assert_eq!(document.get_path(&["appstate", "USERCONFIG", "language"]), Some(&text("english")));
+        assert!(document.get_path(&["AppState", "installdir", "deeper"]).is_none());
+        assert!(document.get_path(&["AppState", "missing"]).is_none());
+    }
+
+    #[test]
+    fn unescapes_quoted_strings() {
+        let document = parse(r#""key" "tab\tquote\"slash\\newline\nother\q""#).unwrap();
+        assert_eq!(document.get("key"), Some(&text("tab\tquote\"slash\\newline\nother\\q")));
+    }
+
+    #[test]
+    fn keeps_nested_blocks_in_order() {
+        let document = parse("\"a\" { \"b\" { \"c\" \"1\" } \"d\" \"2\" } \"e\" {}").unwrap();
+        let expected = VdfValue::Object(vec![
+            (
+                "a".to_string(),
+                VdfValue::Object(vec![
+                    ("b".to_string(), VdfValue::Object(vec![("c".to_string(), text("1"))])),
+                    ("d".to_string(), text("2")),
+                ]),
+            ),
+            ("e".to_string(), VdfValue::Object(Vec::new())),
+        ]);
+        assert_eq!(document, expected);
+    }
+
+    #[test]
+    fn skips_comments_conditionals_and_bom() {
+        let input = "\u{feff}// header\n\"root\"\n{\n\tunquoted value // trailing\n\t\"win\" \"yes\" [$WIN32]\n}\n";
+        let document = parse(input).unwrap();
+        assert_eq!(document.get_path(&["root", "unquoted"]), Some(&text("value")));
+        assert_eq!(document.get_path(&["root", "win"]), Some(&text("yes")));
+    }
+
+    #[test]
+    fn reports_malformed_documents() {
+        assert_eq!(parse("\"a\" { \"b\" \"c\"").unwrap_err(), "Unexpected end of file inside an object");
+        assert_eq!(parse("\"a\" \"b\" }").unwrap_err(), "Unexpected '}' on line 1");
+        assert_eq!(parse("\"a\"\n").unwrap_err(), "Missing value for key 'a' on line 2");
+        assert_eq!(parse("\"a\" \"b\n").unwrap_err(), "Unterminated string starting on line 1");
+        assert_eq!(parse("\"a\" / \"b\"").unwrap_err(), "Unexpected '/' on line 1");
+        assert!(parse("{ }").is_err());
+        assert_eq!(parse("").unwrap(), VdfValue::Object(Vec::new()));
+    }
+}
@@ -1,19 +1,18 @@
+use log::warn;
 use semver::Version;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::io;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
 use tauri::Emitter;
+use crate::{cache, doorstop, download, extract, manifest, settings, transaction, InstalledMod, Mod};
+use crate::operations::Operation;
+use crate::settings::ReleaseChannel;
 
 const SILK_VERSION_URL: &str = "https://raw.githubusercontent.com/SilkModding/Silk/master/version";
 const GITHUB_RELEASES_API: &str = "https://api.github.com/repos/SilkModding/Silk/releases";
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct SilkVersion {
-    pub version: String,
-    pub download_url: String,
-}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -25,6 +24,352 @@ pub struct ModVersionInfo {
     pub max_silk_version: Option<String>,
 }
 
+/// A downloadable file attached to a GitHub release
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub download_url: String,
+    pub size: u64,
+    /// e.g. "sha256:abc123..." (only present for assets uploaded after GitHub added digests)
+    pub digest: Option<String>,
+}
+
+/// A published Silk release on GitHub
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SilkRelease {
+    /// Tag without the leading `v`
+    pub version: String,
+    pub tag: String,
+    pub name: String,
+    pub prerelease: bool,
+    pub draft: bool,
+    /// ISO 8601 timestamp, absent for drafts
+    pub published_at: Option<String>,
+    /// Release notes in Markdown
+    pub body: String,
+    pub html_url: String,
+    pub assets: Vec<ReleaseAsset>,
+}
+
+impl SilkRelease {
+    fn from_json(json: &serde_json::Value) -> Option<SilkRelease> {
+        let tag = json.get("tag_name")?.as_str()?.to_string();
+        let text = |key: &str| json.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+        let flag = |key: &str| json.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+
+        Some(SilkRelease {
+            version: tag.trim_start_matches('v').to_string(),
+            name: Some(text("name")).filter(|n| !n.is_empty()).unwrap_or_else(|| tag.clone()),
+            prerelease: flag("prerelease"),
+            draft: flag("draft"),
+            published_at: json.get("published_at").and_then(|v| v.as_str()).map(|s| s.to_string()),
+            body: text("body"),
+            html_url: text("html_url"),
+            assets: json
+                .get("assets")
+                .and_then(|a| a.as_array())
+                .map(|assets| assets.iter().filter_map(ReleaseAsset::from_json).collect())
+                .unwrap_or_default(),
+            tag,
+        })
+    }
+
+    /// The Silk archive among the release's assets. Older releases name it
+    /// `Silk-v{version}.zip`; otherwise any Silk zip is accepted.
+    pub fn archive_asset(&self) -> Option<&ReleaseAsset> {
+        let expected = format!("Silk-v{}.zip", self.version);
+        self.assets.iter().find(|a| a.name == expected).or_else(|| {
+            self.assets.iter().find(|a| {
+                let name = a.name.to_lowercase();
+                name.starts_with("silk") && name.ends_with(".zip")
+            })
+        })
+    }
+
+    /// Whether the release is offered on a channel
+    pub fn is_in_channel(&self, channel: ReleaseChannel) -> bool {
+        !self.draft && (channel == ReleaseChannel::Prerelease || !self.prerelease)
+    }
+}
+
+impl ReleaseAsset {
+    pub fn from_json(json: &serde_json::Value) -> Option<ReleaseAsset> {
+        Some(ReleaseAsset {
+            name: json.get("name")?.as_str()?.to_string(),
+            download_url: json.get("browser_download_url")?.as_str()?.to_string(),
+            size: json.get("size").and_then(|s| s.as_u64()).unwrap_or(0),
+            digest: json
+                .get("digest")
+                .and_then(|d| d.as_str())
+                .map(|d| d.to_string()),
+        })
+    }
+}
+
+/// How long until GitHub's rate limit resets, as shown to the user
+fn describe_reset(reset_at: u64) -> String {
+    let wait = reset_at.saturating_sub(now());
+    match wait.div_ceil(60) {
+        0 => "now".to_string(),
+        1 => "in 1 minute".to_string(),
+        minutes => format!("in {} minutes", minutes),
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// A failed GitHub API request
+#[derive(Debug)]
+pub enum GitHubError {
+    /// The rate limit is used up until `reset_at` (seconds since the Unix epoch)
+    RateLimited { reset_at: u64, authenticated: bool },
+    Other(String),
+}
+
+impl std::fmt::Display for GitHubError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GitHubError::RateLimited { reset_at, authenticated: true } => write!(
+                f,
+                "GitHub API rate limit reached for your token; try again {}",
+                describe_reset(*reset_at)
+            ),
+            GitHubError::RateLimited { reset_at, authenticated: false } => write!(
+                f,
+                "GitHub API rate limit reached; try again {}, or add a GitHub token in Settings to raise the limit",
+                describe_reset(*reset_at)
+            ),
+            GitHubError::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+impl From<GitHubError> for String {
+    fn from(error: GitHubError) -> String {
+        error.to_string()
+    }
+}
+
+/// When the rate limit last reported as used up resets, so requests aren't sent until then
+static RATE_LIMIT_RESET: AtomicU64 = AtomicU64::new(0);
+
+/// A GitHub API response stored with its ETag so it can be revalidated for free
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedResponse {
+    etag: String,
+    link: Option<String>,
+    body: serde_json::Value,
+}
+
+/// The URL of the `rel="next"` page in a `Link` header
+fn next_page_url(link: &str) -> Option<String> {
+    link.split(',').find_map(|part| {
+        let mut pieces = part.split(';');
+        let url = pieces.next()?.trim().strip_prefix('<')?.strip_suffix('>')?;
+        pieces
+            .any(|param| param.trim() == "rel=\"next\"")
+            .then(|| url.to_string())
+    })
+}
+
+/// When a rate-limited response says the limit resets, from `Retry-After` (secondary limits)
+/// or an exhausted `X-RateLimit-Remaining`; `None` for any other response
+fn rate_limit_reset(status: reqwest::StatusCode, headers: &reqwest::header::HeaderMap) -> Option<u64> {
+    if status != reqwest::StatusCode::FORBIDDEN && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+    let header = |name: &str| headers.get(name)?.to_str().ok()?.parse::<u64>().ok();
+    match (header("retry-after"), header("x-ratelimit-remaining"), header("x-ratelimit-reset")) {
+        (Some(seconds), _, _) => Some(now() + seconds),
+        (None, Some(0), Some(reset)) => Some(reset),
+        _ => None,
+    }
+}
+
+/// Client for the GitHub REST API that caches responses by ETag and tracks the rate limit
+pub struct GitHubClient {
+    client: reqwest::Client,
+    token: Option<String>,
+}
+
+impl GitHubClient {
+    /// Create a client using the GitHub token from the settings, if one is set
+    pub fn from_settings() -> GitHubClient {
+        let token = settings::load_settings()
+            .ok()
+            .and_then(|s| s.github_token)
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty());
+
+        GitHubClient {
+            client: reqwest::Client::new(),
+            token,
+        }
+    }
+
+    fn cache_path(&self, url: &str) -> Option<PathBuf> {
+        // Authenticated responses can differ (drafts, private repos), so they are cached apart
+        let key = cache::cache_key(&format!("{}\n{}", url, self.token.is_some()));
+        let dir = cache::cache_dir("github").ok()?;
+        Some(dir.join(format!("{}.json", key)))
+    }
+
+    fn load_cached(&self, url: &str) -> Option<CachedResponse> {
+        let content = fs::read_to_string(self.cache_path(url)?).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    fn store_cached(&self, url: &str, response: &CachedResponse) {
+        let Some(path) = self.cache_path(url) else {
+            return;
+        };
+        match serde_json::to_string(response) {
+            Ok(json) => {
+                if let Err(e) = fs::write(&path, json) {
+                    warn!("Failed to cache GitHub response for {}: {}", url, e);
+                }
+            }
+            Err(e) => warn!("Failed to serialize GitHub response for {}: {}", url, e),
+        }
+    }
+
+    /// Use a cached copy while rate limited, since it's better than no answer
+    fn rate_limited(&self, url: &str, reset_at: u64) -> Result<CachedResponse, GitHubError> {
+        match self.load_cached(url) {
+            Some(cached) => {
+                warn!("GitHub rate limit reached; using cached response for {}", url);
+                Ok(cached)
+            }
+            None => Err(GitHubError::RateLimited {
+                reset_at,
+                authenticated: self.token.is_some(),
+            }),
+        }
+    }
+
+    /// GET a URL, revalidating any cached copy with `If-None-Match`
+    async fn fetch(&self, url: &str) -> Result<CachedResponse, GitHubError> {
+        let reset_at = RATE_LIMIT_RESET.load(Ordering::Relaxed);
+        if reset_at > now() {
+            return self.rate_limited(url, reset_at);
+        }
+
+        let cached = self.load_cached(url);
+        let mut request = self
+            .client
+            .get(url)
+            .header("User-Agent", "entwine-app")
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28");
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(cached) = &cached {
+            request = request.header("If-None-Match", &cached.etag);
+        }
+
+        let resp = match request.send().await {
+            Ok(resp) => resp,
+            Err(e) => {
+                // Offline: the last answer GitHub gave is the best we have
+                return match cached {
+                    Some(cached) => {
+                        warn!("Could not reach GitHub ({}); using cached response for {}", e, url);
+                        Ok(cached)
+                    }
+                    None => Err(GitHubError::Other(format!("Failed to reach GitHub: {}", e))),
+                };
+            }
+        };
+
+        let header = |name: &str| {
+            resp.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+        let status = resp.status();
+        if status == reqwest::StatusCode::NOT_MODIFIED {
+            if let Some(cached) = cached {
+                return Ok(cached);
+            }
+        }
+
+        if let Some(reset_at) = rate_limit_reset(status, resp.headers()) {
+            RATE_LIMIT_RESET.store(reset_at, Ordering::Relaxed);
+            return self.rate_limited(url, reset_at);
+        }
+
+        if status == reqwest::StatusCode::UNAUTHORIZED {
+            return Err(GitHubError::Other(
+                "GitHub rejected the token from Settings; check that it is valid".to_string(),
+            ));
+        }
+
+        if !status.is_success() {
+            return Err(GitHubError::Other(format!("GitHub request to {} failed: {}", url, status)));
+        }
+
+        let etag = header("etag");
+        let link = header("link");
+        let body: serde_json::Value = resp
+            .json()
+            .await
+            .map_err(|e| GitHubError::Other(format!("Failed to parse GitHub response: {}", e)))?;
+
+        let response = CachedResponse {
+            etag: etag.unwrap_or_default(),
+            link,
+            body,
+        };
+        if !response.etag.is_empty() {
+            self.store_cached(url, &response);
+        }
+        Ok(response)
+    }
+
+    /// GET a single API resource
+    pub async fn get_json(&self, url: &str) -> Result<serde_json::Value, GitHubError> {
+        Ok(self.fetch(url).await?.body)
+    }
+
+    /// GET every page of a list endpoint by following `Link: rel="next"`
+    pub async fn get_all_pages(&self, url: &str) -> Result<Vec<serde_json::Value>, GitHubError> {
+        let mut items = Vec::new();
+        let mut next = Some(url.to_string());
+
+        while let Some(page_url) = next {
+            let page = self.fetch(&page_url).await?;
+            match page.body {
+                serde_json::Value::Array(page_items) => items.extend(page_items),
+                _ => return Err(GitHubError::Other(format!("Expected a list from {}", page_url))),
+            }
+            next = page.link.as_deref().and_then(next_page_url);
+        }
+
+        Ok(items)
+    }
+}
+
+/// An installed mod with a newer version available in the registry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModUpdate {
+    pub mod_id: String,
+    pub name: String,
+    pub file_name: String,
+    pub installed_version: String,
+    pub latest_version: String,
+    pub latest: Mod,
+}
+
 /// Get the currently installed Silk version
 pub fn get_installed_silk_version(game_path: &str) -> Result<String, String> {
     let version_file = PathBuf::from(game_path).join("Silk").join("version.txt");
@@ -38,37 +383,45 @@ pub fn get_installed_silk_version(game_path: &str) -> Result<String, String> {
         .map_err(|e| format!("Failed to read version file: {}", e))
 }
 
-/// Get the latest Silk version from GitHub
-pub async fn get_latest_silk_version() -> Result<String, String> {
-    // Prefer GitHub releases latest endpoint to get the most accurate release tag
-    let client = reqwest::Client::new();
+/// Get the newest Silk release on a channel
+pub async fn get_latest_silk_release(channel: ReleaseChannel) -> Result<SilkRelease, GitHubError> {
+    if channel == ReleaseChannel::Prerelease {
+        // The `latest` endpoint never returns prereleases
+        return fetch_silk_releases(channel)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| GitHubError::Other("No Silk releases found".to_string()));
+    }
 
-    let resp = client
-        .get(format!("{}/latest", GITHUB_RELEASES_API))
-        .header("User-Agent", "entwine-app")
-        .send()
-        .await
-        .map_err(|e| format!("Failed to fetch latest release from GitHub: {}", e))?;
+    let json = GitHubClient::from_settings()
+        .get_json(&format!("{}/latest", GITHUB_RELEASES_API))
+        .await?;
 
-    if resp.status().is_success() {
-        let json: serde_json::Value = resp
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse GitHub latest release JSON: {}", e))?;
+    SilkRelease::from_json(&json).ok_or_else(|| GitHubError::Other("GitHub latest release has no tag".to_string()))
+}
 
-        if let Some(tag) = json.get("tag_name").and_then(|t| t.as_str()) {
-            // Strip leading 'v' if present
-            let v = tag.trim_start_matches('v').to_string();
-            return Ok(v);
-        }
-    }
+/// Get the latest Silk version on a channel from GitHub
+pub async fn get_latest_silk_version(channel: ReleaseChannel) -> Result<String, String> {
+    let release_error = match get_latest_silk_release(channel).await {
+        Ok(release) => return Ok(release.version),
+        // The raw version file can't tell channels apart, so don't paper over a rate limit with it
+        Err(e @ GitHubError::RateLimited { .. }) => return Err(e.into()),
+        Err(GitHubError::Other(e)) => e,
+    };
+    warn!("Falling back to the raw Silk version file: {}", release_error);
 
     // Fallback: try the raw version file
+    let client = reqwest::Client::new();
     let response = client
         .get(SILK_VERSION_URL)
         .send()
         .await
-        .map_err(|e| format!("Failed to fetch fallback latest version: {}", e))?;
+        .map_err(|e| format!("{}; failed to fetch fallback latest version: {}", release_error, e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("Fallback latest version request failed: {}", response.status()));
+    }
 
     let version = response
         .text()
@@ -78,72 +431,115 @@ pub async fn get_latest_silk_version() -> Result<String, String> {
     Ok(version.trim().to_string())
 }
 
-/// Check if an update is available
-pub async fn check_for_updates(game_path: &str) -> Result<Option<SilkVersion>, String> {
+/// Check if an update is available on a channel
+pub async fn check_for_updates(game_path: &str, channel: ReleaseChannel) -> Result<Option<SilkRelease>, String> {
     let current_version = get_installed_silk_version(game_path)?;
-    let latest_version = get_latest_silk_version().await?;
-    
-    if is_newer_version(&latest_version, &current_version) {
-        let download_url = format!("https://github.com/SilkModding/Silk/releases/download/v{}/Silk-v{}.zip", latest_version, latest_version);
-        
-        Ok(Some(SilkVersion {
-            version: latest_version,
-            download_url,
-        }))
+    let latest = get_latest_silk_release(channel).await?;
+
+    if is_newer_version(&latest.version, &current_version) {
+        Ok(Some(latest))
     } else {
         Ok(None)
     }
 }
 
+/// Parse a version the way mod authors tend to write it ("v1.2", "1.2.0.3", "1.0")
+pub fn parse_version_lenient(version: &str) -> Option<Version> {
+    let trimmed = version.trim().trim_start_matches(['v', 'V']);
+    if let Ok(parsed) = Version::parse(trimmed) {
+        return Some(parsed);
+    }
+
+    // Split off any pre-release/build suffix, then pad or truncate to three numeric parts
+    let (core, suffix) = match trimmed.find(['-', '+']) {
+        Some(idx) => trimmed.split_at(idx),
+        None => (trimmed, ""),
+    };
+    let mut parts: Vec<&str> = core.split('.').collect();
+    if parts.is_empty() || parts.iter().any(|p| p.is_empty() || !p.chars().all(|c| c.is_ascii_digit())) {
+        return None;
+    }
+    parts.resize(parts.len().max(3), "0");
+    parts.truncate(3);
+
+    Version::parse(&format!("{}{}", parts.join("."), suffix)).ok()
+}
+
 /// Check if version_a is newer than version_b
 pub fn is_newer_version(version_a: &str, version_b: &str) -> bool {
-    match (Version::parse(version_a), Version::parse(version_b)) {
-        (Ok(a), Ok(b)) => a > b,
+    match (parse_version_lenient(version_a), parse_version_lenient(version_b)) {
+        (Some(a), Some(b)) => a > b,
         _ => false,
     }
 }
 
-/// List available Silk versions (for version swapping)
-pub async fn list_available_versions() -> Result<Vec<String>, String> {
-    let client = reqwest::Client::new();
+async fn fetch_silk_releases(channel: ReleaseChannel) -> Result<Vec<SilkRelease>, GitHubError> {
+    let items = GitHubClient::from_settings()
+        .get_all_pages(&format!("{}?per_page=100", GITHUB_RELEASES_API))
+        .await?;
 
-    let resp = client
-        .get(GITHUB_RELEASES_API)
-        .header("User-Agent", "entwine-app")
-        .send()
-        .await
-        .map_err(|e| format!("Failed to fetch releases from GitHub: {}", e))?;
+    let mut releases: Vec<SilkRelease> = items.iter().filter_map(SilkRelease::from_json).collect();
+    releases.retain(|r| r.is_in_channel(channel));
 
-    if !resp.status().is_success() {
-        return Err(format!("GitHub releases request failed: {}", resp.status()));
-    }
+    // Sort and dedupe versions (descending semver)
+    releases.sort_by(|a, b| {
+        match (parse_version_lenient(&a.version), parse_version_lenient(&b.version)) {
+            (Some(av), Some(bv)) => bv.cmp(&av),
+            _ => b.version.cmp(&a.version),
+        }
+    });
+    releases.dedup_by(|a, b| a.version == b.version);
 
-    let json: serde_json::Value = resp
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse releases JSON: {}", e))?;
+    Ok(releases)
+}
 
-    let mut versions = Vec::new();
+/// List the Silk releases on a channel, newest first
+pub async fn list_silk_releases(channel: ReleaseChannel) -> Result<Vec<SilkRelease>, String> {
+    Ok(fetch_silk_releases(channel).await?)
+}
 
-    if let Some(arr) = json.as_array() {
-        for release in arr.iter() {
-            if let Some(tag) = release.get("tag_name").and_then(|t| t.as_str()) {
-                let v = tag.trim_start_matches('v').to_string();
-                versions.push(v);
-            }
-        }
-    }
+/// List available Silk versions on a channel (for version swapping)
+pub async fn list_available_versions(channel: ReleaseChannel) -> Result<Vec<String>, String> {
+    Ok(list_silk_releases(channel)
+        .await?
+        .into_iter()
+        .map(|r| r.version)
+        .collect())
+}
 
-    // Sort and dedupe versions (descending semver)
-    versions.sort_by(|a, b| {
-        match (Version::parse(a), Version::parse(b)) {
-            (Ok(av), Ok(bv)) => bv.cmp(&av),
-            _ => b.cmp(a),
-        }
-    });
-    versions.dedup();
+/// Fetch a single Silk release, including its notes and assets
+pub async fn get_silk_release(version: &str) -> Result<SilkRelease, String> {
+    let tag = format!("v{}", version.trim_start_matches('v'));
+    let json = fetch_release_json(GITHUB_RELEASES_API, &tag).await?;
+    SilkRelease::from_json(&json).ok_or_else(|| format!("GitHub release {} has no tag", tag))
+}
 
-    Ok(versions)
+/// Fetch a GitHub release by tag
+pub async fn fetch_release_json(releases_api: &str, tag: &str) -> Result<serde_json::Value, String> {
+    GitHubClient::from_settings()
+        .get_json(&format!("{}/tags/{}", releases_api, tag))
+        .await
+        .map_err(|e| format!("Failed to fetch release {} from GitHub: {}", tag, e))
+}
+
+/// Download the release archive of a Silk version, verified against the release asset's size and digest
+pub async fn fetch_silk_archive(
+    version: &str,
+    window: &tauri::Window,
+    operation: &Operation,
+) -> Result<download::DownloadedFile, String> {
+    let release = get_silk_release(version)
+        .await
+        .map_err(|e| format!("Could not verify Silk v{} download: {}", version, e))?;
+    let asset = release
+        .archive_asset()
+        .ok_or_else(|| format!("Silk v{} release has no Silk archive", version))?;
+    
+    let _ = window.emit("install-progress", format!("Downloading Silk v{}...", version));
+    
+    let client = reqwest::Client::new();
+    let expected = download::ExpectedDownload::new(Some(asset.size), asset.digest.as_deref());
+    download::download_to_file(&client, &asset.download_url, &expected, &format!("Silk v{}", version), window, operation).await
 }
 
 /// Download a specific Silk version
@@ -151,71 +547,55 @@ pub async fn download_silk_version(
     version: &str,
     game_path: &str,
     window: tauri::Window,
+    operation: &Operation,
 ) -> Result<(), String> {
     let game_dir = PathBuf::from(game_path);
-    let download_url = format!("https://github.com/SilkModding/Silk/releases/download/v{}/Silk-v{}.zip", version, version);
-    
-    let _ = window.emit("install-progress", format!("Downloading Silk v{}...", version));
-    
-    // Download Silk
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&download_url)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to download Silk: {}", e))?;
-    
-    let bytes = response
-        .bytes()
-        .await
-        .map_err(|e| format!("Failed to read Silk download: {}", e))?;
+    let label = format!("Silk v{}", version);
+    let downloaded = fetch_silk_archive(version, &window, operation).await?;
     
     let _ = window.emit("install-progress", "Extracting Silk...");
+    download::emit_progress(&window, &label, download::DownloadPhase::Extracting, downloaded.size, Some(downloaded.size), 0);
     
-    // Create a temp file for the zip
-    let temp_dir = std::env::temp_dir();
-    let zip_path = temp_dir.join("silk_download.zip");
+    // Stage the new files next to the game so a failure leaves the current install alone
+    let mut transaction = transaction::Transaction::begin(&game_dir)?;
+    let staging_dir = transaction.staging_dir();
+    extract::extract_zip(downloaded.open()?, &staging_dir, &extract::Limits::default(), |_| true, Some(operation))
+        .map_err(|e| format!("Failed to extract Silk: {}", e))?;
     
-    fs::write(&zip_path, &bytes)
-        .map_err(|e| format!("Failed to write temp file: {}", e))?;
+    if !staging_dir.join("Silk").is_dir() {
+        return Err(format!("The Silk v{} archive does not contain a Silk folder", version));
+    }
+    let doorstop_shared = doorstop::prepare_staged(&game_dir, &staging_dir, doorstop::Loader::Silk)?;
     
-    // Extract the zip directly to game directory
-    let file = fs::File::open(&zip_path)
-        .map_err(|e| format!("Failed to open zip file: {}", e))?;
+    // Write version file
+    fs::write(staging_dir.join("Silk").join("version.txt"), version)
+        .map_err(|e| format!("Failed to write version file: {}", e))?;
     
-    let mut archive = zip::ZipArchive::new(file)
-        .map_err(|e| format!("Failed to read zip archive: {}", e))?;
+    let mut new_manifest = manifest::InstallManifest::from_staged(
+        manifest::SILK_COMPONENT,
+        version,
+        &staging_dir,
+        &[PathBuf::new()],
+    )?;
     
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i)
-            .map_err(|e| format!("Failed to read zip entry: {}", e))?;
-        
-        let outpath = game_dir.join(file.mangled_name());
-        
-        if file.name().ends_with('/') {
-            fs::create_dir_all(&outpath)
-                .map_err(|e| format!("Failed to create directory: {}", e))?;
-        } else {
-            if let Some(parent) = outpath.parent() {
-                fs::create_dir_all(parent)
-                    .map_err(|e| format!("Failed to create parent directory: {}", e))?;
-            }
-            
-            let mut outfile = fs::File::create(&outpath)
-                .map_err(|e| format!("Failed to create file: {}", e))?;
-            
-            io::copy(&mut file, &mut outfile)
-                .map_err(|e| format!("Failed to write file: {}", e))?;
+    // Past this point the install is no longer cancellable; replaced files are restored on failure
+    operation.check()?;
+    transaction.place_tree(&staging_dir)?;
+    
+    // Files the previous version installed that this one doesn't ship
+    if let Some(old_manifest) = manifest::load(&game_dir, manifest::SILK_COMPONENT) {
+        for stale in manifest::stale_files(&game_dir, &old_manifest, &new_manifest) {
+            transaction.remove(&stale)?;
         }
     }
+    if doorstop_shared {
+        doorstop::record_shared(&game_dir, &mut new_manifest, &mut transaction)?;
+    }
+    transaction.back_up(&manifest::manifest_path(manifest::SILK_COMPONENT))?;
+    manifest::save(&game_dir, &new_manifest)?;
+    transaction.commit();
     
-    // Clean up temp file
-    let _ = fs::remove_file(&zip_path);
-    
-    // Write version file
-    let version_file = game_dir.join("Silk").join("version.txt");
-    fs::write(&version_file, version)
-        .map_err(|e| format!("Failed to write version file: {}", e))?;
+    download::emit_progress(&window, &label, download::DownloadPhase::Done, downloaded.size, Some(downloaded.size), 0);
     
     let _ = window.emit("install-progress", format!("Silk v{} installed successfully!", version));
     
@@ -253,6 +633,28 @@ pub fn check_mod_compatibility(
     Ok(true)
 }
 
+/// Compare every installed mod against the registry and list the ones that can be updated
+pub fn find_mod_updates(installed: &[InstalledMod], registry: &[Mod]) -> Vec<ModUpdate> {
+    installed
+        .iter()
+        .filter_map(|installed_mod| {
+            let latest = registry.iter().find(|m| m.id == installed_mod.id)?;
+            if !is_newer_version(&latest.version, &installed_mod.version) {
+                return None;
+            }
+
+            Some(ModUpdate {
+                mod_id: installed_mod.id.clone(),
+                name: installed_mod.name.clone(),
+                file_name: installed_mod.file_name.clone(),
+                installed_version: installed_mod.version.clone(),
+                latest_version: latest.version.clone(),
+                latest: latest.clone(),
+            })
+        })
+        .collect()
+}
+
 /// Get mod version info from metadata
 pub fn get_mod_version_info(mod_id: &str, mods_path: &str) -> Result<ModVersionInfo, String> {
     let metadata_path = PathBuf::from(mods_path).join(".entwine_metadata.json");
@@ -301,3 +703,94 @@ pub fn get_mod_version_info(mod_id: &str, mods_path: &str) -> Result<ModVersionI
         max_silk_version,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderMap, HeaderValue};
+    use reqwest::StatusCode;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn finds_the_next_page_among_other_rels() {
+        let link = "<https://api.github.com/repositories/1/releases?page=1>; rel=\"prev\", \
+                    <https://api.github.com/repositories/1/releases?page=3>; rel=\"next\", \
+                    <https://api.github.com/repositories/1/releases?page=5>; rel=\"last\"";
+        assert_eq!(
+            next_page_url(link).as_deref(),
+            Some("https://api.github.com/repositories/1/releases?page=3")
+        );
+    }
+
+    #[test]
+    fn last_page_has_no_next() {
+        let link = "<https://api.github.com/repositories/1/releases?page=1>; rel=\"first\", \
+                    <https://api.github.com/repositories/1/releases?page=4>; rel=\"prev\"";
+        assert_eq!(next_page_url(link), None);
+        assert_eq!(next_page_url(""), None);
+    }
+
+    #[test]
+    fn exhausted_rate_limit_reports_when_it_resets() {
+        let reset_at = now() + 90;
+        let exhausted = headers(&[("x-ratelimit-remaining", "0"), ("x-ratelimit-reset", &reset_at.to_string())]);
+        assert_eq!(rate_limit_reset(StatusCode::FORBIDDEN, &exhausted), Some(reset_at));
+        assert_eq!(rate_limit_reset(StatusCode::TOO_MANY_REQUESTS, &exhausted), Some(reset_at));
+
+        let anonymous = GitHubError::RateLimited { reset_at, authenticated: false }.to_string();
+        assert!(anonymous.starts_with("GitHub API rate limit reached; try again in 2 minutes"), "{}", anonymous);
+        assert!(anonymous.contains("add a GitHub token in Settings"));
+        let with_token = GitHubError::RateLimited { reset_at, authenticated: true }.to_string();
+        assert!(with_token.contains("for your token") && !with_token.contains("add a GitHub token"));
+    }
+
+    #[test]
+    fn retry_after_takes_precedence() {
+        let secondary = headers(&[("retry-after", "60"), ("x-ratelimit-remaining", "10")]);
+        let reset_at = rate_limit_reset(StatusCode::FORBIDDEN, &secondary).unwrap();
+        assert!(reset_at >= now() + 59 && reset_at <= now() + 60);
+    }
+
+    #[test]
+    fn other_responses_are_not_rate_limits() {
+        let exhausted = headers(&[("x-ratelimit-remaining", "0"), ("x-ratelimit-reset", "1")]);
+        assert_eq!(rate_limit_reset(StatusCode::OK, &exhausted), None);
+        // A 403 with quota left is a permissions problem, not a rate limit
+        let remaining = headers(&[("x-ratelimit-remaining", "42"), ("x-ratelimit-reset", "1")]);
+        assert_eq!(rate_limit_reset(StatusCode::FORBIDDEN, &remaining), None);
+        assert_eq!(rate_limit_reset(StatusCode::FORBIDDEN, &HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn parses_versions_leniently() {
+        for (input, expected) in [
+            ("v1.2", "1.2.0"),
+            ("V1", "1.0.0"),
+            (" 1.2.3 ", "1.2.3"),
+            ("1.2.0.3", "1.2.0"),
+            ("v1.2-beta.1", "1.2.0-beta.1"),
+            ("1.0+build", "1.0.0+build"),
+        ] {
+            assert_eq!(parse_version_lenient(input), Some(Version::parse(expected).unwrap()), "{}", input);
+        }
+        for input in ["", "v", "1..2", "1.x", "latest"] {
+            assert_eq!(parse_version_lenient(input), None, "{}", input);
+        }
+    }
+
+    #[test]
+    fn compares_lenient_versions() {
+        assert!(is_newer_version("v1.10", "1.9.9"));
+        assert!(is_newer_version("1.2.1", "v1.2"));
+        assert!(!is_newer_version("1.2", "1.2.0"));
+        assert!(!is_newer_version("1.2.0-beta", "1.2.0"));
+        assert!(!is_newer_version("unknown", "1.0"));
+    }
+}
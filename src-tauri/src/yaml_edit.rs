@@ -0,0 +1,334 @@
+use serde_yaml::Value as YamlValue;
+
+/// Spaces per nesting level for keys that have to be added
+const INDENT: usize = 2;
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+fn is_ignorable(line: &str) -> bool {
+    let content = line.trim();
+    content.is_empty() || content.starts_with('#')
+}
+
+/// Length of the quoted string at the start of `text`, including both quotes
+fn quoted_len(text: &str) -> Option<usize> {
+    let quote = text.chars().next()?;
+    let mut chars = text.char_indices().skip(1).peekable();
+    while let Some((i, c)) = chars.next() {
+        if quote == '"' && c == '\\' {
+            chars.next();
+        } else if c == quote {
+            // '' is an escaped quote inside a single-quoted string
+            if quote == '\'' && chars.peek().is_some_and(|(_, next)| *next == '\'') {
+                chars.next();
+                continue;
+            }
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+/// The key of a `key: value` line and the byte offset just past its colon
+fn parse_key(content: &str) -> Option<(String, usize)> {
+    if content.starts_with(['"', '\'']) {
+        let end = quoted_len(content)?;
+        let key: String = serde_yaml::from_str(&content[..end]).ok()?;
+        let rest = &content[end..];
+        let colon = rest.len() - rest.trim_start().len();
+        rest[colon..].starts_with(':').then_some((key, end + colon + 1))
+    } else {
+        if content.starts_with(['-', '?', '{', '[', '&', '*', '!', '|', '>']) {
+            return None;
+        }
+        let colon = content
+            .char_indices()
+            .find(|(i, c)| *c == ':' && content[i + 1..].chars().next().is_none_or(|n| n == ' '))
+            .map(|(i, _)| i)?;
+        Some((content[..colon].trim_end().to_string(), colon + 1))
+    }
+}
+
+/// Split what follows a key's colon into the value and a trailing comment
+fn split_comment(rest: &str) -> (&str, &str) {
+    let mut in_quote: Option<char> = None;
+    let mut escaped = false;
+    let mut previous = ' ';
+    for (i, c) in rest.char_indices() {
+        match in_quote {
+            Some(_) if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => in_quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => in_quote = Some(c),
+            None if c == '#' && previous.is_whitespace() => {
+                let value = rest[..i].trim_end();
+                return (value.trim_start(), &rest[value.len()..]);
+            }
+            None => {}
+        }
+        previous = c;
+    }
+    (rest.trim(), "")
+}
+
+/// One past the last line belonging to the value of the key on line `key_line`
+fn block_end(lines: &[String], key_line: usize, end: usize) -> usize {
+    let key_indent = indent_of(&lines[key_line]);
+    let mut last = key_line;
+    for (i, line) in lines.iter().enumerate().take(end).skip(key_line + 1) {
+        if is_ignorable(line) {
+            continue;
+        }
+        let indent = indent_of(line);
+        let content = line.trim_start();
+        // A sequence may sit at the same indent as the key that holds it
+        let is_item = content == "-" || content.starts_with("- ");
+        if indent > key_indent || (indent == key_indent && is_item) {
+            last = i;
+        } else {
+            break;
+        }
+    }
+    last + 1
+}
+
+/// A scalar or flow collection that fits on the key's line
+fn render(value: &YamlValue) -> Option<String> {
+    match value {
+        YamlValue::Sequence(_) | YamlValue::Mapping(_) => serde_json::to_string(value).ok(),
+        YamlValue::Tagged(_) => None,
+        YamlValue::String(s) => {
+            let text = serde_yaml::to_string(value).ok()?;
+            let text = text.trim_end_matches('\n');
+            if text.contains('\n') {
+                // Multi-line strings become a double-quoted scalar with escapes
+                serde_json::to_string(s).ok()
+            } else {
+                Some(text.to_string())
+            }
+        }
+        _ => Some(serde_yaml::to_string(value).ok()?.trim_end_matches('\n').to_string()),
+    }
+}
+
+/// Set `keys` in the mapping spanning `lines[start..end]`, whose entries are indented
+/// more than `parent_indent`
+fn set_in(
+    lines: &mut Vec<String>,
+    start: usize,
+    end: usize,
+    parent_indent: Option<usize>,
+    keys: &[&str],
+    rendered: &str,
+) -> Option<()> {
+    let content_lines: Vec<usize> = (start..end).filter(|&i| !is_ignorable(&lines[i])).collect();
+    let mapping_indent = content_lines.first().map(|&i| indent_of(&lines[i]));
+    if let (Some(indent), Some(parent)) = (mapping_indent, parent_indent) {
+        if indent <= parent {
+            return None;
+        }
+    }
+
+    // Step from key to key, skipping over each key's value
+    let mut found = None;
+    let mut mapping_end = start;
+    let mut next = content_lines.first().copied();
+    while let Some(i) = next {
+        if Some(indent_of(&lines[i])) != mapping_indent {
+            return None;
+        }
+        // Anything but spaces before the key (tabs, Unicode whitespace) isn't indentation
+        let content = &lines[i][indent_of(&lines[i])..];
+        if content.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let (key, after_colon) = parse_key(content)?;
+        if key == keys[0] {
+            found = Some((i, after_colon));
+            break;
+        }
+        mapping_end = block_end(lines, i, end);
+        next = (mapping_end..end).find(|&j| !is_ignorable(&lines[j]));
+    }
+
+    let Some((line, after_colon)) = found else {
+        // Add the missing keys after the mapping's last entry
+        let indent = mapping_indent.unwrap_or_else(|| parent_indent.map_or(0, |p| p + INDENT));
+        let at = mapping_end;
+        let new_lines = keys.iter().enumerate().map(|(depth, key)| {
+            let key = render(&YamlValue::String(key.to_string())).unwrap_or_else(|| key.to_string());
+            let padding = " ".repeat(indent + depth * INDENT);
+            if depth + 1 == keys.len() {
+                format!("{}{}: {}", padding, key, rendered)
+            } else {
+                format!("{}{}:", padding, key)
+            }
+        });
+        lines.splice(at..at, new_lines.collect::<Vec<_>>());
+        return Some(());
+    };
+
+    let indent = indent_of(&lines[line]);
+    let body_end = block_end(lines, line, end);
+    let content = lines[line][indent..].to_string();
+    let (value, comment) = split_comment(&content[after_colon..]);
+
+    if keys.len() > 1 {
+        // Only block mappings can be descended into line by line
+        if !value.is_empty() {
+            return None;
+        }
+        return set_in(lines, line + 1, body_end, Some(indent), &keys[1..], rendered);
+    }
+
+    lines[line] = format!("{}{} {}{}", &lines[line][..indent], &content[..after_colon], rendered, comment);
+    lines.drain(line + 1..body_end);
+    Some(())
+}
+
+/// Set the value at `keys` in a YAML document, changing only the lines that hold it so
+/// comments, key order and formatting elsewhere are kept. `None` when the document isn't
+/// laid out in a way that can be edited in place, e.g. flow-style mappings.
+pub fn set_value(content: &str, keys: &[&str], value: &YamlValue) -> Option<String> {
+    if keys.is_empty() {
+        return None;
+    }
+    let rendered = render(value)?;
+    let mut lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
+
+    // Skip a leading document marker; several documents in one file aren't supported
+    let mut start = 0;
+    if let Some(first) = lines.iter().position(|l| !is_ignorable(l)) {
+        if lines[first].trim_end() == "---" {
+            start = first + 1;
+        }
+    }
+    if lines[start..].iter().any(|l| l.starts_with("---") || l.starts_with("...") || l.starts_with('\t')) {
+        return None;
+    }
+
+    let end = lines.len();
+    set_in(&mut lines, start, end, None, keys, &rendered)?;
+
+    let line_ending = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut result = lines.join(line_ending);
+    if content.ends_with('\n') || content.is_empty() {
+        result.push_str(line_ending);
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(content: &str, keys: &[&str], value: YamlValue) -> String {
+        set_value(content, keys, &value).unwrap()
+    }
+
+    fn text(value: &str) -> YamlValue {
+        YamlValue::String(value.to_string())
+    }
+
+    #[test]
+    fn replaces_a_value_and_keeps_comments() {
+        let content = "# Settings\nspeed: 1 # units per second\nname: player\n";
+        let updated = set(content, &["speed"], YamlValue::from(2));
+        assert_eq!(updated, "# Settings\nspeed: 2 # units per second\nname: player\n");
+    }
+
+    #[test]
+    fn comments_after_escaped_quotes() {
+        let content = "greeting: \"5\\\" # not a comment\" # a comment\n";
+        let (value, comment) = split_comment(content["greeting:".len()..].trim_end());
+        assert_eq!(value, "\"5\\\" # not a comment\"");
+        assert_eq!(comment, " # a comment");
+
+        let updated = set(content, &["greeting"], text("bye"));
+        assert_eq!(updated, "greeting: bye # a comment\n");
+    }
+
+    #[test]
+    fn comments_after_single_quoted_values() {
+        let (value, comment) = split_comment(" 'it''s # here' # there");
+        assert_eq!(value, "'it''s # here'");
+        assert_eq!(comment, " # there");
+        assert_eq!(split_comment(" a#b"), ("a#b", ""));
+    }
+
+    #[test]
+    fn finds_quoted_keys() {
+        let content = "\"key: with colon\": 1\n'other': 2\n";
+        let updated = set(content, &["key: with colon"], YamlValue::from(3));
+        assert_eq!(updated, "\"key: with colon\": 3\n'other': 2\n");
+        assert_eq!(set(content, &["other"], YamlValue::from(4)), "\"key: with colon\": 1\n'other': 4\n");
+    }
+
+    #[test]
+    fn replaces_a_block_scalar() {
+        let content = "motd: |\n  line one\n  line two\nafter: true\n";
+        let updated = set(content, &["motd"], text("single"));
+        assert_eq!(updated, "motd: single\nafter: true\n");
+
+        let multi_line = set(content, &["motd"], text("a\nb"));
+        assert_eq!(multi_line, "motd: \"a\\nb\"\nafter: true\n");
+    }
+
+    #[test]
+    fn sets_nested_keys() {
+        let content = "player:\n  speed: 1\n  jump: 2\nworld: {}\n";
+        assert_eq!(
+            set(content, &["player", "jump"], YamlValue::from(5)),
+            "player:\n  speed: 1\n  jump: 5\nworld: {}\n"
+        );
+    }
+
+    #[test]
+    fn adds_a_nested_key_to_an_existing_mapping() {
+        let content = "player:\n  speed: 1\n  items:\n  - sword\nworld: 2\n";
+        let updated = set(content, &["player", "health"], YamlValue::from(100));
+        assert_eq!(updated, "player:\n  speed: 1\n  items:\n  - sword\n  health: 100\nworld: 2\n");
+
+        let deeper = set(content, &["player", "stats", "armor"], YamlValue::from(3));
+        assert_eq!(
+            deeper,
+            "player:\n  speed: 1\n  items:\n  - sword\n  stats:\n    armor: 3\nworld: 2\n"
+        );
+    }
+
+    #[test]
+    fn adds_a_top_level_key() {
+        assert_eq!(set("a: 1\n", &["b"], YamlValue::Bool(true)), "a: 1\nb: true\n");
+        assert_eq!(set("", &["b"], YamlValue::from(1)), "b: 1\n");
+    }
+
+    #[test]
+    fn keeps_crlf_line_endings() {
+        let content = "a: 1\r\nb:\r\n  c: 2\r\n";
+        assert_eq!(set(content, &["b", "c"], YamlValue::from(3)), "a: 1\r\nb:\r\n  c: 3\r\n");
+    }
+
+    #[test]
+    fn skips_a_document_marker() {
+        let content = "---\na: 1\n";
+        assert_eq!(set(content, &["a"], YamlValue::from(2)), "---\na: 2\n");
+        assert!(set_value("a: 1\n---\nb: 2\n", &["a"], &YamlValue::from(2)).is_none());
+    }
+
+    #[test]
+    fn refuses_layouts_it_cannot_edit() {
+        // Flow mappings can't be descended into line by line
+        assert!(set_value("player: {speed: 1}\n", &["player", "speed"], &YamlValue::from(2)).is_none());
+        assert!(set_value("a:\n\tb: 1\n", &["a", "b"], &YamlValue::from(2)).is_none());
+    }
+
+    #[test]
+    fn refuses_keys_after_non_space_whitespace() {
+        // U+3000 is part of the key, not indentation, so `a` isn't in this document
+        assert!(set_value("\u{3000}a: 1\n", &["a"], &YamlValue::from(2)).is_none());
+        assert!(set_value("b: 1\n\u{3000}a: 1\n", &["a"], &YamlValue::from(2)).is_none());
+    }
+}
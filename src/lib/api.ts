@@ -1,7 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
-import type { Mod, InstalledMod, AppStatus, ModConfig, ConfigValue, SilkVersion, ModVersionInfo, AppSettings, ModConfigFile } from './types';
-
-const MODS_BASE_URL = 'https://silk.abstractmelon.net';
+import type { Mod, ModRegistry, InstalledMod, AppStatus, ModConfig, ConfigValue, SilkRelease, ReleaseChannel, ModVersionInfo, AppSettings, ModConfigFile, ConfigSchema, GameInstall, Profile, ProfileStore, ProfileSwitchResult, ImportReport, InstallPlan, ModUpdate, UninstallReport, VerificationReport, BackupInfo, RegistrySource, BepInExRelease, DoorstopInfo, BepInExConfigFile } from './types';
 
 export async function getAppStatus(): Promise<AppStatus> {
   return invoke('get_app_status');
@@ -11,81 +9,142 @@ export async function getAppVersion(): Promise<string> {
   return invoke('get_app_version');
 }
 
-export async function setGamePath(path: string): Promise<AppStatus> {
-  return invoke('set_game_path', { path });
+export async function setGamePath(path: string, name?: string): Promise<AppStatus> {
+  return invoke('set_game_path', { path, name });
+}
+
+// Game Install API
+export async function listGameInstalls(): Promise<GameInstall[]> {
+  return invoke('list_game_installs');
+}
+
+export async function addGameInstall(name: string, path: string): Promise<GameInstall> {
+  return invoke('add_game_install', { name, path });
 }
 
-export async function fetchMods(): Promise<Mod[]> {
+export async function removeGameInstall(id: string): Promise<void> {
+  return invoke('remove_game_install', { id });
+}
+
+export async function setActiveGameInstall(id: string): Promise<AppStatus> {
+  return invoke('set_active_game_install', { id });
+}
+
+export async function fetchMods(): Promise<ModRegistry> {
   return invoke('fetch_mods');
 }
 
-export async function installSilk(gamePath: string): Promise<void> {
-  return invoke('install_silk', { gamePath });
+export async function listRegistrySources(): Promise<RegistrySource[]> {
+  return invoke('list_registry_sources');
 }
 
-export async function uninstallSilk(gamePath: string): Promise<void> {
+export async function installSilk(gamePath: string | null, operationId?: string): Promise<void> {
+  return invoke('install_silk', { gamePath, operationId });
+}
+
+export async function uninstallSilk(gamePath: string | null): Promise<UninstallReport> {
   return invoke('uninstall_silk', { gamePath });
 }
 
-export async function getInstalledMods(modsPath: string): Promise<InstalledMod[]> {
+export async function getInstalledMods(modsPath: string | null): Promise<InstalledMod[]> {
   return invoke('get_installed_mods', { modsPath });
 }
 
-export async function installMod(modInfo: Mod, modsPath: string): Promise<void> {
-  return invoke('install_mod', { modInfo, modsPath });
+export async function installMod(modInfo: Mod, modsPath: string | null, operationId?: string): Promise<void> {
+  return invoke('install_mod', { modInfo, modsPath, operationId });
+}
+
+export async function resolveModInstall(modInfo: Mod, modsPath: string | null): Promise<InstallPlan> {
+  return invoke('resolve_mod_install', { modInfo, modsPath });
+}
+
+export async function installModFromPath(path: string, modsPath: string | null, operationId?: string): Promise<void> {
+  return invoke('install_mod_from_path', { path, modsPath, operationId });
 }
 
-export async function toggleMod(modsPath: string, fileName: string, enable: boolean): Promise<void> {
+export async function installModFromUrl(url: string, modsPath: string | null, operationId?: string): Promise<void> {
+  return invoke('install_mod_from_url', { url, modsPath, operationId });
+}
+
+export async function toggleMod(modsPath: string | null, fileName: string, enable: boolean): Promise<void> {
   return invoke('toggle_mod', { modsPath, fileName, enable });
 }
 
-export async function uninstallMod(modsPath: string, fileName: string): Promise<void> {
+export async function uninstallMod(modsPath: string | null, fileName: string): Promise<UninstallReport> {
   return invoke('uninstall_mod', { modsPath, fileName });
 }
 
+export async function checkModUpdates(modsPath: string | null): Promise<ModUpdate[]> {
+  return invoke('check_mod_updates', { modsPath });
+}
+
+export async function updateMod(modId: string, modsPath: string | null, operationId?: string): Promise<void> {
+  return invoke('update_mod', { modId, modsPath, operationId });
+}
+
+export async function updateAllMods(modsPath: string | null, operationId?: string): Promise<string[]> {
+  return invoke('update_all_mods', { modsPath, operationId });
+}
+
 // Version Management API
-export async function getSilkVersion(gamePath: string): Promise<string> {
+export async function getSilkVersion(gamePath: string | null): Promise<string> {
   return invoke('get_silk_version', { gamePath });
 }
 
-export async function getLatestSilkVersion(): Promise<string> {
-  return invoke('get_latest_silk_version');
+export async function getLatestSilkVersion(channel?: ReleaseChannel): Promise<string> {
+  return invoke('get_latest_silk_version', { channel });
+}
+
+export async function checkForSilkUpdates(gamePath: string | null, channel?: ReleaseChannel): Promise<SilkRelease | null> {
+  return invoke('check_for_silk_updates', { gamePath, channel });
+}
+
+export async function listAvailableSilkVersions(channel?: ReleaseChannel): Promise<string[]> {
+  return invoke('list_available_silk_versions', { channel });
 }
 
-export async function checkForSilkUpdates(gamePath: string): Promise<SilkVersion | null> {
-  return invoke('check_for_silk_updates', { gamePath });
+export async function listSilkReleases(channel?: ReleaseChannel): Promise<SilkRelease[]> {
+  return invoke('list_silk_releases', { channel });
 }
 
-export async function listAvailableSilkVersions(): Promise<string[]> {
-  return invoke('list_available_silk_versions');
+export async function getSilkRelease(version: string): Promise<SilkRelease> {
+  return invoke('get_silk_release', { version });
 }
 
-export async function installSilkVersion(version: string, gamePath: string): Promise<void> {
-  return invoke('install_silk_version', { version, gamePath });
+export async function installSilkVersion(version: string, gamePath: string | null, operationId?: string): Promise<void> {
+  return invoke('install_silk_version', { version, gamePath, operationId });
 }
 
 export async function checkModCompatibility(
-  gamePath: string,
-  modsPath: string,
+  gamePath: string | null,
+  modsPath: string | null,
   modId: string
 ): Promise<boolean> {
   return invoke('check_mod_compatibility', { gamePath, modsPath, modId });
 }
 
 // BepInEx API
-export async function isBepInExInstalled(gamePath: string): Promise<boolean> {
+export async function isBepInExInstalled(gamePath: string | null): Promise<boolean> {
   return invoke('is_bepinex_installed', { gamePath });
 }
 
-export async function getBepInExVersion(gamePath: string): Promise<string> {
+export async function getBepInExVersion(gamePath: string | null): Promise<string> {
   return invoke('get_bepinex_version', { gamePath });
 }
 
-export async function installBepInEx(gamePath: string): Promise<void> {
-  return invoke('install_bepinex', { gamePath });
+export async function getDoorstopInfo(gamePath: string | null): Promise<DoorstopInfo | null> {
+  return invoke('get_doorstop_info', { gamePath });
+}
+
+export async function listBepInExReleases(channel?: ReleaseChannel): Promise<BepInExRelease[]> {
+  return invoke('list_bepinex_releases', { channel });
 }
 
-export async function uninstallBepInEx(gamePath: string): Promise<void> {
+export async function installBepInEx(gamePath: string | null, version?: string, operationId?: string): Promise<void> {
+  return invoke('install_bepinex', { gamePath, version, operationId });
+}
+
+export async function uninstallBepInEx(gamePath: string | null): Promise<UninstallReport> {
   return invoke('uninstall_bepinex', { gamePath });
 }
 
@@ -99,21 +158,21 @@ export async function saveSettings(settings: AppSettings): Promise<void> {
 }
 
 // Game Launch API
-export async function launchGame(gamePath: string): Promise<void> {
+export async function launchGame(gamePath: string | null): Promise<void> {
   return invoke('launch_game', { gamePath });
 }
 
 // Config API
-export async function listModConfigs(gamePath: string): Promise<ModConfigFile[]> {
+export async function listModConfigs(gamePath: string | null): Promise<ModConfigFile[]> {
   return invoke('list_mod_configs', { gamePath });
 }
 
-export async function getModConfig(gamePath: string, modId: string): Promise<ModConfig> {
+export async function getModConfig(gamePath: string | null, modId: string): Promise<ModConfig> {
   return invoke('get_mod_config', { gamePath, modId });
 }
 
 export async function setModConfigValue(
-  gamePath: string,
+  gamePath: string | null,
   modId: string,
   key: string,
   value: ConfigValue
@@ -121,8 +180,109 @@ export async function setModConfigValue(
   return invoke('set_mod_config_value', { gamePath, modId, key, value });
 }
 
-export async function resetModConfig(gamePath: string, modId: string): Promise<void> {
-  return invoke('reset_mod_config', { gamePath, modId });
+export async function resetModConfig(gamePath: string | null, modId: string, key?: string): Promise<void> {
+  return invoke('reset_mod_config', { gamePath, modId, key });
+}
+
+export async function getModConfigSchema(gamePath: string | null, modId: string): Promise<ConfigSchema | null> {
+  return invoke('get_mod_config_schema', { gamePath, modId });
+}
+
+export async function listBepInExConfigs(gamePath: string | null): Promise<BepInExConfigFile[]> {
+  return invoke('list_bepinex_configs', { gamePath });
+}
+
+export async function getBepInExConfig(gamePath: string | null, fileName: string): Promise<BepInExConfigFile> {
+  return invoke('get_bepinex_config', { gamePath, fileName });
+}
+
+export async function setBepInExConfigValue(
+  gamePath: string | null,
+  fileName: string,
+  section: string,
+  key: string,
+  value: string | number | boolean
+): Promise<void> {
+  return invoke('set_bepinex_config_value', { gamePath, fileName, section, key, value });
+}
+
+export async function resetBepInExConfig(
+  gamePath: string | null,
+  fileName: string,
+  section?: string,
+  key?: string
+): Promise<void> {
+  return invoke('reset_bepinex_config', { gamePath, fileName, section, key });
+}
+
+// Profile API
+export async function listProfiles(gamePath: string | null): Promise<ProfileStore> {
+  return invoke('list_profiles', { gamePath });
+}
+
+export async function saveProfile(gamePath: string | null, name: string): Promise<Profile> {
+  return invoke('save_profile', { gamePath, name });
+}
+
+export async function deleteProfile(gamePath: string | null, name: string): Promise<void> {
+  return invoke('delete_profile', { gamePath, name });
+}
+
+export async function applyProfile(gamePath: string | null, name: string): Promise<ProfileSwitchResult> {
+  return invoke('apply_profile', { gamePath, name });
+}
+
+// Backup API
+export async function listBackups(gamePath: string | null): Promise<BackupInfo[]> {
+  return invoke('list_backups', { gamePath });
+}
+
+export async function createBackup(gamePath: string | null, reason?: string): Promise<BackupInfo> {
+  return invoke('create_backup', { gamePath, reason });
+}
+
+export async function restoreBackup(gamePath: string | null, backupId: string): Promise<BackupInfo> {
+  return invoke('restore_backup', { gamePath, backupId });
+}
+
+export async function pruneBackups(gamePath: string | null): Promise<string[]> {
+  return invoke('prune_backups', { gamePath });
+}
+
+export async function deleteBackup(gamePath: string | null, backupId: string): Promise<void> {
+  return invoke('delete_backup', { gamePath, backupId });
+}
+
+// Modpack API
+export async function exportModpack(
+  gamePath: string | null,
+  outputPath: string,
+  name: string,
+  includeConfigs: boolean
+): Promise<void> {
+  return invoke('export_modpack', { gamePath, outputPath, name, includeConfigs });
+}
+
+export async function importModpack(gamePath: string | null, path: string, operationId?: string): Promise<ImportReport> {
+  return invoke('import_modpack', { gamePath, path, operationId });
+}
+
+// Integrity API
+export async function verifyInstallation(gamePath: string | null): Promise<VerificationReport> {
+  return invoke('verify_installation', { gamePath });
+}
+
+export async function repairInstallation(gamePath: string | null, operationId?: string): Promise<VerificationReport> {
+  return invoke('repair', { gamePath, operationId });
+}
+
+// Operation API
+export function newOperationId(): string {
+  return crypto.randomUUID();
+}
+
+export async function cancelOperation(operationId: string): Promise<boolean> {
+  return invoke('cancel_operation', { operationId });
 }
 
 // Logging API
@@ -134,10 +294,13 @@ export async function readLogFile(): Promise<string> {
   return invoke('read_log_file');
 }
 
-export function getModIconUrl(iconPath: string): string {
+export async function getModIcon(iconPath: string, source?: string | null): Promise<string> {
   if (!iconPath) return '/default-mod.png';
-  if (iconPath.startsWith('http')) return iconPath;
-  return `${MODS_BASE_URL}${iconPath}`;
+  try {
+    return await invoke('get_mod_icon', { iconPath, source });
+  } catch {
+    return '/default-mod.png';
+  }
 }
 
 export function formatDate(dateString: string): string {
@@ -7,10 +7,29 @@ export interface Mod {
   fileName: string;
   filePath: string;
   fileSize: number;
+  sha256?: string | null;
   iconPath: string;
   uploadDate: string;
   downloads: number;
   lastDownloaded: string | null;
+  dependencies: ModDependency[];
+  conflicts: ModDependency[];
+  source?: string | null;
+}
+
+export interface ModDependency {
+  id: string;
+  versionRange: string;
+}
+
+export interface InstallPlan {
+  toInstall: Mod[];
+  alreadySatisfied: string[];
+}
+
+export interface ModRegistry {
+  mods: Mod[];
+  staleSince: number | null;
 }
 
 export interface InstalledMod {
@@ -22,12 +41,51 @@ export interface InstalledMod {
   author: string;
   description: string;
   iconPath: string;
+  sourceUrl?: string | null;
+  source?: string | null;
+  origin?: ModOrigin | null;
+  assembly?: AssemblyInfo;
+}
+
+export interface AssemblyInfo {
+  name: string;
+  version: string;
+  informationalVersion: string | null;
+  title: string | null;
+  description: string | null;
+  company: string | null;
+  plugin: PluginInfo | null;
+  silkMod: SilkModInfo | null;
+  references: AssemblyReference[];
+}
+
+export interface PluginInfo {
+  guid: string;
+  name: string;
+  version: string;
 }
 
+export interface SilkModInfo {
+  id: string | null;
+  name: string;
+  authors: string[];
+  version: string | null;
+  silkVersion: string | null;
+}
+
+export interface AssemblyReference {
+  name: string;
+  version: string;
+}
+
+export type ModOrigin = 'registry' | 'file' | 'url';
+
 export interface AppStatus {
   silkInstalled: boolean;
+  silkNeedsRepair: boolean;
   gamePath: string | null;
   modsPath: string | null;
+  activeInstall: GameInstall | null;
 }
 
 export type Tab = 'browse' | 'installed' | 'config' | 'settings';
@@ -44,10 +102,65 @@ export interface ModConfig {
   [key: string]: ConfigValue;
 }
 
+// Download progress types
+export type DownloadPhase = 'downloading' | 'verifying' | 'extracting' | 'done';
+
+export interface DownloadProgress {
+  label: string;
+  phase: DownloadPhase;
+  bytesDone: number;
+  totalBytes: number | null;
+  bytesPerSecond: number;
+}
+
 // Version types
-export interface SilkVersion {
-  version: string;
+export interface ReleaseAsset {
+  name: string;
   downloadUrl: string;
+  size: number;
+  digest: string | null;
+}
+
+export interface SilkRelease {
+  version: string;
+  tag: string;
+  name: string;
+  prerelease: boolean;
+  draft: boolean;
+  publishedAt: string | null;
+  body: string;
+  htmlUrl: string;
+  assets: ReleaseAsset[];
+}
+
+export type DoorstopLoader = 'silk' | 'bepinex' | 'unknown';
+
+export interface DoorstopInfo {
+  owner: DoorstopLoader;
+  version: string | null;
+  targetAssembly: string | null;
+  enabled: boolean;
+  shared: boolean;
+}
+
+export interface BepInExRelease {
+  version: string;
+  tag: string;
+  name: string;
+  prerelease: boolean;
+  publishedAt: string | null;
+  body: string;
+  htmlUrl: string;
+  asset: ReleaseAsset;
+}
+
+export interface ModUpdate {
+  modId: string;
+  name: string;
+  fileName: string;
+  installedVersion: string;
+  latestVersion: string;
+  latest: Mod;
 }
 
 export interface ModVersionInfo {
@@ -61,8 +174,39 @@ export interface ModVersionInfo {
 // Settings types
 export type LaunchMethod = 'steam' | 'executable';
 
+export interface GameInstall {
+  id: string;
+  name: string;
+  path: string;
+}
+
+export type ReleaseChannel = 'stable' | 'prerelease';
+
+export interface BackupRetention {
+  maxBackups: number;
+  maxAgeDays: number;
+}
+
+export type RegistryKind = 'http' | 'local';
+
+export interface RegistrySource {
+  id: string;
+  name: string;
+  kind: RegistryKind;
+  url: string;
+  priority: number;
+  authToken: string | null;
+  enabled: boolean;
+}
+
 export interface AppSettings {
   launchMethod: LaunchMethod;
+  gameInstalls: GameInstall[];
+  activeInstall: string | null;
+  backupRetention: BackupRetention;
+  silkChannel: ReleaseChannel;
+  githubToken: string | null;
+  registrySources: RegistrySource[];
 }
 
 export interface Settings {
@@ -78,10 +222,128 @@ export interface ConfigField {
   type: 'string' | 'number' | 'boolean' | 'array' | 'object';
 }
 
+export type ConfigSchemaType = 'boolean' | 'integer' | 'number' | 'string' | 'array' | 'object';
+
+export interface ConfigSchema {
+  type: ConfigSchemaType | null;
+  title: string | null;
+  description: string | null;
+  default: ConfigValue | null;
+  minimum: number | null;
+  maximum: number | null;
+  enum: ConfigValue[] | null;
+  items: ConfigSchema | null;
+  properties?: Record<string, ConfigSchema>;
+  additionalProperties: boolean;
+}
+
 export interface ModConfigFile {
   modId: string;
   modName: string;
   config: ModConfig;
+  schema: ConfigSchema | null;
+}
+
+export type BepInExValueKind = 'boolean' | 'integer' | 'float' | 'string' | 'enum' | 'flags';
+
+export interface BepInExConfigEntry {
+  key: string;
+  value: string;
+  description: string | null;
+  settingType: string | null;
+  kind: BepInExValueKind;
+  defaultValue: string | null;
+  acceptableValues: string[];
+  range: { min: number; max: number } | null;
+}
+
+export interface BepInExConfigSection {
+  name: string;
+  entries: BepInExConfigEntry[];
+}
+
+export interface BepInExConfigFile {
+  fileName: string;
+  pluginName: string | null;
+  pluginVersion: string | null;
+  pluginGuid: string | null;
+  sections: BepInExConfigSection[];
+}
+
+// Profile types
+export interface ProfileMod {
+  id: string;
+  name: string;
+  fileName: string;
+  version: string;
+  enabled: boolean;
+}
+
+export interface Profile {
+  name: string;
+  mods: ProfileMod[];
+  configs: Record<string, string>;
+}
+
+export interface ProfileStore {
+  activeProfile: string | null;
+  profiles: Profile[];
+}
+
+export interface ProfileSwitchResult {
+  enabled: string[];
+  disabled: string[];
+  missing: string[];
+  versionMismatches: string[];
+  configsRestored: string[];
+}
+
+// Modpack types
+export interface UnmatchedEntry {
+  id: string;
+  name: string;
+  version: string;
+  reason: string;
+}
+
+export interface ImportReport {
+  installed: string[];
+  alreadyInstalled: string[];
+  versionChanged: string[];
+  unmatched: UnmatchedEntry[];
+  configsImported: string[];
+}
+
+export interface UninstallReport {
+  removed: string[];
+  modified: string[];
+  missing: string[];
+  shared: string[];
+}
+
+export interface ComponentStatus {
+  component: string;
+  name: string;
+  version: string;
+  tracked: boolean;
+  missing: string[];
+  modified: string[];
+  extra: string[];
+}
+
+export interface VerificationReport {
+  silk: ComponentStatus | null;
+  bepinex: ComponentStatus | null;
+  mods: ComponentStatus[];
+}
+
+export interface BackupInfo {
+  id: string;
+  createdAt: number;
+  reason: string;
+  silkVersion: string | null;
+  modCount: number;
+  size: number;
 }
 
 export interface ConfigError {